@@ -1,9 +1,24 @@
+use super::config_file::ConfigFile;
+use super::error::CggError;
 use super::rrdtool;
 use anyhow::{anyhow, Context};
-use rrdtool::common::Plugins;
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use log::warn;
+use rrdtool::common::{Plugins, Rrdtool};
+use rrdtool::compare_hosts::CompareHosts;
+use rrdtool::consolidation_function::ConsolidationFunction;
+use rrdtool::graph_arguments::validate_color;
+use rrdtool::graph_element::GraphElement;
+use rrdtool::hrule::Hrule;
+use rrdtool::image_format::ImageFormat;
+use rrdtool::legend::{LegendDirection, LegendPosition};
+use rrdtool::palette::PalettePreset;
+use rrdtool::right_axis::RightAxis;
+use rrdtool::rrd_file::is_rrd_file;
+use rrdtool::transfer::Transfer;
 use std::any::Any;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::SystemTime;
 
@@ -14,16 +29,114 @@ pub struct Config<'a> {
     ///
     /// Path to directory with collectd results
     pub input_dir: &'a Path,
+    /// Paths to directories with collectd results of all hosts, the first one is `input_dir`
+    pub inputs: Vec<PathBuf>,
     /// Output filename
     pub output_filename: &'a str,
+    /// Format of generated graphs, detected from output filename if None
+    pub image_format: Option<ImageFormat>,
+    /// Export data to JSON instead of drawing graphs
+    pub export: bool,
     /// Width of the generated graph
     pub width: u32,
     /// Height of the generated graph
     pub height: u32,
+    /// Width and height of graphs of particular plugins
+    pub plugin_sizes: HashMap<Plugins, (u32, u32)>,
     /// Start timestamp
     pub start: u64,
     /// End timestamp
     pub end: u64,
+    /// Timestamp used as current time
+    pub now: u64,
+    /// Maximum length of gap in seconds filled with the last known value
+    pub nan_fill: Option<u64>,
+    /// Consolidation function of data read from rrd files
+    pub cf: ConsolidationFunction,
+    /// Data source read from rrd files instead of value
+    pub ds: Option<&'a str>,
+    /// Function reducing data when there are more data points than pixels
+    pub reduce: Option<ConsolidationFunction>,
+    /// Draw only the lines without any decorations
+    pub sparkline: bool,
+    /// Program copying result from remote target
+    pub transfer: Transfer,
+    /// Number of retries of copying result from remote target
+    pub scp_retries: u32,
+    /// chrono format of time range shown in graph titles
+    pub title_time_format: Option<&'a str>,
+    /// Add overview graph over whole data history
+    pub overview: bool,
+    /// Maximum age in seconds of the newest data point
+    pub max_data_age: Option<u64>,
+    /// Log step of each rrd file
+    pub show_rrd_step: bool,
+    /// Number of legend entries in one line
+    pub legend_columns: Option<u32>,
+    /// Hide legend box, labels are kept for exported data
+    pub no_legend: bool,
+    /// Placement of legend relative to the graph
+    pub legend_position: Option<LegendPosition>,
+    /// Order of legend entries
+    pub legend_direction: Option<LegendDirection>,
+    /// Text printed at the bottom of graphs
+    pub watermark: Option<String>,
+    /// Step of graphed data in seconds, chosen by rrdtool if None
+    pub step: Option<u64>,
+    /// Graph element overriding the one chosen by plugins
+    pub style: Option<GraphElement>,
+    /// Print maximum, average and last value of each line in legend
+    pub statistics: bool,
+    /// Draw maximum of each line as translucent area behind it
+    pub show_max: bool,
+    /// Window in seconds of moving average drawn over each line
+    pub trend: Option<u64>,
+    /// Draw all plugins on one graph
+    pub combine: bool,
+    /// Horizontal lines drawn on all graphs
+    pub hrules: Vec<Hrule>,
+    /// Lower limit of vertical axis
+    pub y_min: Option<f64>,
+    /// Upper limit of vertical axis
+    pub y_max: Option<f64>,
+    /// Don't expand vertical axis beyond its limits
+    pub rigid: bool,
+    /// Second vertical axis at the right side of graphs
+    pub right_axis: Option<RightAxis>,
+    /// Label of the right vertical axis
+    pub right_axis_label: Option<&'a str>,
+    /// Plugins drawn against the right axis
+    pub right_axis_plugins: Vec<Plugins>,
+    /// Label of vertical axis of all graphs
+    pub vertical_label: Option<&'a str>,
+    /// Colors of lines read from palette file or of chosen preset
+    pub palette: Option<Vec<String>>,
+    /// Title of all graphs
+    pub title: Option<&'a str>,
+    /// Titles of graphs of particular plugins
+    pub plugin_titles: HashMap<Plugins, &'a str>,
+    /// Title graphs with name of the host, unless title is given
+    pub auto_title: bool,
+    /// Draw one metric of multiple hosts instead of running plugins
+    pub compare_hosts: Option<CompareHosts>,
+    /// Path to JSON manifest describing the run
+    pub manifest: Option<&'a str>,
+    /// Print commands instead of executing them
+    pub dry_run: bool,
+    /// Write command generating each graph to file next to it
+    pub embed_command: bool,
+    /// Log commands before executing them
+    pub print_command: bool,
+    /// Overwrite existing output files
+    pub force: bool,
+    /// rrdtool binary, found on PATH unless it's a path
+    pub rrdtool_bin: &'a str,
+    /// Identity file used to connect to remote target
+    pub ssh_key: Option<&'a str>,
+    /// Share one SSH connection by all commands executed on remote target
+    pub ssh_multiplex: bool,
+    /// Fail instead of executing commands on remote target
+    pub offline: bool,
     /// ---------------
     /// Plugins
     /// ---------------
@@ -34,94 +147,589 @@ pub struct Config<'a> {
 pub struct PluginsConfig {
     /// Map of plugins data
     pub data: HashMap<Plugins, Box<dyn Any + 'static>>,
+    /// Order in which plugins are run, as given in command line
+    pub order: Vec<Plugins>,
+}
+
+impl PluginsConfig {
+    /// Returns plugins with data in order in which they are run. Plugins missing
+    /// in [`PluginsConfig::order`] are run after the others, sorted by name.
+    pub fn ordered(&self) -> Vec<Plugins> {
+        let mut unordered = self
+            .data
+            .keys()
+            .filter(|plugin| !self.order.contains(plugin))
+            .copied()
+            .collect::<Vec<Plugins>>();
+        unordered.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        self.order
+            .iter()
+            .filter(|plugin| self.data.contains_key(plugin))
+            .copied()
+            .chain(unordered)
+            .collect()
+    }
 }
 
 impl<'a> Config<'a> {
-    pub fn new(cli: &'a clap::ArgMatches) -> anyhow::Result<Config<'a>> {
-        let input: &str;
-        if let Some(input_dir) = cli.value_of("input") {
-            input = input_dir;
-        } else {
-            unreachable!()
+    /// Default width of the graph in sparkline mode
+    pub const SPARKLINE_WIDTH: u32 = 120;
+    /// Default height of the graph in sparkline mode
+    pub const SPARKLINE_HEIGHT: u32 = 30;
+
+    /// Parse and validate command line arguments
+    pub fn new(cli: &'a clap::ArgMatches) -> Result<Config<'a>, CggError> {
+        Ok(Config::build(cli, None)?)
+    }
+
+    /// Parse and validate command line arguments merged with options read from
+    /// configuration file given with --config. Options given in command line take
+    /// precedence over the file, which takes precedence over defaults of command line.
+    pub fn from_file(
+        cli: &'a clap::ArgMatches,
+        file: &'a ConfigFile,
+    ) -> Result<Config<'a>, CggError> {
+        Ok(Config::build(cli, Some(file))?)
+    }
+
+    /// Returns input given in command line, or input of configuration file if not given
+    pub fn get_input(cli: &'a clap::ArgMatches, file: Option<&'a ConfigFile>) -> Option<&'a str> {
+        cli.value_of("input")
+            .or_else(|| file.and_then(|file| file.input.as_deref()))
+    }
+
+    fn build(
+        cli: &'a clap::ArgMatches,
+        file: Option<&'a ConfigFile>,
+    ) -> anyhow::Result<Config<'a>> {
+        let inputs = match (
+            cli.values_of("input"),
+            file.and_then(|file| file.input.as_deref()),
+        ) {
+            (Some(inputs), _) => inputs.flat_map(Config::split_input).collect::<Vec<&str>>(),
+            (None, Some(input)) => Config::split_input(input),
+            (None, None) => anyhow::bail!("Missing --input parameter"),
+        };
+
+        let input = match inputs.first() {
+            Some(input) => *input,
+            None => anyhow::bail!("Missing --input parameter"),
+        };
+
+        // Single rrd file is drawn without plugins, so it can't be drawn with other inputs
+        if inputs.len() > 1 && inputs.iter().any(|input| is_rrd_file(Path::new(input))) {
+            anyhow::bail!("Input .rrd file can't be combined with other inputs");
         }
 
         let output: &str;
         if let Some(output_filename) = cli.value_of("out") {
             output = output_filename;
         } else {
-            unreachable!()
+            anyhow::bail!("Missing --out parameter")
         }
 
+        let output = Config::prefer_file(
+            cli,
+            "out",
+            output,
+            file.and_then(|file| file.output.as_deref()),
+        );
+
+        let image_format = match cli.value_of("format") {
+            Some(format) => Some(
+                ImageFormat::from_str(format)
+                    .map_err(|_| anyhow!("Cannot parse format argument {}", format))?,
+            ),
+            None => None,
+        };
+
+        let export = cli.is_present("export");
+
         let width: u32;
         if let Some(w) = cli.value_of("width") {
             width = w.parse::<u32>().context("Cannot parse width argument")?;
         } else {
-            unreachable!()
+            anyhow::bail!("Missing --width parameter")
         }
 
         let height: u32;
         if let Some(h) = cli.value_of("height") {
             height = h.parse::<u32>().context("Cannot parse height argument")?;
         } else {
-            unreachable!()
+            anyhow::bail!("Missing --height parameter")
+        }
+
+        let sparkline = cli.is_present("sparkline");
+
+        // Sparkline should be small, unless user explicitly wants otherwise
+        let width = match sparkline && cli.occurrences_of("width") == 0 {
+            true => Config::SPARKLINE_WIDTH,
+            false => width,
+        };
+
+        let height = match sparkline && cli.occurrences_of("height") == 0 {
+            true => Config::SPARKLINE_HEIGHT,
+            false => height,
+        };
+
+        let width = Config::prefer_file(cli, "width", width, file.and_then(|file| file.width));
+        let height = Config::prefer_file(cli, "height", height, file.and_then(|file| file.height));
+
+        let mut plugin_sizes = HashMap::new();
+        for plugin in &[
+            Plugins::Processes,
+            Plugins::Memory,
+            Plugins::Cpu,
+            Plugins::Df,
+        ] {
+            if let Some(size) = cli.value_of(format!("{}_size", plugin.as_str()).as_str()) {
+                plugin_sizes.insert(
+                    *plugin,
+                    Config::parse_size(size)
+                        .context(format!("Cannot parse {}-size argument", plugin.as_str()))?,
+                );
+            }
         }
 
-        let (start, end) = match cli.value_of("timespan") {
-            Some(timespan) => Config::parse_timespan(String::from(timespan))
-                .context(format!("Cannot parse timespan {}", timespan))?,
-            None => (
-                cli.value_of("start")
-                    .context("Missing --start parameter")?
+        let now = Config::get_now(cli).context("Cannot get current time")?;
+
+        // Timespan of the file conflicts with range given in command line
+        let timespan = cli.value_of("timespan").or_else(|| {
+            match ["start", "end", "since", "until"]
+                .iter()
+                .all(|name| cli.occurrences_of(*name) == 0)
+            {
+                true => file.and_then(|file| file.timespan.as_deref()),
+                false => None,
+            }
+        });
+
+        let (start, end) = match timespan {
+            Some(timespan) => {
+                Config::parse_timespan(String::from(timespan), now).map_err(|err| {
+                    CggError::InvalidTimespan {
+                        timespan: String::from(timespan),
+                        reason: format!("{:#}", err),
+                    }
+                })?
+            }
+            None => {
+                let start = cli
+                    .value_of("start")
+                    .or_else(|| cli.value_of("since"))
+                    .context("Missing --start parameter")?;
+                // Without end data is drawn until now
+                let end = cli
+                    .value_of("end")
+                    .or_else(|| cli.value_of("until"))
+                    .unwrap_or("now");
+
+                let start = Config::parse_time_point(start, now).map_err(|err| {
+                    CggError::InvalidTimespan {
+                        timespan: String::from(start),
+                        reason: format!("Cannot parse start argument: {:#}", err),
+                    }
+                })?;
+                let end = Config::parse_time_point(end, now).map_err(|err| {
+                    CggError::InvalidTimespan {
+                        timespan: String::from(end),
+                        reason: format!("Cannot parse end argument: {:#}", err),
+                    }
+                })?;
+
+                if start >= end {
+                    anyhow::bail!("Start {} is not before end {}", start, end);
+                }
+
+                (start, end)
+            }
+        };
+
+        let nan_fill = match cli.value_of("nan_fill") {
+            Some(nan_fill) => Some(
+                nan_fill
                     .parse::<u64>()
-                    .context("Cannot parse start argument")?,
-                cli.value_of("end")
-                    .context("Missing --end parameter")?
+                    .context("Cannot parse nan-fill argument")?,
+            ),
+            None => None,
+        };
+
+        let cf = match cli.value_of("cf") {
+            Some(cf) => ConsolidationFunction::from_str(cf)
+                .map_err(|_| anyhow!("Cannot parse cf argument {}", cf))?,
+            None => ConsolidationFunction::Average,
+        };
+
+        let ds = cli.value_of("ds");
+
+        let reduce = match cli.value_of("reduce") {
+            Some(reduce) => Some(
+                ConsolidationFunction::from_str(reduce)
+                    .map_err(|_| anyhow!("Cannot parse reduce argument {}", reduce))?,
+            ),
+            None => None,
+        };
+
+        let scp_retries = match cli.value_of("scp_retries") {
+            Some(retries) => retries
+                .parse::<u32>()
+                .context("Cannot parse scp-retries argument")?,
+            None => anyhow::bail!("Missing --scp-retries parameter"),
+        };
+
+        let transfer = match cli.value_of("transfer") {
+            Some(transfer) => Transfer::from_str(transfer)
+                .map_err(|_| anyhow!("Cannot parse transfer argument {}", transfer))?,
+            None => Transfer::Scp,
+        };
+
+        let title_time_format = cli.value_of("title_time_format");
+
+        let overview = cli.is_present("with_overview");
+
+        let max_data_age = match cli.value_of("max_data_age") {
+            Some(max_data_age) => Some(
+                max_data_age
                     .parse::<u64>()
-                    .context("Cannot parse start argument")?,
+                    .context("Cannot parse max-data-age argument")?,
+            ),
+            None => None,
+        };
+
+        let show_rrd_step = cli.is_present("show_rrd_step");
+
+        let legend_columns = match cli.value_of("legend_columns") {
+            Some(columns) => match columns
+                .parse::<u32>()
+                .context("Cannot parse legend-columns argument")?
+            {
+                0 => anyhow::bail!("Number of legend columns must be positive"),
+                columns => Some(columns),
+            },
+            None => None,
+        };
+
+        let no_legend = cli.is_present("no_legend");
+
+        let legend_position = match cli.value_of("legend_position") {
+            Some(position) => Some(
+                LegendPosition::from_str(position)
+                    .map_err(|_| anyhow!("Cannot parse legend-position argument {}", position))?,
             ),
+            None => None,
+        };
+
+        let legend_direction = match cli.value_of("legend_direction") {
+            Some(direction) => Some(
+                LegendDirection::from_str(direction)
+                    .map_err(|_| anyhow!("Cannot parse legend-direction argument {}", direction))?,
+            ),
+            None => None,
+        };
+
+        let step = match cli.value_of("step") {
+            Some(step) => match step.parse::<u64>().context("Cannot parse step argument")? {
+                0 => anyhow::bail!("Step must be positive"),
+                step => Some(step),
+            },
+            None => None,
+        };
+
+        // Without text, watermark tells when graphs were generated
+        let watermark = match (cli.is_present("watermark"), cli.value_of("watermark")) {
+            (_, Some(watermark)) => Some(String::from(watermark)),
+            (true, None) => Some(match Local.timestamp_opt(now as i64, 0).single() {
+                Some(time) => format!("generated by cgg on {}", time.format("%Y-%m-%d %H:%M")),
+                None => String::from("generated by cgg"),
+            }),
+            (false, None) => None,
+        };
+
+        let style = match cli.value_of("style") {
+            Some(style) => Some(
+                GraphElement::from_str(style)
+                    .map_err(|_| anyhow!("Cannot parse style argument {}", style))?,
+            ),
+            None => None,
+        };
+
+        let statistics = cli.is_present("stats");
+
+        let show_max = cli.is_present("show_max");
+
+        let trend = match cli.value_of("trend") {
+            Some(trend) => match trend
+                .parse::<u64>()
+                .context("Cannot parse trend argument")?
+            {
+                0 => anyhow::bail!("Trend window must be greater than 0"),
+                trend => Some(trend),
+            },
+            None => None,
+        };
+
+        let combine = cli.is_present("combine");
+
+        let y_min = match cli.value_of("y_min") {
+            Some(y_min) => Some(
+                y_min
+                    .parse::<f64>()
+                    .context("Cannot parse y-min argument")?,
+            ),
+            None => None,
+        };
+
+        let y_max = match cli.value_of("y_max") {
+            Some(y_max) => Some(
+                y_max
+                    .parse::<f64>()
+                    .context("Cannot parse y-max argument")?,
+            ),
+            None => None,
+        };
+
+        if let (Some(y_min), Some(y_max)) = (y_min, y_max) {
+            if y_min >= y_max {
+                anyhow::bail!("--y-min {} must be lower than --y-max {}", y_min, y_max);
+            }
+        }
+
+        let rigid = cli.is_present("rigid");
+
+        let right_axis = match cli.value_of("right_axis") {
+            Some(axis) => {
+                Some(RightAxis::from_str(axis).context("Cannot parse right-axis argument")?)
+            }
+            None => None,
+        };
+
+        let right_axis_label = cli.value_of("right_axis_label");
+
+        let right_axis_plugins = match cli.value_of("right_axis_plugins") {
+            Some(plugins) => Config::get_vec_of_type_from_cli::<Plugins>(plugins)
+                .context(format!("Cannot parse right-axis-plugins {}", plugins))?,
+            None => Vec::new(),
+        };
+
+        let hrules = match cli.values_of("hrule") {
+            Some(hrules) => hrules
+                .map(Hrule::from_str)
+                .collect::<anyhow::Result<Vec<Hrule>>>()?,
+            None => Vec::new(),
+        };
+
+        let vertical_label = cli.value_of("vertical_label");
+
+        let palette = match cli.value_of("palette") {
+            Some(path) => Some(
+                Config::read_palette(Path::new(path))
+                    .context(format!("Cannot read palette {}", path))?,
+            ),
+            None => match cli.value_of("palette_preset") {
+                Some(preset) => Some(
+                    PalettePreset::from_str(preset)
+                        .map_err(|_| anyhow!("Cannot parse palette-preset argument {}", preset))?
+                        .colors(),
+                ),
+                None => None,
+            },
+        };
+
+        let (title, plugin_titles) = match cli.values_of("title") {
+            Some(titles) => Config::parse_titles(titles.collect::<Vec<&str>>())
+                .context("Cannot parse titles")?,
+            None => (None, HashMap::new()),
         };
 
+        let auto_title = cli.is_present("auto_title");
+
         let plugins = match cli.value_of("plugins") {
-            Some(plugins) => Config::get_vec_of_type_from_cli::<Plugins>(plugins).unwrap(),
-            None => unreachable!(),
+            Some(plugins) => Config::get_vec_of_type_from_cli::<Plugins>(plugins)
+                .context(format!("Cannot parse plugins {}", plugins))?,
+            None => anyhow::bail!("Missing --plugins parameter"),
+        };
+
+        let plugins = match file.map(|file| file.plugins.names()) {
+            Some(names) if !names.is_empty() && cli.occurrences_of("plugins") == 0 => names,
+            _ => plugins,
+        };
+
+        let inputs = inputs
+            .into_iter()
+            .map(PathBuf::from)
+            .collect::<Vec<PathBuf>>();
+
+        let compare_hosts = match cli.value_of("compare_hosts") {
+            Some(metric) => {
+                if plugins.len() != 1 {
+                    anyhow::bail!("--compare-hosts requires exactly one plugin");
+                }
+
+                Some(CompareHosts {
+                    inputs: inputs.clone(),
+                    plugin: plugins[0],
+                    metric: String::from(metric),
+                })
+            }
+            None => None,
         };
 
+        let manifest = cli.value_of("manifest");
+
+        // Graph written to standard output isn't a file which manifest could describe
+        if manifest.is_some() && output == Rrdtool::STDOUT {
+            anyhow::bail!("--manifest can't be used with graph written to standard output");
+        }
+
+        let dry_run = cli.is_present("dry_run");
+
+        let embed_command = cli.is_present("embed_command");
+
+        let print_command = cli.is_present("print_command");
+
+        let force = cli.is_present("force");
+
+        let rrdtool_bin = cli.value_of("rrdtool_bin").unwrap_or("rrdtool");
+
+        let ssh_key = cli.value_of("ssh_key");
+
+        let ssh_multiplex = cli.is_present("ssh_multiplex");
+
+        let offline = cli.is_present("offline");
+
         let mut plugins_config = PluginsConfig {
             data: HashMap::new(),
+            order: Vec::new(),
         };
 
         for plugin in plugins.iter() {
+            if !plugins_config.order.contains(plugin) {
+                plugins_config.order.push(*plugin);
+            }
+
             match plugin {
                 Plugins::Memory => plugins_config.data.insert(
                     *plugin,
                     Box::new(
                         Config::get_memory_data(cli, &plugins)
-                            .unwrap()
-                            .context("Failed to get memory data")?,
+                            .context("Failed to get memory data")?
+                            .context("memory plugin selected but memory data is missing")?,
+                    ),
+                ),
+                Plugins::Cpu => plugins_config.data.insert(
+                    *plugin,
+                    Box::new(
+                        Config::get_cpu_data(cli, &plugins)
+                            .context("Failed to get CPU data")?
+                            .context("cpu plugin selected but CPU data is missing")?,
+                    ),
+                ),
+                Plugins::Df => plugins_config.data.insert(
+                    *plugin,
+                    Box::new(
+                        Config::get_df_data(cli, &plugins)
+                            .context("Failed to get df data")?
+                            .context("df plugin selected but df data is missing")?,
                     ),
                 ),
                 Plugins::Processes => plugins_config.data.insert(
                     *plugin,
                     Box::new(
                         Config::get_processes_data(cli, &plugins)
-                            .unwrap()
-                            .context("Failed to get processes data")?,
+                            .context("Failed to get processes data")?
+                            .context("processes plugin selected but processes data is missing")?,
                     ),
                 ),
             };
         }
 
+        if let Some(file) = file {
+            file.plugins
+                .merge(cli, &mut plugins_config)
+                .context("Cannot merge plugins of config file")?;
+        }
+
         Ok(Config {
             input_dir: Path::new(input),
+            inputs,
             output_filename: output,
+            image_format,
+            export,
             width,
             height,
+            plugin_sizes,
             start,
             end,
+            now,
+            nan_fill,
+            cf,
+            ds,
+            reduce,
+            sparkline,
+            transfer,
+            scp_retries,
+            title_time_format,
+            overview,
+            max_data_age,
+            show_rrd_step,
+            legend_columns,
+            no_legend,
+            legend_position,
+            legend_direction,
+            watermark,
+            step,
+            style,
+            statistics,
+            show_max,
+            trend,
+            combine,
+            hrules,
+            y_min,
+            y_max,
+            rigid,
+            right_axis,
+            right_axis_label,
+            right_axis_plugins,
+            vertical_label,
+            palette,
+            title,
+            plugin_titles,
+            auto_title,
+            compare_hosts,
+            manifest,
+            dry_run,
+            embed_command,
+            print_command,
+            force,
+            rrdtool_bin,
+            ssh_key,
+            ssh_multiplex,
+            offline,
             plugins_config,
         })
     }
 
+    /// Returns value read from configuration file, unless the option is given in command line
+    fn prefer_file<T>(cli: &clap::ArgMatches, name: &str, value: T, file_value: Option<T>) -> T {
+        match (cli.occurrences_of(name), file_value) {
+            (0, Some(file_value)) => file_value,
+            _ => value,
+        }
+    }
+
+    /// Returns timestamp given with --now or CGG_NOW, so relative timespans are reproducible.
+    /// Real time is used by default.
+    fn get_now(cli: &clap::ArgMatches) -> anyhow::Result<u64> {
+        match cli.value_of("now") {
+            Some(now) => now.parse::<u64>().context("Cannot parse now argument"),
+            None => Ok(SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .context("System time is before UNIX epoch")?
+                .as_secs()),
+        }
+    }
+
     /// Parsing descriptive timespan to UNIX timestamp, e.g.:
     /// - last 5 minutes
     /// - last 20 hours
@@ -129,7 +737,12 @@ impl<'a> Config<'a> {
     /// - last minute
     /// - last 30 seconds
     /// - last day
-    fn parse_timespan(mut timespan: String) -> anyhow::Result<(u64, u64)> {
+    /// - last 1.5 hours
+    /// - last 4h, 4h, with shorthand units s, m, h, d and w
+    /// - 2021-03-01..2021-03-08
+    /// - 2021-03-01 12:00..now
+    /// - today, yesterday, this week, at local midnight boundaries
+    fn parse_timespan(mut timespan: String, now: u64) -> anyhow::Result<(u64, u64)> {
         if !timespan.is_ascii() {
             return Err(anyhow!(format!(
                 "Timespan contains non ASCII characters: {}",
@@ -139,9 +752,35 @@ impl<'a> Config<'a> {
 
         timespan.make_ascii_lowercase();
 
+        if let Some(index) = timespan.find("..") {
+            let start = Config::parse_date(timespan[..index].trim(), now)?;
+            let end = Config::parse_date(timespan[index + 2..].trim(), now)?;
+
+            if start >= end {
+                anyhow::bail!("Start of timespan is not before its end: {}", timespan);
+            }
+
+            return Ok((start, end));
+        }
+
+        if let Some(range) = Config::parse_calendar_timespan(&timespan, now)? {
+            return Ok(range);
+        }
+
+        // Bare shorthand, e.g. 4h, is the same as last 4h
+        if Config::split_shorthand(&timespan).is_some() {
+            timespan.insert_str(0, "last ");
+        }
+
         match timespan.starts_with("last ") {
             true => {
-                let words: Vec<&str> = timespan.split(' ').collect();
+                let mut words: Vec<&str> = timespan.split(' ').collect();
+
+                // Unit attached to number, e.g. last 4h, is split as in last 4 h
+                if let Some((number, unit)) = words.get(1).and_then(|w| Config::split_shorthand(w))
+                {
+                    words.splice(1..2, vec![number, unit]);
+                }
 
                 if words.len() < 2 {
                     return Err(anyhow!(format!(
@@ -150,22 +789,27 @@ impl<'a> Config<'a> {
                     )));
                 }
 
-                // String may or may not contain number in second word, e.g. last 5 minutes or last minute
+                // String may or may not contain number in second word, e.g. last 5 minutes or last minute,
+                // the number may be fractional, e.g. last 1.5 hours
                 let mut index = 1;
-                let number = match u64::from_str(words[index]) {
+                let number = match f64::from_str(words[index]) {
                     Ok(number) => {
                         index += 1;
                         number
                     }
-                    Err(_) => 1,
+                    Err(_) => 1.0,
                 };
 
-                let multiplier = match words[index] {
-                    "second" | "seconds" => 1,
-                    "minute" | "minutes" => 60,
-                    "hour" | "hours" => 3600,
-                    "day" | "days" => 86400,
-                    "week" | "weeks" => 604800,
+                if !number.is_finite() || number < 0.0 {
+                    anyhow::bail!("Expected positive number in timespan: {}", timespan);
+                }
+
+                let multiplier = match words.get(index).copied().unwrap_or_default() {
+                    "second" | "seconds" | "s" => 1,
+                    "minute" | "minutes" | "m" => 60,
+                    "hour" | "hours" | "h" => 3600,
+                    "day" | "days" | "d" => 86400,
+                    "week" | "weeks" | "w" => 604800,
                     "month" | "months" => 2592000,
                     "year" | "years" => 31536000,
                     _ => {
@@ -176,12 +820,26 @@ impl<'a> Config<'a> {
                     }
                 };
 
-                let now = SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
+                let span = number * multiplier as f64;
+
+                if span >= u64::MAX as f64 {
+                    anyhow::bail!("Timespan is too long: {}", timespan);
+                }
 
-                Ok((now - (number * multiplier), now))
+                // Fraction of a second is dropped
+                let span = span as u64;
+
+                if span == 0 {
+                    anyhow::bail!("Timespan must not be empty: {}", timespan);
+                }
+
+                match now.checked_sub(span) {
+                    Some(start) => Ok((start, now)),
+                    None => {
+                        warn!("Timespan {} starts before epoch, using epoch", timespan);
+                        Ok((0, now))
+                    }
+                }
             }
             false => Err(anyhow!(format!(
                 "Unrecognized string in timespan: {}",
@@ -190,29 +848,222 @@ impl<'a> Config<'a> {
         }
     }
 
+    /// Split number with attached shorthand unit, e.g. 4h or 1.5d, into number and unit.
+    /// Returns None for other words.
+    fn split_shorthand(word: &str) -> Option<(&str, &str)> {
+        if word.len() < 2 || !word.is_ascii() {
+            return None;
+        }
+
+        let (number, unit) = word.split_at(word.len() - 1);
+
+        match unit {
+            "s" | "m" | "h" | "d" | "w" if f64::from_str(number).is_ok() => Some((number, unit)),
+            _ => None,
+        }
+    }
+
+    /// Parse single point in time to UNIX timestamp, given as:
+    /// - UNIX timestamp, e.g. 1600000000
+    /// - date, e.g. 2021-03-01 or 2021-03-01 12:00, or now
+    /// - relative timespan, beginning of which is used, e.g. last 2 days or yesterday
+    /// - time ago, e.g. 2 hours ago, same as beginning of last 2 hours
+    fn parse_time_point(point: &str, now: u64) -> anyhow::Result<u64> {
+        let point = point.trim();
+
+        if let Ok(timestamp) = point.parse::<u64>() {
+            return Ok(timestamp);
+        }
+
+        let lowercase = point.to_ascii_lowercase();
+
+        if let Ok(timestamp) = Config::parse_date(&lowercase, now) {
+            return Ok(timestamp);
+        }
+
+        let timespan = match lowercase.strip_suffix(" ago") {
+            Some(ago) => format!("last {}", ago.trim()),
+            None => String::from(point),
+        };
+
+        match Config::parse_timespan(timespan, now) {
+            Ok((start, _)) => Ok(start),
+            Err(_) => anyhow::bail!(
+                "Expected UNIX timestamp, date or relative time, e.g. last 2 days or 2 hours ago, got {}",
+                point
+            ),
+        }
+    }
+
+    /// Parse calendar timespan, starting at local midnight:
+    /// - today - from midnight to now
+    /// - yesterday - previous calendar day, from midnight to midnight
+    /// - this week - from Monday midnight to now
+    ///
+    /// Returns None for other timespans.
+    fn parse_calendar_timespan(timespan: &str, now: u64) -> anyhow::Result<Option<(u64, u64)>> {
+        let today = Local
+            .timestamp_opt(now as i64, 0)
+            .single()
+            .context(format!("Invalid current time {}", now))?
+            .naive_local()
+            .date();
+
+        let midnight = |date: NaiveDate| match date.and_hms_opt(0, 0, 0) {
+            Some(datetime) => Config::get_local_timestamp(datetime),
+            None => anyhow::bail!("Invalid date {}", date),
+        };
+
+        Ok(match timespan {
+            "today" => Some((midnight(today)?, now)),
+            "yesterday" => Some((midnight(today - Duration::days(1))?, midnight(today)?)),
+            "this week" => Some((
+                midnight(today - Duration::days(today.weekday().num_days_from_monday() as i64))?,
+                now,
+            )),
+            _ => None,
+        })
+    }
+
+    /// Parse local date given as YYYY-MM-DD or YYYY-MM-DD HH:MM, or "now", to UNIX timestamp
+    fn parse_date(date: &str, now: u64) -> anyhow::Result<u64> {
+        if date == "now" {
+            return Ok(now);
+        }
+
+        let datetime = match NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M") {
+            Ok(datetime) => datetime,
+            Err(_) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .ok_or_else(|| {
+                    anyhow!("Expected YYYY-MM-DD, YYYY-MM-DD HH:MM or now, got {}", date)
+                })?,
+        };
+
+        Config::get_local_timestamp(datetime)
+    }
+
+    /// Returns UNIX timestamp of date and time in local time zone
+    fn get_local_timestamp(datetime: NaiveDateTime) -> anyhow::Result<u64> {
+        let timestamp = Local
+            .from_local_datetime(&datetime)
+            .earliest()
+            .context(format!("{} doesn't exist in local time zone", datetime))?
+            .timestamp();
+
+        match timestamp < 0 {
+            true => anyhow::bail!("{} is before epoch", datetime),
+            false => Ok(timestamp as u64),
+        }
+    }
+
+    /// Parse size of graphs given as WIDTHxHEIGHT, e.g. 1024x768
+    fn parse_size(size: &str) -> anyhow::Result<(u32, u32)> {
+        let index = size
+            .find('x')
+            .ok_or_else(|| anyhow::anyhow!("Expected WIDTHxHEIGHT, got {}", size))?;
+        let (width, height) = (&size[..index], &size[index + 1..]);
+
+        Ok((
+            width
+                .parse::<u32>()
+                .context(format!("Invalid width {}", width))?,
+            height
+                .parse::<u32>()
+                .context(format!("Invalid height {}", height))?,
+        ))
+    }
+
+    /// Parse titles given as "plugin=title" for graphs of particular plugin,
+    /// or just "title" for all graphs
+    fn parse_titles(
+        titles: Vec<&'a str>,
+    ) -> anyhow::Result<(Option<&'a str>, HashMap<Plugins, &'a str>)> {
+        let mut title = None;
+        let mut plugin_titles = HashMap::new();
+
+        for value in titles {
+            let plugin = value.find('=').and_then(|index| {
+                Plugins::from_str(&value[..index])
+                    .ok()
+                    .map(|plugin| (plugin, &value[index + 1..]))
+            });
+
+            match plugin {
+                Some((plugin, plugin_title)) => {
+                    plugin_titles.insert(plugin, plugin_title);
+                }
+                None => match title {
+                    None => title = Some(value),
+                    Some(title) => {
+                        anyhow::bail!("Only one common title allowed, got {} and {}", title, value)
+                    }
+                },
+            }
+        }
+
+        Ok((title, plugin_titles))
+    }
+
+    /// Read colors from palette file, one #rrggbb or #rrggbbaa per line, empty lines are skipped
+    fn read_palette(path: &Path) -> anyhow::Result<Vec<String>> {
+        let content = std::fs::read_to_string(path).context("Failed to read palette file")?;
+
+        let colors = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| validate_color(line).map(|_| String::from(line)))
+            .collect::<anyhow::Result<Vec<String>>>()?;
+
+        if colors.is_empty() {
+            anyhow::bail!("No colors in palette file");
+        }
+
+        Ok(colors)
+    }
+
+    /// Split input into local paths separated with colon, remote input is kept whole
+    /// as it contains colon after host name
+    fn split_input(input: &'a str) -> Vec<&'a str> {
+        match input.contains('@') {
+            true => vec![input],
+            false => input.split(':').filter(|path| !path.is_empty()).collect(),
+        }
+    }
+
     pub fn get_vec_of_type_from_cli<T>(args: &'a str) -> anyhow::Result<Vec<T>>
     where
         T: FromStr,
         <T as std::str::FromStr>::Err: std::fmt::Debug,
     {
-        Ok(args
-            .split(',')
-            .collect::<Vec<&str>>()
-            .iter()
-            .map(|arg| T::from_str(arg).unwrap())
-            .collect::<Vec<T>>())
+        args.split(',')
+            .map(|arg| {
+                T::from_str(arg).map_err(|err| anyhow!("Unrecognized value {}: {:?}", arg, err))
+            })
+            .collect::<anyhow::Result<Vec<T>>>()
     }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use super::super::processes::processes_data::ProcessesData;
     use super::*;
     use anyhow::Result;
+    use clap::{load_yaml, App};
     use std::time::SystemTime;
 
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
     #[test]
     pub fn parse_timespan_error() -> Result<()> {
-        let res = Config::parse_timespan(String::from("lasts 5 minutes"));
+        let res = Config::parse_timespan(String::from("lasts 5 minutes"), now());
         assert!(res.is_err());
 
         Ok(())
@@ -220,7 +1071,7 @@ pub mod tests {
 
     #[test]
     pub fn parse_timespan_ok_last_5_minutes() -> Result<()> {
-        let (start, end) = Config::parse_timespan(String::from("last 5 minutes")).unwrap();
+        let (start, end) = Config::parse_timespan(String::from("last 5 minutes"), now()).unwrap();
 
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -235,7 +1086,7 @@ pub mod tests {
 
     #[test]
     pub fn parse_timespan_ok_last_week() -> Result<()> {
-        let (start, end) = Config::parse_timespan(String::from("last week")).unwrap();
+        let (start, end) = Config::parse_timespan(String::from("last week"), now()).unwrap();
 
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -250,7 +1101,7 @@ pub mod tests {
 
     #[test]
     pub fn parse_timespan_ok_last_10_days() -> Result<()> {
-        let (start, end) = Config::parse_timespan(String::from("last 10 days")).unwrap();
+        let (start, end) = Config::parse_timespan(String::from("last 10 days"), now()).unwrap();
 
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -263,6 +1114,317 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn parse_timespan_huge_and_empty() -> Result<()> {
+        assert!(Config::parse_timespan(String::from("last 999999999999999 years"), now()).is_err());
+        assert_eq!(
+            (0, 1600000000),
+            Config::parse_timespan(String::from("last 999999 years"), 1600000000)?
+        );
+        assert!(Config::parse_timespan(String::from("last 0 hours"), now()).is_err());
+        assert!(Config::parse_timespan(String::from("last 0.1 seconds"), now()).is_err());
+        assert!(Config::parse_timespan(String::from("last 5"), now()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_timespan_shorthand() -> Result<()> {
+        let now = 1600000000;
+        let span = |timespan: &str| {
+            Config::parse_timespan(String::from(timespan), now).map(|(start, end)| end - start)
+        };
+
+        assert_eq!(4 * 3600, span("4h")?);
+        assert_eq!(30 * 60, span("last 30m")?);
+        assert_eq!(2 * 604800, span("2w")?);
+        assert_eq!(45, span("last 45s")?);
+        assert_eq!(2 * 86400, span("2D")?);
+        assert_eq!(5400, span("1.5h")?);
+        assert_eq!(
+            (now - 7200, now),
+            Config::parse_timespan(String::from("last 2h"), now)?
+        );
+
+        assert!(span("h").is_err());
+        assert!(span("4x").is_err());
+        assert!(span("last 4hh").is_err());
+        assert!(span("-4h").is_err());
+        assert!(span("0h").is_err());
+
+        assert_eq!(now - 3600, Config::parse_time_point("1h ago", now)?);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_timespan_fractional() -> Result<()> {
+        let now = 1600000000;
+        let span = |timespan: &str| {
+            Config::parse_timespan(String::from(timespan), now).map(|(start, end)| end - start)
+        };
+
+        assert_eq!(5400, span("last 1.5 hours")?);
+        assert_eq!(43200, span("last 0.5 days")?);
+        assert_eq!(90, span("last 1.5 minutes")?);
+        assert_eq!(7200, span("last 2 hours")?);
+        assert_eq!(3600, span("last hour")?);
+
+        assert!(span("last -1.5 hours").is_err());
+        assert!(span("last -2 days").is_err());
+        assert!(span("last nan hours").is_err());
+        assert!(span("last inf hours").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_timespan_date_range() -> Result<()> {
+        let timestamp = |year, month, day, hour, minute| {
+            let datetime = NaiveDate::from_ymd_opt(year, month, day)
+                .and_then(|date| date.and_hms_opt(hour, minute, 0))
+                .unwrap();
+
+            Local.from_local_datetime(&datetime).unwrap().timestamp() as u64
+        };
+
+        assert_eq!(
+            (timestamp(2021, 3, 1, 0, 0), timestamp(2021, 3, 8, 0, 0)),
+            Config::parse_timespan(String::from("2021-03-01..2021-03-08"), now())?
+        );
+        assert_eq!(
+            (timestamp(2021, 3, 1, 12, 30), 1700000000),
+            Config::parse_timespan(String::from("2021-03-01 12:30..now"), 1700000000)?
+        );
+
+        assert!(Config::parse_timespan(String::from("2021-03-08..2021-03-01"), now()).is_err());
+        assert!(Config::parse_timespan(String::from("2021-03-01..2021-02-30"), now()).is_err());
+        assert!(Config::parse_timespan(String::from("yesterday..now"), now()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_timespan_calendar() -> Result<()> {
+        // Sun Sep 13 2020, far from daylight saving time changes
+        let now = 1600000000;
+
+        let (start, end) = Config::parse_timespan(String::from("yesterday"), now)?;
+        assert_eq!(86400, end - start);
+        assert!(end <= now && now - end < 86400);
+        assert_eq!(
+            "2020-09-12 00:00",
+            Local
+                .timestamp_opt(start as i64, 0)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        );
+
+        let (start, end) = Config::parse_timespan(String::from("Today"), now)?;
+        assert_eq!(now, end);
+        assert_eq!(
+            "2020-09-13 00:00",
+            Local
+                .timestamp_opt(start as i64, 0)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        );
+
+        let (start, _) = Config::parse_timespan(String::from("this week"), now)?;
+        assert_eq!(
+            "2020-09-07 00:00",
+            Local
+                .timestamp_opt(start as i64, 0)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_timespan_with_now() -> Result<()> {
+        let yaml = load_yaml!("cli.yml");
+        let cli = App::from(yaml).get_matches_from(vec![
+            "cgg",
+            "-i",
+            "/some/local",
+            "-t",
+            "last 1 hour",
+            "--now",
+            "1600000000",
+        ]);
+
+        let config = Config::new(&cli)?;
+
+        assert_eq!(1600000000, config.now);
+        assert_eq!(1600000000 - 3600, config.start);
+        assert_eq!(1600000000, config.end);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_time_point() -> Result<()> {
+        let now = 1600000000;
+
+        assert_eq!(1500000000, Config::parse_time_point("1500000000", now)?);
+        assert_eq!(now, Config::parse_time_point("now", now)?);
+        assert_eq!(
+            now - 2 * 86400,
+            Config::parse_time_point("last 2 days", now)?
+        );
+        assert_eq!(now - 3600, Config::parse_time_point(" Last hour ", now)?);
+
+        let datetime = NaiveDate::from_ymd_opt(2020, 9, 1)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .unwrap();
+        assert_eq!(
+            Local.from_local_datetime(&datetime).unwrap().timestamp() as u64,
+            Config::parse_time_point("2020-09-01 12:00", now)?
+        );
+
+        assert_eq!(now - 7200, Config::parse_time_point("2 hours ago", now)?);
+        assert_eq!(now - 60, Config::parse_time_point("1 Minute Ago", now)?);
+        assert!(Config::parse_time_point("two days ago", now).is_err());
+        assert!(Config::parse_time_point("ago", now).is_err());
+        assert!(Config::parse_time_point("", now).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_start_and_end_mixed() -> Result<()> {
+        let parse = |start: &str, end: &str| {
+            let yaml = load_yaml!("cli.yml");
+            let cli = App::from(yaml).get_matches_from(vec![
+                "cgg",
+                "-i",
+                "/some/local",
+                "--start",
+                start,
+                "--end",
+                end,
+                "--now",
+                "1600000000",
+            ]);
+
+            Config::new(&cli).map(|config| (config.start, config.end))
+        };
+
+        assert_eq!(
+            (1600000000 - 2 * 86400, 1600000000 - 86400),
+            parse("last 2 days", "last 1 day")?
+        );
+        assert_eq!(
+            (1500000000, 1600000000 - 3600),
+            parse("1500000000", "last hour")?
+        );
+        assert_eq!(
+            (1600000000 - 3600, 1600000000),
+            parse("last hour", "1600000000")?
+        );
+        assert_eq!((1500000000, 1600000000), parse("1500000000", "now")?);
+
+        assert!(parse("last 1 day", "last 2 days").is_err());
+        assert!(parse("1600000000", "1600000000").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_size() -> Result<()> {
+        assert_eq!((2048, 1024), Config::parse_size("2048x1024")?);
+        assert!(Config::parse_size("2048").is_err());
+        assert!(Config::parse_size("2048x").is_err());
+        assert!(Config::parse_size("widexhigh").is_err());
+
+        let yaml = clap::load_yaml!("cli.yml");
+        let cli = clap::App::from(yaml).get_matches_from(vec![
+            "cgg",
+            "-i",
+            "/some/local",
+            "-t",
+            "last 1 hour",
+            "--processes-size",
+            "2048x1024",
+            "--memory-size",
+            "1024x512",
+        ]);
+        let config = Config::new(&cli)?;
+
+        assert_eq!(2, config.plugin_sizes.len());
+        assert_eq!((2048, 1024), config.plugin_sizes[&Plugins::Processes]);
+        assert_eq!((1024, 512), config.plugin_sizes[&Plugins::Memory]);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_titles() -> Result<()> {
+        let (title, plugin_titles) = Config::parse_titles(vec![
+            "processes=Process RSS",
+            "Load = high",
+            "memory=System Memory",
+        ])?;
+
+        assert_eq!(Some("Load = high"), title);
+        assert_eq!(2, plugin_titles.len());
+        assert_eq!("Process RSS", plugin_titles[&Plugins::Processes]);
+        assert_eq!("System Memory", plugin_titles[&Plugins::Memory]);
+
+        assert!(Config::parse_titles(vec!["First", "Second"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_palette_preset() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |args: &[&'static str]| {
+            let mut all = vec!["cgg", "-i", "/some/local", "-t", "last 1 hour"];
+            all.extend(args);
+            clap::App::from(yaml).try_get_matches_from(all)
+        };
+
+        let cli = args(&["--palette-preset", "grayscale"])?;
+        assert_eq!(
+            Some(PalettePreset::Grayscale.colors()),
+            Config::new(&cli)?.palette
+        );
+
+        let cli = args(&[])?;
+        assert_eq!(None, Config::new(&cli)?.palette);
+
+        assert!(args(&["--palette-preset", "neon"]).is_err());
+        assert!(args(&["--palette-preset", "colorblind", "--palette", "colors.txt"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn read_palette() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("colors.txt");
+
+        std::fs::write(&path, "#ff0000\n\n  #00FF00\n")?;
+        assert_eq!(vec!["#ff0000", "#00FF00"], Config::read_palette(&path)?);
+
+        std::fs::write(&path, "#ff000080\n")?;
+        assert_eq!(vec!["#ff000080"], Config::read_palette(&path)?);
+
+        std::fs::write(&path, "#ff0000\nblue\n")?;
+        assert!(Config::read_palette(&path).is_err());
+
+        std::fs::write(&path, "\n")?;
+        assert!(Config::read_palette(&path).is_err());
+
+        Ok(())
+    }
+
     #[test]
     pub fn get_plugins_from_cli() -> Result<()> {
         let plugins = Config::get_vec_of_type_from_cli::<Plugins>("processes,memory").unwrap();
@@ -272,6 +1434,350 @@ pub mod tests {
         assert!(plugins.contains(&Plugins::Processes));
         assert!(plugins.contains(&Plugins::Memory));
 
+        assert!(Config::get_vec_of_type_from_cli::<Plugins>("processes,disk").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_watermark() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |watermark: &[&'static str]| {
+            let mut args = vec![
+                "cgg",
+                "-i",
+                "/some/local",
+                "-t",
+                "last 1 hour",
+                "--now",
+                "1600000000",
+            ];
+            args.extend(watermark);
+            clap::App::from(yaml).get_matches_from(args)
+        };
+
+        let cli = args(&[]);
+        assert_eq!(None, Config::new(&cli)?.watermark);
+
+        let cli = args(&["--watermark", "generated by cgg"]);
+        assert_eq!(
+            Some(String::from("generated by cgg")),
+            Config::new(&cli)?.watermark
+        );
+
+        let cli = args(&["--watermark", "--stats"]);
+        assert!(Config::new(&cli)?
+            .watermark
+            .unwrap()
+            .starts_with("generated by cgg on 2020-09-"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_step() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |step: &'static str| {
+            clap::App::from(yaml).get_matches_from(vec![
+                "cgg",
+                "-i",
+                "/some/local",
+                "-t",
+                "last 1 hour",
+                "--step",
+                step,
+            ])
+        };
+
+        let cli = args("300");
+        assert_eq!(Some(300), Config::new(&cli)?.step);
+
+        let cli = args("5m");
+        assert!(Config::new(&cli).is_err());
+
+        let cli = args("0");
+        assert!(Config::new(&cli).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_manifest_stdout() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |output: &'static str| {
+            clap::App::from(yaml).get_matches_from(vec![
+                "cgg",
+                "-i",
+                "/some/local",
+                "-t",
+                "last 1 hour",
+                "-o",
+                output,
+                "--manifest",
+                "manifest.json",
+            ])
+        };
+
+        let cli = args("out.png");
+        assert_eq!(Some("manifest.json"), Config::new(&cli)?.manifest);
+
+        let cli = args("-");
+        assert!(Config::new(&cli).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_trend() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |trend: &'static str| {
+            clap::App::from(yaml).get_matches_from(vec![
+                "cgg",
+                "-i",
+                "/some/local",
+                "-t",
+                "last 1 hour",
+                "--trend",
+                trend,
+            ])
+        };
+
+        let cli = args("3600");
+        assert_eq!(Some(3600), Config::new(&cli)?.trend);
+
+        let cli = args("0");
+        assert!(Config::new(&cli).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_max_processes() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |max_processes: &'static str| {
+            clap::App::from(yaml).get_matches_from(vec![
+                "cgg",
+                "-i",
+                "/some/local",
+                "-t",
+                "last 1 hour",
+                "-p",
+                "processes",
+                "-m",
+                max_processes,
+            ])
+        };
+
+        let cli = args("5");
+        let config = Config::new(&cli)?;
+        let processes = config.plugins_config.data[&Plugins::Processes]
+            .downcast_ref::<ProcessesData>()
+            .unwrap();
+        assert_eq!(5, processes.max_processes);
+
+        let cli = args("0");
+        assert!(Config::new(&cli).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_since_until() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |args: &[&'static str]| {
+            let mut all = vec!["cgg", "-i", "/some/local", "--now", "1600000000"];
+            all.extend(args);
+            clap::App::from(yaml).try_get_matches_from(all)
+        };
+
+        let cli = args(&["--since", "2 hours ago", "--until", "now"])?;
+        let config = Config::new(&cli)?;
+        assert_eq!((1600000000 - 7200, 1600000000), (config.start, config.end));
+
+        let cli = args(&["--since", "last 2 days", "--until", "1 day ago"])?;
+        let config = Config::new(&cli)?;
+        assert_eq!(
+            (1600000000 - 2 * 86400, 1600000000 - 86400),
+            (config.start, config.end)
+        );
+
+        // Aliases are mixed with --start and --end, end defaults to now
+        let cli = args(&["--start", "1599990000", "--until", "1 hour ago"])?;
+        let config = Config::new(&cli)?;
+        assert_eq!((1599990000, 1600000000 - 3600), (config.start, config.end));
+
+        let cli = args(&["--since", "30 minutes ago"])?;
+        let config = Config::new(&cli)?;
+        assert_eq!((1600000000 - 1800, 1600000000), (config.start, config.end));
+
+        // Conflicting ranges are rejected
+        assert!(args(&["--since", "2 hours ago", "-t", "last 1 hour"]).is_err());
+        assert!(args(&["--until", "now", "-t", "last 1 hour"]).is_err());
+        assert!(args(&["--since", "2 hours ago", "--start", "1599990000"]).is_err());
+        assert!(args(&["--until", "now", "--end", "1600000000"]).is_err());
+
+        let cli = args(&["--since", "now", "--until", "2 hours ago"])?;
+        assert!(Config::new(&cli).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_start_without_end() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |args: &[&'static str]| {
+            let mut all = vec!["cgg", "-i", "/some/local"];
+            all.extend(args);
+            clap::App::from(yaml).get_matches_from(all)
+        };
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+
+        let cli = args(&["--start", "1600000000"]);
+        let config = Config::new(&cli)?;
+        assert_eq!(1600000000, config.start);
+        assert!(config.end >= now && config.end - now < 60);
+
+        let cli = args(&["--start", "1600000000", "--now", "1600003600"]);
+        let config = Config::new(&cli)?;
+        assert_eq!((1600000000, 1600003600), (config.start, config.end));
+
+        let cli = args(&["--start", "1600000000", "--end", "1600000600"]);
+        let config = Config::new(&cli)?;
+        assert_eq!((1600000000, 1600000600), (config.start, config.end));
+
+        assert!(Config::new(&args(&["--start", "1600000000", "--now", "1500000000"])).is_err());
+
+        let cli = clap::App::from(yaml).try_get_matches_from(vec![
+            "cgg",
+            "-i",
+            "/some/local",
+            "--start",
+            "1600000000",
+            "-t",
+            "last 1 hour",
+        ]);
+        assert!(cli.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_plugins_order() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let cli = clap::App::from(yaml).get_matches_from(vec![
+            "cgg",
+            "-i",
+            "/some/local",
+            "-t",
+            "last 1 hour",
+            "-p",
+            "memory,processes,memory",
+        ]);
+
+        let config = Config::new(&cli)?;
+        assert_eq!(
+            vec![Plugins::Memory, Plugins::Processes],
+            config.plugins_config.ordered()
+        );
+
+        let mut plugins_config = config.plugins_config;
+        plugins_config.order.clear();
+        assert_eq!(
+            vec![Plugins::Memory, Plugins::Processes],
+            plugins_config.ordered()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn split_input() -> Result<()> {
+        assert_eq!(
+            vec!["/data/host-a", "/data/host-b/"],
+            Config::split_input("/data/host-a:/data/host-b/:")
+        );
+        assert_eq!(
+            vec!["user@host:2222:/data/host-a"],
+            Config::split_input("user@host:2222:/data/host-a")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_rrd_file_input() -> Result<()> {
+        let yaml = clap::load_yaml!("cli.yml");
+        let args = |inputs: Vec<&'static str>| {
+            let mut args = vec!["cgg", "-t", "last 1 hour"];
+            for input in inputs {
+                args.push("-i");
+                args.push(input);
+            }
+            clap::App::from(yaml).get_matches_from(args)
+        };
+
+        let cli = args(vec!["/some/load.rrd"]);
+        assert_eq!(Path::new("/some/load.rrd"), Config::new(&cli)?.input_dir);
+
+        let cli = args(vec!["/some/load.rrd", "/some/local"]);
+        assert!(Config::new(&cli).is_err());
+
+        let cli = args(vec!["/some/local", "/some/load.rrd"]);
+        assert!(Config::new(&cli).is_err());
+
+        let cli = args(vec!["/some/load.rrd", "/some/memory.rrd"]);
+        assert!(Config::new(&cli).is_err());
+
+        let cli = args(vec!["/some/load.rrd:/some/local"]);
+        assert!(Config::new(&cli).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_memory_plugin_without_memory_types() -> Result<()> {
+        // No default values, so nothing fills in missing --memory
+        let mut app = clap::App::new("cgg");
+        for name in &[
+            "input",
+            "out",
+            "width",
+            "height",
+            "timespan",
+            "plugins",
+            "scp_retries",
+        ] {
+            app = app.arg(clap::Arg::new(*name).long(name).takes_value(true));
+        }
+
+        let cli = app.get_matches_from(vec![
+            "cgg",
+            "--input",
+            "/some/local",
+            "--out",
+            "out.png",
+            "--width",
+            "1024",
+            "--height",
+            "768",
+            "--timespan",
+            "last 1 hour",
+            "--plugins",
+            "memory",
+            "--scp_retries",
+            "0",
+        ]);
+
+        let error = match Config::new(&cli) {
+            Ok(_) => anyhow::bail!("Config should fail without --memory"),
+            Err(error) => format!("{:?}", error),
+        };
+
+        assert!(error.contains("memory plugin selected but --memory not provided and no default"));
+
         Ok(())
     }
 }
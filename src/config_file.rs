@@ -0,0 +1,365 @@
+use super::config::PluginsConfig;
+use super::cpu::{cpu_data::CpuData, cpu_type::CpuType};
+use super::df::df_data::DfData;
+use super::memory::{memory_data::MemoryData, memory_type::MemoryType};
+use super::processes::processes_data::ProcessesData;
+use super::rrdtool::common::Plugins;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Options read from TOML configuration file given with --config
+///
+/// # Examples
+///
+/// ```toml
+/// input = "/var/lib/collectd/marcin-manjaro/"
+/// output = "graph.png"
+/// width = 2048
+/// height = 1024
+/// timespan = "last 2 days"
+///
+/// [plugins.processes]
+/// processes = ["firefox", "spotify"]
+/// max_processes = 5
+///
+/// [plugins.memory]
+/// types = ["used", "free"]
+/// ```
+///
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Path to the directory with collectd output
+    pub input: Option<String>,
+    /// Output filename
+    pub output: Option<String>,
+    /// Width of the generated graph
+    pub width: Option<u32>,
+    /// Height of the generated graph
+    pub height: Option<u32>,
+    /// Descriptive timespan of data range
+    pub timespan: Option<String>,
+    /// Plugins to run, each with its own options
+    #[serde(default)]
+    pub plugins: PluginsFile,
+}
+
+/// Plugins to run, a plugin is run if its table is present
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginsFile {
+    pub processes: Option<ProcessesFile>,
+    pub memory: Option<MemoryFile>,
+    pub cpu: Option<CpuFile>,
+    pub df: Option<DfFile>,
+}
+
+/// Options of processes plugin
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessesFile {
+    /// Processes to draw, all processes if not given
+    pub processes: Option<Vec<String>>,
+    /// Maximum number of processes in one graph
+    pub max_processes: Option<usize>,
+}
+
+/// Options of memory plugin
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryFile {
+    /// Memory types to draw
+    pub types: Option<Vec<String>>,
+}
+
+/// Options of CPU plugin
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuFile {
+    /// CPU states to draw
+    pub types: Option<Vec<String>>,
+    /// Draw all cores on one graph
+    #[serde(default)]
+    pub combined: bool,
+}
+
+/// Options of df plugin
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DfFile {
+    /// Mountpoints to draw, all mountpoints if not given
+    pub mountpoints: Option<Vec<String>>,
+}
+
+impl ConfigFile {
+    /// Reads and parses TOML configuration file
+    pub fn read(path: &Path) -> Result<ConfigFile> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Cannot read config file {}", path.display()))?;
+
+        toml::from_str(&content).context(format!("Cannot parse config file {}", path.display()))
+    }
+}
+
+impl PluginsFile {
+    /// Returns plugins with a table in the file
+    pub fn names(&self) -> Vec<Plugins> {
+        vec![
+            (Plugins::Processes, self.processes.is_some()),
+            (Plugins::Memory, self.memory.is_some()),
+            (Plugins::Cpu, self.cpu.is_some()),
+            (Plugins::Df, self.df.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, present)| *present)
+        .map(|(plugin, _)| plugin)
+        .collect()
+    }
+
+    /// Set options of the file in data of selected plugins, unless they are given in `cli`
+    pub fn merge(&self, cli: &clap::ArgMatches, plugins_config: &mut PluginsConfig) -> Result<()> {
+        let given = |name: &str| cli.occurrences_of(name) > 0;
+
+        if let (Some(file), Some(data)) = (
+            &self.processes,
+            get_data::<ProcessesData>(plugins_config, Plugins::Processes),
+        ) {
+            if let Some(processes) = file.processes.as_ref().filter(|_| !given("processes")) {
+                data.processes_to_draw = Some(processes.clone());
+            }
+
+            if let Some(max_processes) = file.max_processes.filter(|_| !given("max_processes")) {
+                if max_processes == 0 {
+                    anyhow::bail!("Maximum number of processes on one graph must be positive");
+                }
+
+                data.max_processes = max_processes;
+            }
+        }
+
+        if let (Some(file), Some(data)) = (
+            &self.memory,
+            get_data::<MemoryData>(plugins_config, Plugins::Memory),
+        ) {
+            if let Some(types) = file.types.as_ref().filter(|_| !given("memory")) {
+                data.memory_types =
+                    parse_all::<MemoryType>(types).context("Cannot parse memory types")?;
+            }
+        }
+
+        if let (Some(file), Some(data)) =
+            (&self.cpu, get_data::<CpuData>(plugins_config, Plugins::Cpu))
+        {
+            if let Some(types) = file.types.as_ref().filter(|_| !given("cpu")) {
+                data.cpu_types = parse_all::<CpuType>(types).context("Cannot parse CPU states")?;
+            }
+
+            data.combined |= file.combined;
+        }
+
+        if let (Some(file), Some(data)) =
+            (&self.df, get_data::<DfData>(plugins_config, Plugins::Df))
+        {
+            if let Some(mountpoints) = file.mountpoints.as_ref().filter(|_| !given("df")) {
+                data.mountpoints_to_draw = Some(mountpoints.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns data of selected plugin, None if the plugin isn't selected
+fn get_data<T: 'static>(plugins_config: &mut PluginsConfig, plugin: Plugins) -> Option<&mut T> {
+    plugins_config
+        .data
+        .get_mut(&plugin)
+        .and_then(|data| data.downcast_mut::<T>())
+}
+
+/// Parse each value of a list read from the file
+fn parse_all<T: FromStr>(values: &[String]) -> Result<Vec<T>> {
+    values
+        .iter()
+        .map(|value| T::from_str(value).map_err(|_| anyhow!("Unrecognized value {}", value)))
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::config::Config;
+    use super::*;
+    use clap::{load_yaml, App};
+
+    const SAMPLE: &str = r#"
+input = "/var/lib/collectd/host"
+output = "graph.png"
+width = 2048
+height = 1024
+timespan = "last 2 hours"
+
+[plugins.processes]
+processes = ["firefox", "spotify"]
+max_processes = 5
+
+[plugins.memory]
+types = ["used", "free"]
+
+[plugins.cpu]
+combined = true
+"#;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    fn cli(args: &[&str]) -> clap::ArgMatches {
+        let yaml = load_yaml!("cli.yml");
+        App::from(yaml).get_matches_from(args)
+    }
+
+    #[test]
+    pub fn config_from_file() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("cgg.toml");
+        std::fs::write(&path, SAMPLE)?;
+
+        let file = ConfigFile::read(&path)?;
+        let cli = cli(&[
+            "cgg",
+            "--config",
+            path.to_str().unwrap(),
+            "--now",
+            "1600000000",
+        ]);
+        let config = Config::from_file(&cli, &file)?;
+
+        assert_eq!(Path::new("/var/lib/collectd/host"), config.input_dir);
+        assert_eq!("graph.png", config.output_filename);
+        assert_eq!(2048, config.width);
+        assert_eq!(1024, config.height);
+        assert_eq!((1600000000 - 7200, 1600000000), (config.start, config.end));
+        assert_eq!(3, config.plugins_config.data.len());
+
+        let processes = config.plugins_config.data[&Plugins::Processes]
+            .downcast_ref::<ProcessesData>()
+            .unwrap();
+        assert_eq!(5, processes.max_processes);
+        assert_eq!(
+            Some(args(&["firefox", "spotify"])),
+            processes.processes_to_draw
+        );
+
+        let memory = config.plugins_config.data[&Plugins::Memory]
+            .downcast_ref::<MemoryData>()
+            .unwrap();
+        assert_eq!(
+            vec![MemoryType::Used, MemoryType::Free],
+            memory.memory_types
+        );
+
+        let cpu = config.plugins_config.data[&Plugins::Cpu]
+            .downcast_ref::<CpuData>()
+            .unwrap();
+        assert!(cpu.combined);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_from_file_cli_wins() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("cgg.toml");
+        std::fs::write(&path, SAMPLE)?;
+
+        let file = ConfigFile::read(&path)?;
+        let cli = cli(&[
+            "cgg",
+            "--config",
+            path.to_str().unwrap(),
+            "-w",
+            "640",
+            "--start",
+            "1500000000",
+            "--end",
+            "1500003600",
+            "-p",
+            "memory",
+            "--memory",
+            "cached",
+        ]);
+        let config = Config::from_file(&cli, &file)?;
+
+        assert_eq!(640, config.width);
+        assert_eq!(1024, config.height);
+        assert_eq!((1500000000, 1500003600), (config.start, config.end));
+        assert_eq!(1, config.plugins_config.data.len());
+
+        let memory = config.plugins_config.data[&Plugins::Memory]
+            .downcast_ref::<MemoryData>()
+            .unwrap();
+        assert_eq!(vec![MemoryType::Cached], memory.memory_types);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_from_file_typed_values() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("cgg.toml");
+        std::fs::write(
+            &path,
+            "input = \"/var/lib/collectd/host\"\n\
+             timespan = \"last 2 hours\"\n\
+             [plugins.processes]\n\
+             processes = [\"web,worker\", \"spotify\"]\n",
+        )?;
+
+        let file = ConfigFile::read(&path)?;
+        let cli = cli(&["cgg", "--config", path.to_str().unwrap()]);
+        let config = Config::from_file(&cli, &file)?;
+
+        // Values aren't joined, so commas in names are kept
+        let processes = config.plugins_config.data[&Plugins::Processes]
+            .downcast_ref::<ProcessesData>()
+            .unwrap();
+        assert_eq!(
+            Some(args(&["web,worker", "spotify"])),
+            processes.processes_to_draw
+        );
+
+        std::fs::write(
+            &path,
+            "input = \"/var/lib/collectd/host\"\n\
+             timespan = \"last 2 hours\"\n\
+             [plugins.processes]\n\
+             max_processes = 0\n",
+        )?;
+
+        let file = ConfigFile::read(&path)?;
+        assert!(Config::from_file(&cli, &file).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn config_file_errors() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join("cgg.toml");
+
+        std::fs::write(&path, "inputs = \"/var/lib/collectd/host\"\n")?;
+        assert!(ConfigFile::read(&path).is_err());
+
+        std::fs::write(&path, "[plugins.disk]\n")?;
+        assert!(ConfigFile::read(&path).is_err());
+
+        std::fs::write(&path, "width = \"wide\"\n")?;
+        assert!(ConfigFile::read(&path).is_err());
+
+        assert!(ConfigFile::read(&temp.path().join("missing.toml")).is_err());
+
+        Ok(())
+    }
+}
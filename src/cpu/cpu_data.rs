@@ -0,0 +1,65 @@
+use super::super::config;
+use super::cpu_type::CpuType;
+use super::rrdtool::common::Plugins;
+use anyhow::{Context, Result};
+
+/// Data used by CPU plugin
+///
+/// # Examples
+///
+/// ```
+/// use cgg::cpu::{cpu_data::CpuData, cpu_type::CpuType};
+///
+/// let cpu_data = CpuData::new(vec![CpuType::User, CpuType::System], false);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct CpuData {
+    /// CPU states to visualize on graph
+    pub cpu_types: Vec<CpuType>,
+    /// Draw all cores on one graph instead of one graph per core
+    pub combined: bool,
+}
+
+impl CpuData {
+    pub fn new(cpu_types: Vec<CpuType>, combined: bool) -> CpuData {
+        CpuData {
+            cpu_types,
+            combined,
+        }
+    }
+}
+
+impl<'a> config::Config<'a> {
+    /// Returns [`CpuData`] structure with all data needed by CPU plugin
+    ///
+    /// # Arguments
+    /// * `cli` - A reference to [`clap::ArgMatches`] to get data from user
+    /// * `plugins` - Vector of plugins already read from command line
+    ///
+    pub fn get_cpu_data(cli: &'a clap::ArgMatches, plugins: &[Plugins]) -> Result<Option<CpuData>> {
+        Ok(match plugins.contains(&Plugins::Cpu) {
+            true => Some(CpuData::new(
+                config::Config::get_cpu_types(cli).context("Failed to get CPU states to draw")?,
+                cli.is_present("cpu_combined"),
+            )),
+            false => None,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::super::config;
+    use super::*;
+
+    #[test]
+    fn get_cpu_data_nok() -> Result<()> {
+        let cli = clap::ArgMatches::default();
+
+        assert!(config::Config::get_cpu_data(&cli, &[Plugins::Memory])?.is_none());
+        assert!(config::Config::get_cpu_data(&cli, &[Plugins::Cpu]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use super::cpu_data::CpuData;
+use super::error::CggError;
+use super::processes::processes_names;
+use super::rrdtool::common::{Plugin, Plugins, Rrdtool};
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::{debug, trace};
+
+impl Plugin<&CpuData> for Rrdtool {
+    fn enter_plugin(&mut self, data: &CpuData) -> Result<&mut Self> {
+        debug!("CPU plugin entry point");
+        trace!("CPU plugin: {:?}", data);
+
+        let cores = get_cores(self).context("Failed to get CPU cores")?;
+
+        if cores.is_empty() {
+            return Err(
+                anyhow::Error::new(CggError::no_data_found(Plugins::Cpu, &self.input_dir))
+                    .context("No cpu-N directories found"),
+            );
+        }
+
+        trace!("Found CPU cores: {:?}", cores);
+
+        // All cores are drawn on the graph shared with other plugins
+        let combined = data.combined || self.graph_args.combine;
+
+        if combined {
+            self.graph_args.new_labeled_plugin_graph("cpu");
+        }
+
+        let mut index = self.graph_args.first_color_index();
+
+        for core in &cores {
+            let core_dir = Path::new(self.input_dir.as_str()).join(format!("cpu-{}", core));
+
+            if !combined {
+                self.graph_args.new_labeled_graph(&format!("cpu-{}", core));
+                index = 0;
+            }
+
+            for cpu_type in &data.cpu_types {
+                let legend = match combined {
+                    // First word of legend names the variable, so it's unique per core and state
+                    true => format!("cpu-{}-{}", core, cpu_type),
+                    false => cpu_type.to_string(),
+                };
+
+                let color = self.get_color(index);
+                let element = self.get_line(index, 3);
+
+                self.graph_args.push(
+                    &legend,
+                    &color,
+                    element,
+                    core_dir.join(cpu_type.to_filename()).to_str().unwrap(),
+                );
+
+                index += 1;
+            }
+        }
+
+        trace!("CPU plugin exit");
+
+        Ok(self)
+    }
+}
+
+/// Returns sorted numbers of CPU cores, found as cpu-N directories
+fn get_cores(rrdtool: &Rrdtool) -> Result<Vec<u32>> {
+    let mut cores = processes_names::get_with_prefix(rrdtool, "cpu-")?
+        .iter()
+        .filter_map(|core| core.parse::<u32>().ok())
+        .collect::<Vec<u32>>();
+
+    cores.sort_unstable();
+
+    Ok(cores)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::cpu_type::CpuType;
+    use super::*;
+    use std::fs::{create_dir, File};
+    use tempfile::TempDir;
+
+    fn create_temp_cpu_files(temp: &TempDir) -> Result<()> {
+        for core in &["cpu-0", "cpu-1", "cpu-10"] {
+            let core_path = temp.path().join(core);
+            create_dir(&core_path)?;
+
+            File::create(core_path.join("cpu-user.rrd"))?;
+            File::create(core_path.join("cpu-system.rrd"))?;
+        }
+
+        create_dir(temp.path().join("cpufreq-0"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_cores_local() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        create_temp_cpu_files(&temp)?;
+
+        let cores = super::get_cores(&Rrdtool::new(temp.path())?)?;
+
+        assert_eq!(vec![0, 1, 10], cores);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cpu_plugin_graph_per_core() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        create_temp_cpu_files(&temp)?;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&CpuData::new(vec![CpuType::User, CpuType::System], false))?;
+
+        assert_eq!(3, rrd.graph_args.args.len());
+        assert_eq!(Some(String::from("cpu-10")), rrd.graph_args.info[2].label);
+        assert_eq!(2, rrd.graph_args.info[2].files.len());
+        assert!(rrd.graph_args.info[2].files[0].ends_with("cpu-10/cpu-user.rrd"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cpu_plugin_combined_graph() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        create_temp_cpu_files(&temp)?;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&CpuData::new(vec![CpuType::User], true))?;
+
+        assert_eq!(1, rrd.graph_args.args.len());
+        assert_eq!(Some(String::from("cpu")), rrd.graph_args.info[0].label);
+        assert_eq!(3, rrd.graph_args.info[0].files.len());
+        assert!(rrd.graph_args.args[0]
+            .iter()
+            .any(|arg| arg.ends_with(":\"cpu-1-user\"")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cpu_plugin_combined_graph_unique_names() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        create_temp_cpu_files(&temp)?;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&CpuData::new(vec![CpuType::User, CpuType::System], true))?;
+
+        let vnames = rrd.graph_args.args[0]
+            .iter()
+            .filter_map(|arg| arg.strip_prefix("DEF:"))
+            .map(|def| def.split('=').next().unwrap())
+            .collect::<Vec<&str>>();
+
+        assert_eq!(6, vnames.len());
+
+        for (index, vname) in vnames.iter().enumerate() {
+            assert!(!vnames[index + 1..].contains(vname));
+        }
+
+        Ok(())
+    }
+}
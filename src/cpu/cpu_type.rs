@@ -0,0 +1,124 @@
+use super::super::config;
+use anyhow::Result;
+use std::fmt;
+use std::str::FromStr;
+
+/// Collectd collects time spent by each CPU core in multiple states
+/// This enum allows to choose which one should be drawn on a graph
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CpuType {
+    Idle,
+    Interrupt,
+    Nice,
+    Softirq,
+    Steal,
+    System,
+    User,
+    Wait,
+}
+
+impl CpuType {
+    /// Returns filename used to store data for particular CPU state
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::cpu::cpu_type::CpuType;
+    ///
+    /// let filename = CpuType::Wait.to_filename();
+    ///
+    /// assert_eq!("cpu-wait.rrd", filename);
+    /// ```
+    ///
+    pub fn to_filename(&self) -> &str {
+        match self {
+            CpuType::Idle => "cpu-idle.rrd",
+            CpuType::Interrupt => "cpu-interrupt.rrd",
+            CpuType::Nice => "cpu-nice.rrd",
+            CpuType::Softirq => "cpu-softirq.rrd",
+            CpuType::Steal => "cpu-steal.rrd",
+            CpuType::System => "cpu-system.rrd",
+            CpuType::User => "cpu-user.rrd",
+            CpuType::Wait => "cpu-wait.rrd",
+        }
+    }
+}
+
+/// Returns [`CpuType`] from str, which allows to convert command line arguments
+/// to appropriate struct
+impl FromStr for CpuType {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<CpuType, Self::Err> {
+        match input {
+            "idle" => Ok(CpuType::Idle),
+            "interrupt" => Ok(CpuType::Interrupt),
+            "nice" => Ok(CpuType::Nice),
+            "softirq" => Ok(CpuType::Softirq),
+            "steal" => Ok(CpuType::Steal),
+            "system" => Ok(CpuType::System),
+            "user" => Ok(CpuType::User),
+            "wait" => Ok(CpuType::Wait),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Converts [`CpuType`] to descriptive string which is used as a legend on a graphs
+impl fmt::Display for CpuType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CpuType::Idle => "idle",
+            CpuType::Interrupt => "interrupt",
+            CpuType::Nice => "nice",
+            CpuType::Softirq => "softirq",
+            CpuType::Steal => "steal",
+            CpuType::System => "system",
+            CpuType::User => "user",
+            CpuType::Wait => "wait",
+        })
+    }
+}
+
+impl<'a> config::Config<'a> {
+    /// Returs vector of [`CpuType`] from command line arguments.
+    /// User may want to draw only chosen CPU states.
+    ///
+    /// # Arguments
+    /// * `cli` - A reference to [`clap::ArgMatches`] to get data from user
+    ///
+    pub fn get_cpu_types(cli: &'a clap::ArgMatches) -> Result<Vec<CpuType>> {
+        match cli.value_of("cpu") {
+            Some(value) => config::Config::get_vec_of_type_from_cli::<CpuType>(value),
+            None => anyhow::bail!("cpu plugin selected but --cpu not provided and no default"),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_type_string_conversion() -> Result<()> {
+        for cpu_type in &[
+            CpuType::Idle,
+            CpuType::Interrupt,
+            CpuType::Nice,
+            CpuType::Softirq,
+            CpuType::Steal,
+            CpuType::System,
+            CpuType::User,
+            CpuType::Wait,
+        ] {
+            assert_eq!(*cpu_type, CpuType::from_str(&cpu_type.to_string()).unwrap());
+            assert_eq!(
+                String::from("cpu-") + &cpu_type.to_string() + ".rrd",
+                cpu_type.to_filename()
+            );
+        }
+
+        assert!(CpuType::from_str("some other").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,6 @@
+pub mod cpu_data;
+pub mod cpu_plugin;
+pub mod cpu_type;
+use super::error;
+use super::processes;
+use super::rrdtool;
@@ -0,0 +1,49 @@
+use super::super::config;
+use super::rrdtool::common::Plugins;
+use anyhow::Result;
+
+/// Data used by df plugin
+///
+/// # Examples
+///
+/// ```
+/// use cgg::df::df_data::DfData;
+///
+/// let df_data = DfData::new(Some(vec![String::from("root"), String::from("home")]));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct DfData {
+    /// List of mountpoints to draw, if None all mountpoints are drawn
+    pub mountpoints_to_draw: Option<Vec<String>>,
+}
+
+impl DfData {
+    pub fn new(mountpoints_to_draw: Option<Vec<String>>) -> DfData {
+        DfData {
+            mountpoints_to_draw,
+        }
+    }
+}
+
+impl<'a> config::Config<'a> {
+    /// Returns [`DfData`] structure with all data needed by df plugin
+    ///
+    /// # Arguments
+    /// * `cli` - A reference to [`clap::ArgMatches`] to get data from user
+    /// * `plugins` - Vector of plugins already read from command line
+    ///
+    pub fn get_df_data(cli: &'a clap::ArgMatches, plugins: &[Plugins]) -> Result<Option<DfData>> {
+        let mountpoints_to_draw = cli.value_of("df").map(|mountpoints| {
+            mountpoints
+                .split(',')
+                .map(String::from)
+                .collect::<Vec<String>>()
+        });
+
+        Ok(match plugins.contains(&Plugins::Df) {
+            true => Some(DfData::new(mountpoints_to_draw)),
+            false => None,
+        })
+    }
+}
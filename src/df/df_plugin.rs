@@ -0,0 +1,125 @@
+use super::df_data::DfData;
+use super::error::CggError;
+use super::processes::processes_names;
+use super::rrdtool::common::{Plugin, Plugins, Rrdtool};
+
+use anyhow::{Context, Result};
+use log::{debug, trace};
+use std::path::Path;
+
+impl Plugin<&DfData> for Rrdtool {
+    /// Entry point for a plugin
+    fn enter_plugin(&mut self, data: &DfData) -> Result<&mut Self> {
+        debug!("Df plugin entry point");
+        trace!("Df plugin: {:?}", data);
+
+        let mountpoints = processes_names::get_with_prefix(self, "df-").context(format!(
+            "Failed to read mountpoints from directory {}",
+            self.input_dir
+        ))?;
+
+        if mountpoints.is_empty() {
+            return Err(
+                anyhow::Error::new(CggError::no_data_found(Plugins::Df, &self.input_dir))
+                    .context("Couldn't find any mountpoints!"),
+            );
+        }
+
+        trace!("Found mountpoints: {:?}", mountpoints);
+
+        let mountpoints = filter_mountpoints(mountpoints, &data.mountpoints_to_draw);
+
+        if mountpoints.is_empty() {
+            return Err(
+                anyhow::Error::new(CggError::no_data_found(Plugins::Df, &self.input_dir)).context(
+                    format!(
+                        "None of mountpoints {:?} found",
+                        data.mountpoints_to_draw.as_ref().unwrap()
+                    ),
+                ),
+            );
+        }
+
+        self.graph_args.new_plugin_graph();
+        let first_color = self.graph_args.first_color_index();
+
+        for (i, mountpoint) in mountpoints.iter().enumerate() {
+            let path = Path::new(self.input_dir.as_str())
+                .join(String::from("df-") + mountpoint)
+                .join("df_complex-used.rrd");
+
+            let color = self.get_color(first_color + i);
+            let element = self.get_line(first_color + i, 3);
+
+            self.graph_args
+                .push(mountpoint, &color, element, path.to_str().unwrap());
+        }
+
+        trace!("Df plugin exit");
+
+        Ok(self)
+    }
+}
+
+/// If mountpoints_to_draw is Some, returns only the mountpoints in both vectors
+fn filter_mountpoints(
+    mut mountpoints: Vec<String>,
+    mountpoints_to_draw: &Option<Vec<String>>,
+) -> Vec<String> {
+    mountpoints.sort();
+
+    match mountpoints_to_draw {
+        None => mountpoints,
+        Some(mountpoints_to_draw) => mountpoints
+            .into_iter()
+            .filter(|mountpoint| mountpoints_to_draw.contains(mountpoint))
+            .collect::<Vec<String>>(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::fs::create_dir;
+    use tempfile::TempDir;
+
+    #[test]
+    pub fn df_plugin_filtered_mountpoints() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for mountpoint in &["df-root", "df-home", "df-var", "memory"] {
+            create_dir(temp.path().join(mountpoint))?;
+        }
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&DfData::new(Some(vec![
+            String::from("var"),
+            String::from("root"),
+        ])))?;
+
+        assert_eq!(1, rrd.graph_args.args.len());
+        assert_eq!(2, rrd.graph_args.info[0].files.len());
+        assert!(rrd.graph_args.info[0].files[0].ends_with("df-root/df_complex-used.rrd"));
+        assert!(rrd.graph_args.info[0].files[1].ends_with("df-var/df_complex-used.rrd"));
+
+        assert!(Rrdtool::new(temp.path())?
+            .enter_plugin(&DfData::new(Some(vec![String::from("boot")])))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn df_plugin_no_mountpoints() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        let error = Rrdtool::new(temp.path())?
+            .enter_plugin(&DfData::new(None))
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().contains("Couldn't find any mountpoints"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod df_data;
+pub mod df_plugin;
+use super::error;
+use super::processes;
+use super::rrdtool;
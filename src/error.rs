@@ -0,0 +1,259 @@
+use super::rrdtool::common::Plugins;
+use std::process::Output;
+use thiserror::Error;
+
+/// Errors returned by public entry points of the crate, e.g. [`crate::run`], so callers
+/// can react to specific failures. Internally errors are [`anyhow::Error`] with context,
+/// typed errors raised deep inside are recovered from it at the crate boundary.
+#[derive(Debug, Error)]
+pub enum CggError {
+    /// Directory on remote target couldn't be listed, e.g. host is unreachable
+    #[error("Failed to list remote directory {target}:{dir}")]
+    RemoteListingFailed {
+        /// Remote target, e.g. user@host
+        target: String,
+        /// Path of listed directory
+        dir: String,
+    },
+    /// Input directory has no processes-* directories
+    #[error("Couldn't find any processes in {input_dir}")]
+    NoProcessesFound {
+        /// Path of input directory
+        input_dir: String,
+    },
+    /// Input directory has no data of a plugin, e.g. no cpu-N directories
+    #[error("Couldn't find any {plugin} data in {input_dir}")]
+    NoDataFound {
+        /// Name of plugin, e.g. memory
+        plugin: String,
+        /// Path of directory where data was expected
+        input_dir: String,
+    },
+    /// rrdtool exited with error
+    #[error("{}", describe_rrdtool_failure(*.status, .stderr))]
+    RrdtoolFailed {
+        /// Exit code, None if rrdtool was killed by signal
+        status: Option<i32>,
+        /// Standard output of rrdtool
+        stdout: String,
+        /// Standard error of rrdtool, usually explains the failure
+        stderr: String,
+    },
+    /// ssh couldn't connect to remote target or lost connection, i.e. it exited with 255,
+    /// so the remote command may not have been executed at all
+    #[error("{}", describe_remote_connection_failure(.target, .stderr))]
+    RemoteConnectionFailed {
+        /// Remote target, e.g. user@host
+        target: String,
+        /// Standard error of ssh, usually explains the failure
+        stderr: String,
+    },
+    /// rrdtool binary can't be executed, e.g. it's not installed
+    #[error("{}", describe_rrdtool_not_found(.command, .target))]
+    RrdtoolNotFound {
+        /// Executed command, e.g. rrdtool
+        command: String,
+        /// Remote target, e.g. user@host, None for local rrdtool
+        target: Option<String>,
+    },
+    /// Timespan, start or end of data range can't be parsed
+    #[error("Invalid timespan {timespan}: {reason}")]
+    InvalidTimespan {
+        /// Timespan as given by user
+        timespan: String,
+        /// Why it can't be parsed
+        reason: String,
+    },
+    /// Any other error, with its context
+    #[error("{0:#}")]
+    Other(anyhow::Error),
+}
+
+/// Describe failed rrdtool with its exit code and standard error, if any
+fn describe_rrdtool_failure(status: Option<i32>, stderr: &str) -> String {
+    let description = match status {
+        Some(status) => format!("rrdtool failed with status {}", status),
+        None => String::from("rrdtool was terminated by signal"),
+    };
+
+    match stderr.trim() {
+        "" => description,
+        stderr => format!("{}: {}", description, stderr),
+    }
+}
+
+/// Describe failed connection to remote target with standard error of ssh, if any
+fn describe_remote_connection_failure(target: &str, stderr: &str) -> String {
+    match stderr.trim() {
+        "" => format!("Failed to connect to {}", target),
+        stderr => format!("Failed to connect to {}: {}", target, stderr),
+    }
+}
+
+/// Describe missing rrdtool, with hint where to install it
+fn describe_rrdtool_not_found(command: &str, target: &Option<String>) -> String {
+    match target {
+        Some(target) => format!(
+            "{} not found on PATH of {}; install rrdtool there or pass --rrdtool-bin",
+            command, target
+        ),
+        None => format!(
+            "{} not found on PATH; install it or pass --rrdtool-bin",
+            command
+        ),
+    }
+}
+
+impl CggError {
+    /// Exit code when cgg failed
+    pub const EXIT_FAILURE: i32 = 1;
+    /// Exit code when cgg ran fine, but found no data to draw, e.g. no processes
+    pub const EXIT_NO_DATA: i32 = 2;
+
+    /// Returns exit code of cgg failed with this error, so scripts can tell apart
+    /// nothing to draw from real failures
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CggError::NoProcessesFound { .. } | CggError::NoDataFound { .. } => {
+                CggError::EXIT_NO_DATA
+            }
+            _ => CggError::EXIT_FAILURE,
+        }
+    }
+
+    /// Creates [`CggError::NoDataFound`] for given plugin
+    pub fn no_data_found(plugin: Plugins, input_dir: &str) -> CggError {
+        CggError::NoDataFound {
+            plugin: String::from(plugin.as_str()),
+            input_dir: String::from(input_dir),
+        }
+    }
+
+    /// Exit code of ssh when connection to remote target failed
+    pub const SSH_CONNECTION_FAILED: i32 = 255;
+
+    /// Creates error of command executed remotely with ssh, telling apart failed
+    /// connection, see [`CggError::SSH_CONNECTION_FAILED`], from failed rrdtool
+    pub fn remote_failed(target: String, output: &Output) -> CggError {
+        match output.status.code() {
+            Some(CggError::SSH_CONNECTION_FAILED) => CggError::RemoteConnectionFailed {
+                target,
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            },
+            _ => CggError::rrdtool_failed(output),
+        }
+    }
+
+    /// Creates [`CggError::RrdtoolFailed`] keeping output of failed rrdtool
+    pub fn rrdtool_failed(output: &Output) -> CggError {
+        CggError::RrdtoolFailed {
+            status: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+}
+
+/// Recover typed error from context chain, other errors are kept in [`CggError::Other`]
+impl From<anyhow::Error> for CggError {
+    fn from(error: anyhow::Error) -> CggError {
+        match error.downcast::<CggError>() {
+            Ok(error) => error,
+            Err(error) => CggError::Other(error),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::config::Config;
+    use super::*;
+    use anyhow::{Context, Result};
+    use clap::{load_yaml, App};
+    use tempfile::TempDir;
+
+    #[test]
+    fn cgg_error_from_context_chain() -> Result<()> {
+        let error = Err::<(), CggError>(CggError::NoProcessesFound {
+            input_dir: String::from("/some/path"),
+        })
+        .context("Failed \"process\" plugin")
+        .context("Failed to execute plugins")
+        .unwrap_err();
+
+        let error = CggError::from(error);
+        assert_eq!(CggError::EXIT_NO_DATA, error.exit_code());
+        assert!(matches!(
+            error,
+            CggError::NoProcessesFound { input_dir } if input_dir == "/some/path"
+        ));
+
+        let error = CggError::from(anyhow::anyhow!("Missing --input parameter"));
+        assert!(matches!(error, CggError::Other(_)));
+        assert_eq!("Missing --input parameter", error.to_string());
+        assert_eq!(CggError::EXIT_FAILURE, error.exit_code());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cgg_error_invalid_timespan() -> Result<()> {
+        let yaml = load_yaml!("cli.yml");
+
+        let cli = App::from(yaml).get_matches_from(vec![
+            "cgg",
+            "-i",
+            "/some/local",
+            "-t",
+            "lasts 5 minutes",
+        ]);
+
+        match Config::new(&cli) {
+            Err(CggError::InvalidTimespan { timespan, .. }) => {
+                assert_eq!("lasts 5 minutes", timespan)
+            }
+            other => anyhow::bail!("Expected invalid timespan, got {:?}", other.err()),
+        }
+
+        let cli = App::from(yaml).get_matches_from(vec![
+            "cgg",
+            "-i",
+            "/some/local",
+            "--start",
+            "tomorrow",
+            "--end",
+            "now",
+        ]);
+
+        assert!(matches!(
+            Config::new(&cli),
+            Err(CggError::InvalidTimespan { timespan, .. }) if timespan == "tomorrow"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cgg_error_no_processes_found() -> Result<()> {
+        let temp = TempDir::new()?;
+        let yaml = load_yaml!("cli.yml");
+
+        let cli = App::from(yaml).get_matches_from(vec![
+            "cgg",
+            "-i",
+            temp.path().to_str().unwrap(),
+            "-t",
+            "last 1 hour",
+            "--dry-run",
+        ]);
+
+        match super::super::run(Config::new(&cli)?) {
+            Err(CggError::NoProcessesFound { input_dir }) => {
+                assert_eq!(temp.path().to_str().unwrap(), input_dir)
+            }
+            other => anyhow::bail!("Expected no processes found, got {:?}", other.err()),
+        }
+
+        Ok(())
+    }
+}
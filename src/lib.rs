@@ -1,18 +1,61 @@
 pub mod config;
+pub mod config_file;
+pub mod cpu;
+pub mod df;
+pub mod error;
+pub mod manifest;
 pub mod memory;
 pub mod processes;
 pub mod rrdtool;
 
 use anyhow::{Context, Result};
 use config::Config;
+use config_file::ConfigFile;
+use error::CggError;
+use manifest::{Manifest, ManifestConfig};
+use rrdtool::command::SystemCommandRunner;
 use rrdtool::common::Rrdtool;
+use rrdtool::rrd_file::is_rrd_file;
+use std::path::PathBuf;
 
-pub fn run(config: Config) -> Result<()> {
-    Rrdtool::new(config.input_dir)
+/// Generate graphs, as [`run_collecting`] does, discarding paths of generated files
+pub fn run(config: Config) -> Result<(), CggError> {
+    run_collecting(config).map(|_| ())
+}
+
+/// Generate graphs and return paths of all generated files, including ones split
+/// with `_N` suffix. Nothing is generated in dry run, so the returned list is empty.
+pub fn run_collecting(config: Config) -> Result<Vec<PathBuf>, CggError> {
+    let mut plugins = config
+        .plugins_config
+        .data
+        .keys()
+        .map(|plugin| String::from(plugin.as_str()))
+        .collect::<Vec<String>>();
+    plugins.sort();
+
+    let manifest_config = ManifestConfig {
+        input: String::from(config.input_dir.to_string_lossy()),
+        start: config.start,
+        end: config.end,
+        width: config.width,
+        height: config.height,
+        plugins,
+    };
+
+    let mut rrdtool = Rrdtool::new(config.input_dir)?;
+
+    rrdtool
+        .with_command(String::from(config.rrdtool_bin))
+        .context("Failed with_command")?
         .with_subcommand(String::from("graph"))
         .context("Failed with_subcommand")?
         .with_output_file(String::from(config.output_filename))
         .context("Failed with_output_file")?
+        .with_image_format(config.image_format)
+        .context("Failed with_image_format")?
+        .with_export(config.export)
+        .context("Failed with_export")?
         .with_start(config.start)
         .context("Failed with_start")?
         .with_end(config.end)
@@ -21,10 +64,206 @@ pub fn run(config: Config) -> Result<()> {
         .context("Failed with_width")?
         .with_height(config.height)
         .context("Failed with_height")?
-        .with_plugins(config.plugins_config)
-        .context("Failed to execute plugins")?
-        .exec()
-        .context("Failed to execute rrdtool")?;
+        .with_plugin_sizes(config.plugin_sizes)
+        .context("Failed with_plugin_sizes")?
+        .with_nan_fill(config.nan_fill)
+        .context("Failed with_nan_fill")?
+        .with_cf(config.cf)
+        .context("Failed with_cf")?
+        .with_ds(config.ds.map(String::from))
+        .context("Failed with_ds")?
+        .with_reduce(config.reduce)
+        .context("Failed with_reduce")?
+        .with_sparkline(config.sparkline)
+        .context("Failed with_sparkline")?
+        .with_transfer(config.transfer)
+        .context("Failed with_transfer")?
+        .with_scp_retries(config.scp_retries)
+        .context("Failed with_scp_retries")?
+        .with_title_time_format(config.title_time_format.map(String::from))
+        .context("Failed with_title_time_format")?
+        .with_overview(config.overview)
+        .context("Failed with_overview")?
+        .with_max_data_age(config.max_data_age, config.now)
+        .context("Failed with_max_data_age")?
+        .with_legend_columns(config.legend_columns)
+        .context("Failed with_legend_columns")?
+        .with_legend(
+            !config.no_legend,
+            config.legend_position,
+            config.legend_direction,
+        )
+        .context("Failed with_legend")?
+        .with_step(config.step)
+        .context("Failed with_step")?
+        .with_style(config.style)
+        .context("Failed with_style")?
+        .with_statistics(config.statistics)
+        .context("Failed with_statistics")?
+        .with_show_max(config.show_max)
+        .context("Failed with_show_max")?
+        .with_trend(config.trend)
+        .context("Failed with_trend")?
+        .with_combine(config.combine)
+        .context("Failed with_combine")?
+        .with_y_limits(config.y_min, config.y_max, config.rigid)
+        .context("Failed with_y_limits")?
+        .with_right_axis(
+            config.right_axis,
+            config.right_axis_label.map(String::from),
+            config.right_axis_plugins,
+        )
+        .context("Failed with_right_axis")?
+        .with_show_rrd_step(config.show_rrd_step)
+        .context("Failed with_show_rrd_step")?
+        .with_dry_run(config.dry_run)
+        .context("Failed with_dry_run")?
+        .with_embed_command(config.embed_command)
+        .context("Failed with_embed_command")?
+        .with_print_command(config.print_command)
+        .context("Failed with_print_command")?
+        .with_force(config.force)
+        .context("Failed with_force")?
+        .with_ssh_key(config.ssh_key.map(std::path::PathBuf::from))
+        .context("Failed with_ssh_key")?
+        .with_ssh_multiplex(config.ssh_multiplex)
+        .context("Failed with_ssh_multiplex")?
+        .with_offline(config.offline)
+        .context("Failed with_offline")?
+        .with_titles(
+            config.title.map(String::from),
+            config
+                .plugin_titles
+                .iter()
+                .map(|(plugin, title)| (*plugin, String::from(*title)))
+                .collect(),
+        )
+        .context("Failed with_titles")?
+        .with_auto_title(config.auto_title)
+        .context("Failed with_auto_title")?;
+
+    if let Some(palette) = config.palette {
+        rrdtool
+            .with_palette(palette)
+            .context("Failed with_palette")?;
+    }
+
+    for hrule in &config.hrules {
+        rrdtool.with_hrule(hrule).context("Failed with_hrule")?;
+    }
+
+    if let Some(watermark) = config.watermark {
+        rrdtool
+            .with_watermark(watermark)
+            .context("Failed with_watermark")?;
+    }
+
+    if let Some(label) = config.vertical_label {
+        rrdtool
+            .with_vertical_label(String::from(label))
+            .context("Failed with_vertical_label")?;
+    }
+
+    let paths = match config.compare_hosts {
+        Some(compare_hosts) => rrdtool
+            .with_compare_hosts(compare_hosts)
+            .context("Failed to compare hosts")?,
+        None if is_rrd_file(config.input_dir) => {
+            rrdtool.with_rrd_file().context("Failed to draw rrd file")?
+        }
+        None => rrdtool
+            .with_inputs(&config.inputs)
+            .context("Failed with_inputs")?
+            .with_plugins(config.plugins_config)
+            .context("Failed to execute plugins")?,
+    }
+    .exec()
+    .context("Failed to execute rrdtool")?;
+
+    let dry_run = config.dry_run;
+
+    // Nothing is generated in dry run, so there is nothing to describe
+    if let Some(manifest) = config.manifest.filter(|_| !dry_run) {
+        Manifest::new(
+            manifest_config,
+            rrdtool.get_version().ok(),
+            &rrdtool.get_input_files(),
+            &paths,
+        )
+        .context("Failed to create manifest")?
+        .write(std::path::Path::new(manifest))
+        .context("Failed to write manifest")?;
+    }
+
+    Ok(paths)
+}
+
+/// Print names of processes or memory types found in input directory, one per line,
+/// sorted case-insensitively. rrdtool is not executed, so time range is not needed.
+pub fn list(cli: &clap::ArgMatches, file: Option<&ConfigFile>) -> Result<(), CggError> {
+    let input = Config::get_input(cli, file).context("Missing --input parameter")?;
+
+    let mut rrdtool = Rrdtool::new(std::path::Path::new(input))?;
+
+    rrdtool
+        .with_ssh_key(cli.value_of("ssh_key").map(std::path::PathBuf::from))
+        .context("Failed with_ssh_key")?
+        .with_ssh_multiplex(cli.is_present("ssh_multiplex"))
+        .context("Failed with_ssh_multiplex")?
+        .with_offline(cli.is_present("offline"))
+        .context("Failed with_offline")?;
+
+    let mut names = match cli.is_present("list_memory_types") {
+        true => memory::memory_plugin::get_memory_types_names(&rrdtool)
+            .context("Failed to list memory types")?,
+        false => processes::processes_names::get(&rrdtool).context("Failed to list processes")?,
+    };
+
+    names.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then(a.cmp(b)));
+
+    for name in names {
+        println!("{}", name);
+    }
+
+    Ok(())
+}
+
+/// Print plugins which have data in input directory, one per line,
+/// followed by collectd plugins which are found but can't be drawn
+pub fn detect(cli: &clap::ArgMatches, file: Option<&ConfigFile>) -> Result<(), CggError> {
+    let input = Config::get_input(cli, file).context("Missing --input parameter")?;
+
+    let mut rrdtool = Rrdtool::new(std::path::Path::new(input))?;
+
+    rrdtool
+        .with_ssh_key(cli.value_of("ssh_key").map(std::path::PathBuf::from))
+        .context("Failed with_ssh_key")?
+        .with_ssh_multiplex(cli.is_present("ssh_multiplex"))
+        .context("Failed with_ssh_multiplex")?
+        .with_offline(cli.is_present("offline"))
+        .context("Failed with_offline")?;
+
+    let plugins = rrdtool::detect::detect_plugins(&rrdtool).context("Failed to detect plugins")?;
+    let unsupported = rrdtool::detect::detect_unsupported_plugins(&rrdtool)
+        .context("Failed to detect plugins")?;
+
+    for plugin in plugins {
+        println!("{}", plugin.as_str());
+    }
+
+    for plugin in unsupported {
+        println!("{} (not supported)", plugin);
+    }
+
+    Ok(())
+}
+
+/// Print installed rrdtool version and supported features
+pub fn probe() -> Result<(), CggError> {
+    let report = rrdtool::probe::probe(&SystemCommandRunner, "rrdtool")
+        .context("Failed to probe rrdtool")?;
+
+    print!("{}", report);
 
     Ok(())
 }
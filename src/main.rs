@@ -1,6 +1,9 @@
 use cgg::config::Config;
+use cgg::config_file::ConfigFile;
+use cgg::error::CggError;
 use clap::{load_yaml, App};
-use log::error;
+use log::{error, LevelFilter};
+use std::path::PathBuf;
 
 const EXAMPLES: &str = &"EXAMPLES:
     ./cgg -i /var/lib/collectd/marcin-manjaro/ -t \"last 4 hours\"\n
@@ -9,32 +12,91 @@ const EXAMPLES: &str = &"EXAMPLES:
     ./cgg -i marcin@192.168.0.163:/var/lib/collectd/marcin-manjaro/ \\
 -t \"last 1 hour\" --processes \"firefox,spotify,visual studio code\"\n
     ./cgg -i marcin@localhost:/var/lib/collectd/marcin-manjaro/ \\
--p processes,memory -t \"last 1 hour\" --memory buffered,free,cached,used";
+-p processes,memory -t \"last 1 hour\" --memory buffered,free,cached,used\n
+    ./cgg probe";
 
 fn main() {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .format_timestamp(None)
-        .init();
-
     let yaml = load_yaml!("cli.yml");
     let cli = App::from(yaml).after_help(EXAMPLES).get_matches();
 
-    let config = match Config::new(&cli) {
+    init_logger(&cli);
+
+    if cli.subcommand_matches("probe").is_some() {
+        std::process::exit(match cgg::probe() {
+            Ok(()) => 0,
+            Err(err) => {
+                error!("Error: {}", err);
+                1
+            }
+        })
+    }
+
+    let file = match cli.value_of("config").map(PathBuf::from) {
+        Some(path) => match ConfigFile::read(&path) {
+            Ok(file) => Some(file),
+            Err(err) => {
+                error!("Error: {:#}", err);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    if cli.is_present("detect") {
+        std::process::exit(exit_code(cgg::detect(&cli, file.as_ref())))
+    }
+
+    if cli.is_present("list_processes") || cli.is_present("list_memory_types") {
+        std::process::exit(exit_code(cgg::list(&cli, file.as_ref())))
+    }
+
+    let config = match &file {
+        Some(file) => Config::from_file(&cli, file),
+        None => Config::new(&cli),
+    };
+
+    let config = match config {
         Ok(config) => config,
         Err(err) => {
-            error!("Error: {:?}\n", err);
+            error!("Error: {}\n", err);
             help();
             std::process::exit(1);
         }
     };
 
-    std::process::exit(match cgg::run(config) {
+    std::process::exit(exit_code(cgg::run(config)))
+}
+
+/// Log error and return exit code: 0 on success, 2 when there was no data to draw,
+/// 1 for other errors
+fn exit_code(result: Result<(), CggError>) -> i32 {
+    match result {
         Ok(()) => 0,
         Err(err) => {
-            error!("Error: {:?}", err);
-            1
+            error!("Error: {}", err);
+            err.exit_code()
+        }
+    }
+}
+
+/// Log info messages, unless level is chosen with --quiet or --verbose, which override RUST_LOG
+fn init_logger(cli: &clap::ArgMatches) {
+    let mut builder = match (cli.is_present("quiet"), cli.occurrences_of("verbose")) {
+        (false, 0) => {
+            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        }
+        (quiet, verbose) => {
+            let mut builder = env_logger::Builder::new();
+            builder.filter_level(match (quiet, verbose) {
+                (true, _) => LevelFilter::Error,
+                (false, 1) => LevelFilter::Debug,
+                (false, _) => LevelFilter::Trace,
+            });
+            builder
         }
-    })
+    };
+
+    builder.format_timestamp(None).init();
 }
 
 fn help() {
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Description of a run, written next to generated graphs for reproducibility
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    /// UNIX timestamp of the moment manifest was created
+    pub generated_at: u64,
+    /// Version of rrdtool which generated graphs, if known
+    pub rrdtool_version: Option<String>,
+    /// Resolved configuration of the run
+    pub config: ManifestConfig,
+    /// rrd files used by graphs
+    pub inputs: Vec<ManifestInput>,
+    /// Generated graphs
+    pub outputs: Vec<ManifestOutput>,
+}
+
+/// Resolved configuration of the run
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestConfig {
+    /// Local or remote path to collectd data
+    pub input: String,
+    /// Start timestamp of graphs
+    pub start: u64,
+    /// End timestamp of graphs
+    pub end: u64,
+    /// Width of graphs
+    pub width: u32,
+    /// Height of graphs
+    pub height: u32,
+    /// Names of plugins
+    pub plugins: Vec<String>,
+}
+
+/// rrd file used by graphs
+#[derive(Debug, Serialize)]
+pub struct ManifestInput {
+    /// Path to rrd file
+    pub path: String,
+    /// UNIX timestamp of last modification, unknown for remote files
+    pub modified: Option<u64>,
+}
+
+/// Generated graph
+#[derive(Debug, Serialize)]
+pub struct ManifestOutput {
+    /// Path to the graph
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the file
+    pub sha256: String,
+}
+
+impl Manifest {
+    /// Build manifest of a finished run
+    ///
+    /// # Arguments
+    /// * `config` - resolved configuration of the run
+    /// * `rrdtool_version` - version of rrdtool, if known
+    /// * `inputs` - paths to rrd files used by graphs
+    /// * `outputs` - paths to generated graphs
+    ///
+    pub fn new(
+        config: ManifestConfig,
+        rrdtool_version: Option<String>,
+        inputs: &[String],
+        outputs: &[PathBuf],
+    ) -> Result<Manifest> {
+        let outputs = outputs
+            .iter()
+            .map(|path| {
+                let content =
+                    std::fs::read(path).context(format!("Failed to read {}", path.display()))?;
+
+                Ok(ManifestOutput {
+                    path: String::from(path.to_string_lossy()),
+                    size: content.len() as u64,
+                    sha256: format!("{:x}", Sha256::digest(&content)),
+                })
+            })
+            .collect::<Result<Vec<ManifestOutput>>>()?;
+
+        let inputs = inputs
+            .iter()
+            .map(|path| ManifestInput {
+                path: String::from(path),
+                modified: get_modified(Path::new(path)),
+            })
+            .collect();
+
+        Ok(Manifest {
+            generated_at: get_timestamp(SystemTime::now()).unwrap_or(0),
+            rrdtool_version,
+            config,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Write manifest as JSON to given path
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize manifest")?;
+
+        std::fs::write(path, json + "\n")
+            .context(format!("Failed to write manifest {}", path.display()))
+    }
+}
+
+/// Returns UNIX timestamp of last modification of local file
+fn get_modified(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(get_timestamp)
+}
+
+fn get_timestamp(time: SystemTime) -> Option<u64> {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    pub fn manifest_lists_outputs() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        let outputs = vec![temp.path().join("out_1.png"), temp.path().join("out_2.png")];
+        std::fs::write(&outputs[0], b"abc")?;
+        std::fs::write(&outputs[1], b"graph")?;
+
+        let input = temp.path().join("ps_rss.rrd");
+        std::fs::write(&input, b"rrd")?;
+
+        let config = ManifestConfig {
+            input: String::from(temp.path().to_str().unwrap()),
+            start: 1600000000,
+            end: 1600003600,
+            width: 1024,
+            height: 768,
+            plugins: vec![String::from("processes")],
+        };
+
+        let manifest = Manifest::new(
+            config,
+            Some(String::from("1.7.2")),
+            &[String::from(input.to_str().unwrap())],
+            &outputs,
+        )?;
+
+        let path = temp.path().join("manifest.json");
+        manifest.write(&path)?;
+
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+
+        assert_eq!(1600000000, json["config"]["start"]);
+        assert_eq!(1600003600, json["config"]["end"]);
+        assert_eq!("1.7.2", json["rrdtool_version"]);
+        assert!(json["inputs"][0]["modified"].is_u64());
+
+        let outputs = json["outputs"].as_array().unwrap();
+        assert_eq!(2, outputs.len());
+        assert_eq!(3, outputs[0]["size"]);
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            outputs[0]["sha256"]
+        );
+        assert_eq!(5, outputs[1]["size"]);
+        assert_eq!(64, outputs[1]["sha256"].as_str().unwrap().len());
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 use super::super::config;
 use super::memory_type::MemoryType;
+use super::memory_unit::MemoryUnit;
 use super::rrdtool::common::Plugins;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use std::str::FromStr;
 
 /// Data used by memory plugin
 ///
@@ -17,11 +19,22 @@ use anyhow::{Context, Result};
 pub struct MemoryData {
     /// Types of data to visualize on graph
     pub memory_types: Vec<MemoryType>,
+    /// Unit which values are converted to, drawn in bytes if None
+    pub unit: Option<MemoryUnit>,
+    /// Order in which types are stacked, types not listed are stacked after them
+    pub order: Vec<MemoryType>,
+    /// Draw line of sum of all drawn types
+    pub total: bool,
 }
 
 impl MemoryData {
     pub fn new(memory_types: Vec<MemoryType>) -> MemoryData {
-        MemoryData { memory_types }
+        MemoryData {
+            memory_types,
+            unit: None,
+            order: Vec::new(),
+            total: false,
+        }
     }
 }
 
@@ -36,13 +49,31 @@ impl<'a> config::Config<'a> {
         cli: &'a clap::ArgMatches,
         plugins: &[Plugins],
     ) -> Result<Option<MemoryData>> {
-        Ok(match plugins.contains(&Plugins::Memory) {
-            true => Some(MemoryData::new(
-                config::Config::get_memory_types(cli)
-                    .context("Failed to get memory types to draw")?,
-            )),
-            false => None,
-        })
+        if !plugins.contains(&Plugins::Memory) {
+            return Ok(None);
+        }
+
+        let mut data = MemoryData::new(
+            config::Config::get_memory_types(cli).context("Failed to get memory types to draw")?,
+        );
+
+        data.unit = match cli.value_of("memory_unit") {
+            Some(unit) => Some(
+                MemoryUnit::from_str(unit)
+                    .map_err(|_| anyhow!("Cannot parse memory-unit argument {}", unit))?,
+            ),
+            None => None,
+        };
+
+        data.order = match cli.value_of("memory_order") {
+            Some(order) => config::Config::get_vec_of_type_from_cli::<MemoryType>(order)
+                .context(format!("Cannot parse memory order {}", order))?,
+            None => Vec::new(),
+        };
+
+        data.total = cli.is_present("memory_total");
+
+        Ok(Some(data))
     }
 }
 
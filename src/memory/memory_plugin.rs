@@ -1,12 +1,17 @@
+use super::error::CggError;
 use super::memory_data::MemoryData;
 use super::memory_type::MemoryType;
-use super::rrdtool::common::{Plugin, Rrdtool, Target};
-use super::rrdtool::remote;
+use super::rrdtool::common::{Plugin, Plugins, Rrdtool, Target};
+use super::rrdtool::graph_element::GraphElement;
 
+use std::fs::read_dir;
 use std::path::Path;
 
-use anyhow::{bail, Context, Result};
-use log::{debug, trace};
+use anyhow::{Context, Result};
+use log::{debug, trace, warn};
+
+/// Color of line with sum of memory types, not taken from palette so it stands out
+const TOTAL_COLOR: &str = "#000000";
 
 impl Plugin<&MemoryData> for Rrdtool {
     fn enter_plugin(&mut self, data: &MemoryData) -> Result<&mut Self> {
@@ -15,95 +20,182 @@ impl Plugin<&MemoryData> for Rrdtool {
 
         let memory_dir = Path::new(self.input_dir.as_str()).join("memory");
 
-        verify_data_files_exist(
-            self.target,
-            &memory_dir,
-            &data.memory_types,
-            &self.username,
-            &self.hostname,
-        )
-        .context("Unable to find expected files")?;
-
-        trace!("All expected files exist");
-
-        self.graph_args.new_graph();
-
-        for i in 0..data.memory_types.len() {
-            self.graph_args.push(
-                data.memory_types[i].to_string().as_str(),
-                Rrdtool::COLORS[i],
-                5,
-                memory_dir
-                    .join(data.memory_types[i].to_filename())
-                    .to_str()
-                    .unwrap(),
+        let missing = find_missing_data_files(self, &memory_dir, &data.memory_types)
+            .context("Unable to find expected files")?;
+
+        if !missing.is_empty() && missing.len() == data.memory_types.len() {
+            return Err(anyhow::Error::new(CggError::no_data_found(
+                Plugins::Memory,
+                memory_dir.to_str().unwrap(),
+            ))
+            .context(format!(
+                "None of memory types {} found",
+                join_memory_types(&missing)
+            )));
+        }
+
+        if !missing.is_empty() {
+            warn!(
+                "Memory types not found in {}: {}",
+                memory_dir.to_str().unwrap(),
+                join_memory_types(&missing)
             );
         }
 
+        let mut memory_types = data
+            .memory_types
+            .iter()
+            .filter(|memory_type| !missing.contains(memory_type))
+            .collect::<Vec<&MemoryType>>();
+
+        // Stable sort keeps types without explicit order in order they were given
+        memory_types.sort_by_key(|memory_type| {
+            data.order
+                .iter()
+                .position(|ordered| ordered == *memory_type)
+                .unwrap_or(data.order.len())
+        });
+
+        trace!("Drawing memory types {:?}", memory_types);
+
+        self.graph_args.new_plugin_graph();
+        let first_color = self.graph_args.first_color_index();
+
+        self.graph_args.unit = data
+            .unit
+            .map(|unit| (String::from(unit.as_str()), unit.divisor()));
+
+        let mut vnames = Vec::new();
+
+        for (i, memory_type) in memory_types.iter().enumerate() {
+            let color = self.get_color(first_color + i);
+
+            vnames.push(self.graph_args.push(
+                memory_type.to_string().as_str(),
+                &color,
+                GraphElement::Stack,
+                memory_dir.join(memory_type.to_filename()).to_str().unwrap(),
+            ));
+        }
+
+        if data.total {
+            self.graph_args.push_cdef(
+                "total",
+                TOTAL_COLOR,
+                GraphElement::Line(3),
+                &build_total_expression(&vnames),
+            );
+        }
+
+        self.graph_args.unit = None;
+
         trace!("Memory plugin exit");
 
         Ok(self)
     }
 }
 
-fn verify_data_files_exist(
-    target: Target,
+/// Returns memory types without rrd file in memory directory
+fn find_missing_data_files(
+    rrdtool: &Rrdtool,
     memory_dir: &Path,
     memory_types: &[MemoryType],
-    username: &Option<String>,
-    hostname: &Option<String>,
-) -> Result<()> {
-    match target {
-        Target::Local => verify_data_files_exist_local(memory_dir, memory_types),
-        Target::Remote => verify_data_files_exist_remote(
-            memory_dir,
-            memory_types,
-            &username.as_ref().unwrap(),
-            &hostname.as_ref().unwrap(),
-        ),
+) -> Result<Vec<MemoryType>> {
+    match rrdtool.target {
+        Target::Local => Ok(find_missing_data_files_local(memory_dir, memory_types)),
+        Target::Remote => find_missing_data_files_remote(rrdtool, memory_dir, memory_types),
     }
 }
 
-fn verify_data_files_exist_remote(
+fn find_missing_data_files_remote(
+    rrdtool: &Rrdtool,
     memory_dir: &Path,
     memory_types: &[MemoryType],
-    username: &str,
-    hostname: &str,
-) -> Result<()> {
-    let files = remote::ls(memory_dir.to_str().unwrap(), username, hostname).context(format!(
-        "Failed to list remote files in: {}",
-        memory_dir.to_str().unwrap()
-    ))?;
-
-    match memory_types
-        .iter()
-        .map(|memory_type| files.contains(&String::from(memory_type.to_filename())))
-        .all(|element| element)
-    {
-        true => Ok(()),
-        false => bail!(
-            "Some foile for memory measurements doesn't exist in {}",
+) -> Result<Vec<MemoryType>> {
+    let files = rrdtool
+        .list_remote_dir(memory_dir.to_str().unwrap())
+        .context(format!(
+            "Failed to list remote files in: {}",
             memory_dir.to_str().unwrap()
-        ),
-    }
+        ))?;
+
+    Ok(memory_types
+        .iter()
+        .filter(|memory_type| !files.contains(&String::from(memory_type.to_filename())))
+        .copied()
+        .collect())
 }
 
-fn verify_data_files_exist_local(memory_dir: &Path, memory_types: &[MemoryType]) -> Result<()> {
-    match memory_types
+fn find_missing_data_files_local(
+    memory_dir: &Path,
+    memory_types: &[MemoryType],
+) -> Vec<MemoryType> {
+    memory_types
         .iter()
-        .map(|memory_type| memory_dir.join(memory_type.to_filename()).exists())
-        .all(|element| element)
-    {
-        true => Ok(()),
-        false => bail!(
-            "Some file for memory measurements doesn't exist in {}",
-            memory_dir.to_str().unwrap()
-        ),
+        .filter(|memory_type| !memory_dir.join(memory_type.to_filename()).exists())
+        .copied()
+        .collect()
+}
+
+/// Returns names of memory types with rrd file in memory directory, including types
+/// not supported by the plugin, e.g. available for memory-available.rrd
+pub fn get_memory_types_names(rrdtool: &Rrdtool) -> Result<Vec<String>> {
+    let memory_dir = Path::new(rrdtool.input_dir.as_str()).join("memory");
+
+    let files = match rrdtool.target {
+        Target::Local => read_dir(&memory_dir)
+            .context(format!(
+                "Failed to read directory: {}",
+                memory_dir.to_str().unwrap()
+            ))?
+            .filter_map(|entry| {
+                entry
+                    .ok()
+                    .and_then(|entry| entry.file_name().to_str().map(String::from))
+            })
+            .collect::<Vec<String>>(),
+        Target::Remote => rrdtool
+            .list_remote_dir(memory_dir.to_str().unwrap())
+            .context(format!(
+                "Failed to list remote files in: {}",
+                memory_dir.to_str().unwrap()
+            ))?,
+    };
+
+    Ok(files
+        .iter()
+        .filter_map(|file| {
+            file.strip_prefix("memory-")
+                .and_then(|file| file.strip_suffix(".rrd"))
+        })
+        .map(String::from)
+        .collect())
+}
+
+/// Returns RPN expression summing all variables, e.g. used,cached,free,+,+
+fn build_total_expression(vnames: &[String]) -> String {
+    let mut expression = vnames.join(",");
+
+    for _ in 1..vnames.len() {
+        expression.push_str(",+");
     }
+
+    expression
+}
+
+/// Returns names of memory types separated by comma
+fn join_memory_types(memory_types: &[MemoryType]) -> String {
+    memory_types
+        .iter()
+        .map(|memory_type| memory_type.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
 }
 
 #[cfg(test)]
 pub mod tests {
+    use super::super::memory_unit::MemoryUnit;
+    use super::super::rrdtool::common::tests::captured_logs;
     use super::*;
     use std::fs::{create_dir, File};
     use std::path::PathBuf;
@@ -125,7 +217,7 @@ pub mod tests {
     }
 
     #[test]
-    fn verify_data_files_exist_local() -> Result<()> {
+    fn find_missing_data_files_local() -> Result<()> {
         let temp = TempDir::new().unwrap();
 
         let mem_path = create_temp_memory_files(&temp)?;
@@ -133,17 +225,184 @@ pub mod tests {
         let memory_types_ok = vec![MemoryType::Free, MemoryType::Cached, MemoryType::Used];
         let memory_types_nok = vec![MemoryType::Used, MemoryType::SlabRecl];
 
-        let memory_types_ok = super::verify_data_files_exist_local(&mem_path, &memory_types_ok);
-        let memory_types_nok = super::verify_data_files_exist_local(&mem_path, &memory_types_nok);
+        let memory_types_ok = super::find_missing_data_files_local(&mem_path, &memory_types_ok);
+        let memory_types_nok = super::find_missing_data_files_local(&mem_path, &memory_types_nok);
+
+        assert!(memory_types_ok.is_empty());
+        assert_eq!(vec![MemoryType::SlabRecl], memory_types_nok);
 
-        assert!(memory_types_ok.is_ok());
-        assert!(memory_types_nok.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn get_memory_types_names() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let mem_path = create_temp_memory_files(&temp)?;
+        File::create(mem_path.join("memory-available.rrd"))?;
+        File::create(mem_path.join("README"))?;
+
+        let mut names = super::get_memory_types_names(&Rrdtool::new(temp.path())?)?;
+        names.sort();
+
+        assert_eq!(vec!["available", "cached", "free", "used"], names);
 
         Ok(())
     }
 
     #[test]
-    fn verify_data_files_exist_remote() -> Result<()> {
+    fn memory_plugin_missing_types() -> Result<()> {
+        captured_logs();
+
+        let temp = TempDir::new().unwrap();
+        create_temp_memory_files(&temp)?;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&MemoryData::new(vec![
+            MemoryType::Used,
+            MemoryType::SlabUnrecl,
+            MemoryType::Free,
+        ]))?;
+
+        // Missing type is skipped with warning
+        let areas = rrd.graph_args.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("AREA"))
+            .collect::<Vec<&String>>();
+        assert_eq!(2, areas.len());
+        assert!(areas[0].starts_with("AREA:used#"));
+        assert!(areas[1].starts_with("AREA:free#"));
+
+        assert!(captured_logs().contains(&format!(
+            "Memory types not found in {}: slab_unrecl",
+            temp.path().join("memory").display()
+        )));
+
+        // Nothing to draw
+        let mut rrd = Rrdtool::new(temp.path())?;
+        assert!(rrd
+            .enter_plugin(&MemoryData::new(vec![MemoryType::SlabRecl]))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_plugin_order() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        create_temp_memory_files(&temp)?;
+        File::create(temp.path().join("memory").join("memory-buffered.rrd"))?;
+
+        let mut data = MemoryData::new(vec![
+            MemoryType::Free,
+            MemoryType::Buffered,
+            MemoryType::Cached,
+            MemoryType::Used,
+        ]);
+        data.order = vec![MemoryType::Used, MemoryType::Cached];
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let names = |prefix: &str| {
+            rrd.graph_args.args[0]
+                .iter()
+                .filter(|arg| arg.starts_with(prefix))
+                .map(|arg| String::from(&arg[prefix.len()..arg.find(['=', '#']).unwrap()]))
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(vec!["used", "cached", "free", "buffered"], names("DEF:"));
+        assert_eq!(vec!["used", "cached", "free", "buffered"], names("AREA:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_plugin_total() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        create_temp_memory_files(&temp)?;
+
+        let mut data =
+            MemoryData::new(vec![MemoryType::Used, MemoryType::Cached, MemoryType::Free]);
+        data.total = true;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let graph = &rrd.graph_args.args[0];
+        assert!(graph.contains(&String::from("CDEF:total=used,cached,free,+,+")));
+        assert_eq!(
+            Some(&format!("LINE3:total{}:\"total\"", TOTAL_COLOR)),
+            graph.last()
+        );
+
+        // Total of converted values, only of types which were found
+        data.memory_types.push(MemoryType::SlabRecl);
+        data.unit = Some(MemoryUnit::MiB);
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        assert!(rrd.graph_args.args[0]
+            .contains(&String::from("CDEF:total=used_mib,cached_mib,free_mib,+,+")));
+
+        // Single type
+        let mut data = MemoryData::new(vec![MemoryType::Used]);
+        data.total = true;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        assert!(rrd.graph_args.args[0].contains(&String::from("CDEF:total=used")));
+
+        // No total by default
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&MemoryData::new(vec![MemoryType::Used]))?;
+
+        assert!(!rrd.graph_args.args[0]
+            .iter()
+            .any(|arg| arg.contains("total")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_plugin_unit() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        create_temp_memory_files(&temp)?;
+
+        for (unit, divisor) in &[
+            (MemoryUnit::KiB, "1024"),
+            (MemoryUnit::MiB, "1048576"),
+            (MemoryUnit::GiB, "1073741824"),
+        ] {
+            let mut data = MemoryData::new(vec![MemoryType::Used]);
+            data.unit = Some(*unit);
+
+            let mut rrd = Rrdtool::new(temp.path())?;
+            rrd.enter_plugin(&data)?;
+
+            let suffix = unit.as_str().to_lowercase();
+            let graph = &rrd.graph_args.args[0];
+            assert!(graph.contains(&format!("CDEF:used_{}=used,{},/", suffix, divisor)));
+            assert!(graph
+                .iter()
+                .any(|arg| arg.starts_with(&format!("AREA:used_{}#", suffix))));
+            assert_eq!(None, rrd.graph_args.unit);
+        }
+
+        // Without unit memory is drawn in bytes
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&MemoryData::new(vec![MemoryType::Used]))?;
+        assert!(!rrd.graph_args.args[0]
+            .iter()
+            .any(|arg| arg.starts_with("CDEF:")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_missing_data_files_remote() -> Result<()> {
         let temp = TempDir::new().unwrap();
 
         let mem_path = create_temp_memory_files(&temp)?;
@@ -151,22 +410,21 @@ pub mod tests {
         let memory_types_ok = vec![MemoryType::Free, MemoryType::Cached, MemoryType::Used];
         let memory_types_nok = vec![MemoryType::Used, MemoryType::SlabRecl];
 
-        let memory_types_ok = super::verify_data_files_exist_remote(
-            &mem_path,
-            &memory_types_ok,
-            &whoami::username(),
-            "localhost",
+        let input = format!(
+            "{}@localhost:{}",
+            whoami::username(),
+            temp.path().to_str().unwrap()
         );
+        let rrd = Rrdtool::new(Path::new(&input))?;
 
-        let memory_types_nok = super::verify_data_files_exist_remote(
-            &mem_path,
-            &memory_types_nok,
-            &whoami::username(),
-            "localhost",
-        );
+        let memory_types_ok =
+            super::find_missing_data_files_remote(&rrd, &mem_path, &memory_types_ok)?;
+
+        let memory_types_nok =
+            super::find_missing_data_files_remote(&rrd, &mem_path, &memory_types_nok)?;
 
-        assert!(memory_types_ok.is_ok());
-        assert!(memory_types_nok.is_err());
+        assert!(memory_types_ok.is_empty());
+        assert_eq!(vec![MemoryType::SlabRecl], memory_types_nok);
 
         Ok(())
     }
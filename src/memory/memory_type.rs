@@ -41,17 +41,18 @@ impl MemoryType {
 }
 
 /// Returns [`MemoryType`] from str, which allows to convert command line arguments
-/// to appropriate struct
+/// to appropriate struct. Besides collectd names common aliases are accepted, e.g.
+/// `buffers` or `slab`.
 impl FromStr for MemoryType {
     type Err = ();
 
     fn from_str(input: &str) -> Result<MemoryType, Self::Err> {
         match input {
-            "buffered" => Ok(MemoryType::Buffered),
-            "cached" => Ok(MemoryType::Cached),
+            "buffered" | "buffers" | "buffer" => Ok(MemoryType::Buffered),
+            "cached" | "cache" => Ok(MemoryType::Cached),
             "free" => Ok(MemoryType::Free),
-            "slab_recl" => Ok(MemoryType::SlabRecl),
-            "slab_unrecl" => Ok(MemoryType::SlabUnrecl),
+            "slab_recl" | "slab" | "reclaimable" | "slab_reclaimable" => Ok(MemoryType::SlabRecl),
+            "slab_unrecl" | "unreclaimable" | "slab_unreclaimable" => Ok(MemoryType::SlabUnrecl),
             "used" => Ok(MemoryType::Used),
             _ => Err(()),
         }
@@ -81,8 +82,13 @@ impl<'a> config::Config<'a> {
     ///
     pub fn get_memory_types(cli: &'a clap::ArgMatches) -> Result<Vec<MemoryType>> {
         match cli.value_of("memory") {
+            Some(value) if cli.is_present("ignore_case") => {
+                config::Config::get_vec_of_type_from_cli::<MemoryType>(&value.to_lowercase())
+            }
             Some(value) => config::Config::get_vec_of_type_from_cli::<MemoryType>(value),
-            None => anyhow::bail!("Didn't find memory in command line"),
+            None => {
+                anyhow::bail!("memory plugin selected but --memory not provided and no default")
+            }
         }
     }
 }
@@ -104,6 +110,41 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn memory_type_aliases() -> Result<()> {
+        let aliases = [
+            ("buffers", MemoryType::Buffered),
+            ("buffer", MemoryType::Buffered),
+            ("cache", MemoryType::Cached),
+            ("slab", MemoryType::SlabRecl),
+            ("reclaimable", MemoryType::SlabRecl),
+            ("slab_reclaimable", MemoryType::SlabRecl),
+            ("unreclaimable", MemoryType::SlabUnrecl),
+            ("slab_unreclaimable", MemoryType::SlabUnrecl),
+        ];
+
+        for (alias, memory_type) in aliases.iter() {
+            let parsed = MemoryType::from_str(alias).unwrap();
+            assert_eq!(*memory_type, parsed);
+            assert_eq!(memory_type.to_string(), parsed.to_string());
+            assert_eq!(memory_type.to_filename(), parsed.to_filename());
+        }
+
+        assert_eq!(
+            "slab_recl",
+            MemoryType::from_str("slab").unwrap().to_string()
+        );
+        assert!(MemoryType::from_str("slabs").is_err());
+        assert!(MemoryType::from_str("unrecl").is_err());
+
+        assert_eq!(
+            vec![MemoryType::Buffered, MemoryType::SlabRecl],
+            config::Config::get_vec_of_type_from_cli::<MemoryType>("buffers,slab")?
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn memory_type_file_names() -> Result<()> {
         assert!(&MemoryType::Buffered
@@ -0,0 +1,51 @@
+use std::str::FromStr;
+
+/// Binary unit which memory is drawn in, instead of bytes stored by collectd
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MemoryUnit {
+    KiB,
+    MiB,
+    GiB,
+}
+
+impl MemoryUnit {
+    /// Returns name of the unit as given in command line, also used as vertical label
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::memory::memory_unit::MemoryUnit;
+    ///
+    /// assert_eq!("MiB", MemoryUnit::MiB.as_str());
+    /// ```
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryUnit::KiB => "KiB",
+            MemoryUnit::MiB => "MiB",
+            MemoryUnit::GiB => "GiB",
+        }
+    }
+
+    /// Returns number of bytes in one unit
+    pub fn divisor(&self) -> u64 {
+        match self {
+            MemoryUnit::KiB => 1 << 10,
+            MemoryUnit::MiB => 1 << 20,
+            MemoryUnit::GiB => 1 << 30,
+        }
+    }
+}
+
+impl FromStr for MemoryUnit {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<MemoryUnit, Self::Err> {
+        match input {
+            "KiB" => Ok(MemoryUnit::KiB),
+            "MiB" => Ok(MemoryUnit::MiB),
+            "GiB" => Ok(MemoryUnit::GiB),
+            _ => Err(()),
+        }
+    }
+}
@@ -1,4 +1,6 @@
 pub mod memory_data;
 pub mod memory_plugin;
 pub mod memory_type;
+pub mod memory_unit;
+use super::error;
 use super::rrdtool;
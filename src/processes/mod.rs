@@ -1,4 +1,6 @@
+pub mod process_metric;
 pub mod processes_data;
 pub mod processes_names;
 pub mod processes_plugin;
+use super::error;
 use super::rrdtool;
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+/// Collectd collects multiple metrics of each process
+/// This enum allows to choose which one should be drawn on a graph
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ProcessMetric {
+    Rss,
+    CpuTime,
+    PageFaults,
+    StackSize,
+    Threads,
+}
+
+impl ProcessMetric {
+    /// Returns filename used to store data for particular process metric
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::processes::process_metric::ProcessMetric;
+    ///
+    /// assert_eq!("ps_cputime.rrd", ProcessMetric::CpuTime.to_filename());
+    /// ```
+    ///
+    pub fn to_filename(&self) -> &str {
+        match self {
+            ProcessMetric::Rss => "ps_rss.rrd",
+            ProcessMetric::CpuTime => "ps_cputime.rrd",
+            ProcessMetric::PageFaults => "ps_pagefaults.rrd",
+            ProcessMetric::StackSize => "ps_stacksize.rrd",
+            ProcessMetric::Threads => "ps_count.rrd",
+        }
+    }
+
+    /// Returns default label of vertical axis for particular process metric
+    pub fn vertical_label(&self) -> &'static str {
+        match self {
+            ProcessMetric::Rss => "RSS (bytes)",
+            ProcessMetric::CpuTime => "CPU time (µs/s)",
+            ProcessMetric::PageFaults => "page faults/s",
+            ProcessMetric::StackSize => "stack size (bytes)",
+            ProcessMetric::Threads => "threads",
+        }
+    }
+
+    /// Returns names of data sources summed on graph for particular process metric.
+    /// For CPU time it is user and system time, for page faults it is minor page faults,
+    /// threads are stored next to number of processes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::processes::process_metric::ProcessMetric;
+    ///
+    /// assert_eq!(&["user", "syst"], ProcessMetric::CpuTime.data_sources());
+    /// ```
+    ///
+    pub fn data_sources(&self) -> &'static [&'static str] {
+        match self {
+            ProcessMetric::Rss => &["value"],
+            ProcessMetric::CpuTime => &["user", "syst"],
+            ProcessMetric::PageFaults => &["minflt"],
+            ProcessMetric::StackSize => &["value"],
+            ProcessMetric::Threads => &["threads"],
+        }
+    }
+}
+
+/// Returns [`ProcessMetric`] from str, which allows to convert command line arguments
+/// to appropriate struct
+impl FromStr for ProcessMetric {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ProcessMetric, Self::Err> {
+        match input {
+            "rss" => Ok(ProcessMetric::Rss),
+            "cputime" => Ok(ProcessMetric::CpuTime),
+            "pagefaults" => Ok(ProcessMetric::PageFaults),
+            "stacksize" => Ok(ProcessMetric::StackSize),
+            "threads" => Ok(ProcessMetric::Threads),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn process_metric_string_conversion() {
+        assert_eq!(Ok(ProcessMetric::Rss), ProcessMetric::from_str("rss"));
+        assert_eq!(
+            Ok(ProcessMetric::CpuTime),
+            ProcessMetric::from_str("cputime")
+        );
+        assert_eq!(
+            Ok(ProcessMetric::PageFaults),
+            ProcessMetric::from_str("pagefaults")
+        );
+        assert_eq!(
+            Ok(ProcessMetric::StackSize),
+            ProcessMetric::from_str("stacksize")
+        );
+        assert_eq!(
+            Ok(ProcessMetric::Threads),
+            ProcessMetric::from_str("threads")
+        );
+        assert!(ProcessMetric::from_str("vmem").is_err());
+    }
+}
@@ -1,7 +1,12 @@
 use super::super::config;
+use super::process_metric::ProcessMetric;
 use super::rrdtool::common::{Plugins, Rrdtool};
+use super::rrdtool::graph_arguments::validate_color;
 
 use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::str::FromStr;
 
 /// Data used by processes plugin
 ///
@@ -18,15 +23,60 @@ use anyhow::{Context, Result};
 pub struct ProcessesData {
     /// Maximum number of processes in one graph
     pub max_processes: usize,
+    /// Maximum number of graphs processes are split into, unbounded if None
+    pub max_files: Option<usize>,
     /// List of processes to draw, if None all processes are drawn
     pub processes_to_draw: Option<Vec<String>>,
+    /// Regular expression which names of drawn processes must match,
+    /// narrows processes_to_draw if both are given
+    pub processes_regex: Option<Regex>,
+    /// Processes which are never drawn, given as names or glob patterns
+    pub exclude: Vec<String>,
+    /// Match names of processes regardless of case
+    pub ignore_case: bool,
+    /// Groups of processes, each drawn on a separate graph
+    pub groups: Vec<ProcessesGroup>,
+    /// Draw number of process instances instead of metric
+    pub process_count: bool,
+    /// Draw sum of all instances of a process, e.g. chrome and chrome.1, as one line
+    pub sum_duplicates: bool,
+    /// Draw only given number of processes with the highest peak of metric
+    pub top: Option<usize>,
+    /// Metric of processes to draw
+    pub metric: ProcessMetric,
+    /// Name of rrd file of drawn metric in directory of each process, with {metric} replaced
+    /// by default name of the file without extension, e.g. {metric}/value.rrd.
+    /// Default name of the file, e.g. ps_rss.rrd, is used if None.
+    pub file_template: Option<String>,
+    /// Colors of chosen processes, others get colors from the default palette
+    pub color_overrides: HashMap<String, String>,
+}
+
+/// Named group of processes matched by glob patterns
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessesGroup {
+    /// Name of the group, appended to output filename
+    pub name: String,
+    /// Shell-style glob patterns, e.g. nginx*
+    pub patterns: Vec<String>,
 }
 
 impl ProcessesData {
     pub fn new(max_processes: usize, processes_to_draw: Option<Vec<String>>) -> ProcessesData {
         ProcessesData {
             max_processes,
+            max_files: None,
             processes_to_draw,
+            processes_regex: None,
+            exclude: Vec::new(),
+            ignore_case: false,
+            groups: Vec::new(),
+            process_count: false,
+            sum_duplicates: false,
+            top: None,
+            metric: ProcessMetric::Rss,
+            file_template: None,
+            color_overrides: HashMap::new(),
         }
     }
 }
@@ -50,21 +100,105 @@ impl<'a> config::Config<'a> {
             None => None,
         };
 
-        let max_processes = match cli.value_of("max_processes") {
-            Some(max_processes) => Some(
-                max_processes
-                    .parse::<usize>()
-                    .context("Failed to parse max_processes argument")?,
+        let exclude = match cli.value_of("exclude_processes") {
+            Some(exclude) => parse_processes(String::from(exclude))
+                .context(format!("Cannot parse excluded processes {}", exclude))?,
+            None => Vec::new(),
+        };
+
+        let ignore_case = cli.is_present("ignore_case");
+
+        let processes_regex = match cli.value_of("processes_regex") {
+            Some(regex) => Some(
+                RegexBuilder::new(regex)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .context(format!("Invalid processes regex {}", regex))?,
             ),
+            None => None,
+        };
+
+        let top = match cli.value_of("top") {
+            Some(top) => match top
+                .parse::<usize>()
+                .context("Failed to parse top argument")?
+            {
+                0 => anyhow::bail!("Number of top processes must be positive"),
+                top => Some(top),
+            },
+            None => None,
+        };
+
+        let max_processes = match cli.value_of("max_processes") {
+            Some(max_processes) => match max_processes
+                .parse::<usize>()
+                .context("Failed to parse max_processes argument")?
+            {
+                0 => anyhow::bail!("Maximum number of processes on one graph must be positive"),
+                max_processes => Some(max_processes),
+            },
             None => Some(Rrdtool::COLORS.len()),
         };
 
+        let max_files = match cli.value_of("max_files") {
+            Some(max_files) => match max_files
+                .parse::<usize>()
+                .context("Failed to parse max-files argument")?
+            {
+                0 => anyhow::bail!("Maximum number of files must be positive"),
+                max_files => Some(max_files),
+            },
+            None => None,
+        };
+
+        let groups = match cli.values_of("group") {
+            Some(groups) => parse_groups(groups.collect::<Vec<&str>>())
+                .context("Cannot parse groups of processes")?,
+            None => Vec::new(),
+        };
+
+        let metric = match cli.value_of("process_metric") {
+            Some(metric) => match ProcessMetric::from_str(metric) {
+                Ok(metric) => metric,
+                Err(_) => anyhow::bail!("Unknown process metric {}", metric),
+            },
+            None => ProcessMetric::Rss,
+        };
+
+        let file_template = match cli.value_of("process_file") {
+            Some(template) if template.is_empty() || template.starts_with('/') => {
+                anyhow::bail!(
+                    "Process file must be relative to directory of process, got {}",
+                    template
+                )
+            }
+            Some(template) => Some(String::from(template)),
+            None => None,
+        };
+
+        let color_overrides = match cli.value_of("colors") {
+            Some(colors) => parse_colors(colors).context("Cannot parse colors of processes")?,
+            None => HashMap::new(),
+        };
+
         Ok(match plugins.contains(&Plugins::Processes) {
-            true => Some(ProcessesData::new(
-                max_processes.unwrap(),
-                processes_to_draw,
-            )),
-            false => unreachable!(),
+            true => {
+                let mut processes_data =
+                    ProcessesData::new(max_processes.unwrap(), processes_to_draw);
+                processes_data.max_files = max_files;
+                processes_data.processes_regex = processes_regex;
+                processes_data.exclude = exclude;
+                processes_data.ignore_case = ignore_case;
+                processes_data.groups = groups;
+                processes_data.process_count = cli.is_present("process_count");
+                processes_data.sum_duplicates = cli.is_present("sum_duplicates");
+                processes_data.top = top;
+                processes_data.metric = metric;
+                processes_data.file_template = file_template;
+                processes_data.color_overrides = color_overrides;
+                Some(processes_data)
+            }
+            false => None,
         })
     }
 }
@@ -77,6 +211,65 @@ fn parse_processes(processes: String) -> anyhow::Result<Vec<String>> {
         .collect::<Vec<String>>())
 }
 
+/// Return colors of processes from CLI provided list, e.g. firefox=#ff0000,chrome=#00ff00
+fn parse_colors(colors: &str) -> anyhow::Result<HashMap<String, String>> {
+    colors
+        .split(',')
+        .map(|color| match color.find('=') {
+            Some(index) if index > 0 => {
+                validate_color(&color[index + 1..])
+                    .context(format!("Invalid color of process {}", &color[..index]))?;
+
+                Ok((
+                    String::from(&color[..index]),
+                    String::from(&color[index + 1..]),
+                ))
+            }
+            _ => anyhow::bail!("Expected process=#rrggbb, got {}", color),
+        })
+        .collect()
+}
+
+/// Return vector of groups of processes from CLI provided list, e.g. web=nginx*,apache*
+fn parse_groups(groups: Vec<&str>) -> anyhow::Result<Vec<ProcessesGroup>> {
+    groups
+        .iter()
+        .map(|group| {
+            let (name, patterns) = match group.find('=') {
+                Some(index) => (&group[..index], &group[index + 1..]),
+                None => anyhow::bail!("Missing \"=\" in group {}", group),
+            };
+
+            if name.is_empty() || patterns.is_empty() {
+                anyhow::bail!("Missing name or patterns in group {}", group);
+            }
+
+            // Name of group is a part of output filename, so it can't point outside of it
+            if name.contains('/') || name.contains('\\') || name.contains("..") {
+                anyhow::bail!(
+                    "Group name {} can't contain path separators or \"..\"",
+                    name
+                );
+            }
+
+            let patterns = patterns
+                .split(',')
+                .map(String::from)
+                .collect::<Vec<String>>();
+
+            for pattern in &patterns {
+                glob::Pattern::new(pattern)
+                    .context(format!("Invalid pattern {} in group {}", pattern, name))?;
+            }
+
+            Ok(ProcessesGroup {
+                name: String::from(name),
+                patterns,
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -100,4 +293,72 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    pub fn get_processes_data_regex() -> Result<()> {
+        let get_processes_data = |regex: &str| {
+            let yaml = clap::load_yaml!("../cli.yml");
+            let cli = clap::App::from(yaml).get_matches_from(vec![
+                "cgg",
+                "-i",
+                "/some/local",
+                "--processes-regex",
+                regex,
+            ]);
+
+            config::Config::get_processes_data(&cli, &[Plugins::Processes])
+        };
+
+        let processes_data = get_processes_data("^(firefox|chrome)$")?.unwrap();
+        let regex = processes_data.processes_regex.unwrap();
+
+        assert!(regex.is_match("chrome"));
+        assert!(!regex.is_match("chrome-sandbox"));
+
+        let error = format!("{:?}", get_processes_data("^(firefox").unwrap_err());
+        assert!(error.contains("Invalid processes regex ^(firefox"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_colors() -> Result<()> {
+        let colors = super::parse_colors("firefox=#ff0000,chrome=#00ff00")?;
+
+        assert_eq!(2, colors.len());
+        assert_eq!("#ff0000", colors["firefox"]);
+        assert_eq!("#00ff00", colors["chrome"]);
+
+        assert!(super::parse_colors("firefox").is_err());
+        assert!(super::parse_colors("=#ff0000").is_err());
+        assert!(super::parse_colors("firefox=").is_err());
+        assert!(super::parse_colors("firefox=red").is_err());
+        assert_eq!(
+            "#ff000080",
+            super::parse_colors("firefox=#ff000080")?["firefox"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn parse_groups() -> Result<()> {
+        let groups = super::parse_groups(vec!["web=nginx*,apache*", "db=postgres*"])?;
+
+        assert_eq!(2, groups.len());
+        assert_eq!("web", groups[0].name);
+        assert_eq!(vec!("nginx*", "apache*"), groups[0].patterns);
+        assert_eq!("db", groups[1].name);
+        assert_eq!(vec!("postgres*"), groups[1].patterns);
+
+        assert!(super::parse_groups(vec!["nginx*,apache*"]).is_err());
+        assert!(super::parse_groups(vec!["=nginx*"]).is_err());
+        assert!(super::parse_groups(vec!["web="]).is_err());
+        assert!(super::parse_groups(vec!["web=[nginx"]).is_err());
+        assert!(super::parse_groups(vec!["../web=nginx*"]).is_err());
+        assert!(super::parse_groups(vec!["web/db=nginx*"]).is_err());
+        assert!(super::parse_groups(vec!["web\\db=nginx*"]).is_err());
+
+        Ok(())
+    }
 }
@@ -1,33 +1,35 @@
-use super::rrdtool::common::Target;
-use super::rrdtool::remote;
+use super::rrdtool::common::{Rrdtool, Target};
 
 use anyhow::{Context, Result};
-use log::trace;
+use log::{trace, warn};
 
 use std::fs::read_dir;
 
 /// Parse collectd results directory to get names of analysed processes
 ///
 /// # Arguments
-/// * `target` - [`Target`] enum describing, whether local or remote directory is provided
-/// * `input_dir` - path to local or remote directory
-/// * `username` - username to login in case of remote directory
-/// * `hostname` - hostname to use in case of remote directory
+/// * `rrdtool` - [`Rrdtool`] with local or remote input directory
 ///
-pub fn get<'a>(
-    target: Target,
-    input_dir: &'a str,
-    username: &Option<String>,
-    hostname: &Option<String>,
-) -> Result<Vec<String>> {
-    match target {
-        Target::Local => get_from_local(input_dir),
-        Target::Remote => get_from_remote(input_dir, username, hostname),
+pub fn get(rrdtool: &Rrdtool) -> Result<Vec<String>> {
+    get_with_prefix(rrdtool, "processes-")
+}
+
+/// Parse collectd results directory to get names of directories with given prefix,
+/// e.g. mountpoints from df-* directories. Returned names are stripped of the prefix
+///
+/// # Arguments
+/// * `rrdtool` - [`Rrdtool`] with local or remote input directory
+/// * `prefix` - prefix of directories, e.g. df-
+///
+pub fn get_with_prefix(rrdtool: &Rrdtool, prefix: &str) -> Result<Vec<String>> {
+    match rrdtool.target {
+        Target::Local => get_from_local(&rrdtool.input_dir, prefix),
+        Target::Remote => get_from_remote(rrdtool, prefix),
     }
 }
 
-/// Get processes names from local directory
-fn get_from_local(input_dir: &str) -> Result<Vec<String>> {
+/// Get names from local directory, following symlinks
+fn get_from_local(input_dir: &str, prefix: &str) -> Result<Vec<String>> {
     let paths = read_dir(input_dir).context(format!("Failed to read directory: {}", input_dir))?;
 
     let processes = paths
@@ -35,36 +37,41 @@ fn get_from_local(input_dir: &str) -> Result<Vec<String>> {
             path.ok().and_then(|path| {
                 path.path().file_name().and_then(|name| {
                     name.to_str()
-                        .and_then(|s| s.strip_prefix("processes-"))
+                        .and_then(|s| s.strip_prefix(prefix))
                         .map(String::from)
                 })
             })
         })
+        .filter(|process| {
+            // Path::is_dir follows symlinks, so symlinked entries are kept
+            let path = std::path::Path::new(input_dir).join(String::from(prefix) + process);
+            let is_dir = path.is_dir();
+
+            if !is_dir && path.symlink_metadata().is_ok() && path.metadata().is_err() {
+                warn!("Skipping broken symlink {}", path.display());
+            }
+
+            is_dir
+        })
         .collect::<Vec<String>>();
 
     Ok(processes)
 }
 
-/// Get processes names from remote directory via SSH and ls commands
-fn get_from_remote<'a>(
-    input_dir: &'a str,
-    username: &Option<String>,
-    hostname: &Option<String>,
-) -> Result<Vec<String>> {
-    let paths = remote::ls(
-        input_dir,
-        username.as_ref().unwrap(),
-        hostname.as_ref().unwrap(),
-    )
-    .context(format!("Failed to read remote directory {}", input_dir))?;
+/// Get names from remote directory via SSH and ls commands
+fn get_from_remote(rrdtool: &Rrdtool, prefix: &str) -> Result<Vec<String>> {
+    let input_dir = &rrdtool.input_dir;
+    let paths = rrdtool
+        .list_remote_dir(input_dir)
+        .context(format!("Failed to read remote directory {}", input_dir))?;
 
     let processes = paths
         .iter()
-        .filter_map(|path| path.strip_prefix("processes-"))
+        .filter_map(|path| path.strip_prefix(prefix))
         .map(String::from)
         .collect::<Vec<String>>();
 
-    trace!("Listed processes from remote directory: {:?}", processes);
+    trace!("Listed {}* from remote directory: {:?}", prefix, processes);
 
     Ok(processes)
 }
@@ -75,6 +82,7 @@ pub mod tests {
 
     use anyhow::Result;
     use std::fs::{create_dir, remove_dir};
+    use std::os::unix::fs::symlink;
     use std::path::Path;
     use tempfile::TempDir;
     #[test]
@@ -94,7 +102,7 @@ pub mod tests {
             }
         }
 
-        let mut processes = super::get(Target::Local, temp.path().to_str().unwrap(), &None, &None)?;
+        let mut processes = super::get(&Rrdtool::new(temp.path())?)?;
 
         processes.sort();
         assert_eq!(4, processes.len());
@@ -112,6 +120,36 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn rrdtool_get_processes_names_from_symlinked_directory() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let host = temp.path().join("host-1");
+        let elsewhere = temp.path().join("elsewhere");
+
+        create_dir(&host)?;
+        create_dir(&elsewhere)?;
+        create_dir(host.join("processes-firefox"))?;
+        create_dir(elsewhere.join("processes-chrome"))?;
+
+        // Symlinked process directory and a broken one
+        symlink(
+            elsewhere.join("processes-chrome"),
+            host.join("processes-chrome"),
+        )?;
+        symlink(elsewhere.join("missing"), host.join("processes-dolphin"))?;
+
+        // Symlinked input directory
+        let current = temp.path().join("current");
+        symlink(&host, &current)?;
+
+        let mut processes = super::get(&Rrdtool::new(&current)?)?;
+
+        processes.sort();
+        assert_eq!(vec!["chrome", "firefox"], processes);
+
+        Ok(())
+    }
+
     #[test]
     pub fn rrdtool_get_processes_names_from_remote_directory_network_hostname() -> Result<()> {
         let processes = vec!["chrome", "dolphin", "firefox"];
@@ -121,12 +159,12 @@ pub mod tests {
             create_dir(Path::new(temp.path()).join(String::from("processes-") + process))?;
         }
 
-        let mut found_processes = super::get(
-            Target::Remote,
-            temp.path().to_str().unwrap(),
-            &Some(whoami::username()),
-            &Some(String::from("localhost")),
-        )?;
+        let input = format!(
+            "{}@localhost:{}",
+            whoami::username(),
+            temp.path().to_str().unwrap()
+        );
+        let mut found_processes = super::get(&Rrdtool::new(Path::new(&input))?)?;
 
         found_processes.sort();
         assert_eq!(3, found_processes.len());
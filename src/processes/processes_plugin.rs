@@ -1,32 +1,303 @@
 use super::processes_data::ProcessesData;
 use super::processes_names;
-use super::rrdtool::common::{Plugin, Rrdtool};
+use super::rrdtool::common::{Plugin, Rrdtool, Target};
+use super::rrdtool::graph_element::GraphElement;
 
-use anyhow::Result;
-use log::{debug, trace};
-use std::path::PathBuf;
+use super::error::CggError;
+
+use anyhow::{Context, Result};
+use log::{debug, trace, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 impl Rrdtool {
-    /// Add process to the graph
-    fn with_process_rss(
+    /// Add chosen metric of process to the graph
+    fn with_process_metric(
+        &mut self,
+        input_dir: PathBuf,
+        process: String,
+        color: String,
+        element: GraphElement,
+        graph_args_no: usize,
+        data: &ProcessesData,
+    ) -> &Self {
+        trace!("Processing {:?} of {}", data.metric, process);
+
+        let (filename, data_sources, _) = get_metric_file(data);
+        let path = input_dir
+            .join(String::from("processes-") + &process)
+            .join(filename);
+
+        if self.graph_args.args.len() <= graph_args_no {
+            self.graph_args.new_graph();
+        }
+
+        self.graph_args.push_sum_ds(
+            process.as_str(),
+            color.as_str(),
+            element,
+            &[path.to_str().unwrap()],
+            data_sources,
+            false,
+        );
+
+        self
+    }
+
+    /// Add number of process instances to the graph, stacked on previously added processes
+    fn with_process_count(
         &mut self,
         input_dir: PathBuf,
         process: String,
         color: String,
+        element: GraphElement,
         graph_args_no: usize,
+        data: &ProcessesData,
     ) -> &Self {
-        trace!("Processing {}", process);
+        trace!("Processing count of {}", process);
 
+        let (filename, data_sources, stack) = get_metric_file(data);
         let path = input_dir
             .join(String::from("processes-") + &process)
-            .join("ps_rss.rrd");
+            .join(filename);
+
+        if self.graph_args.args.len() <= graph_args_no {
+            self.graph_args.new_graph();
+        }
+
+        self.graph_args.push_sum_ds(
+            process.as_str(),
+            color.as_str(),
+            element,
+            &[path.to_str().unwrap()],
+            data_sources,
+            stack,
+        );
+
+        self
+    }
+
+    /// Add sum of chosen metric or number of instances of all instances of process,
+    /// given as names of their directories, e.g. chrome and chrome.1
+    fn with_process_instances(
+        &mut self,
+        data: &ProcessesData,
+        process: String,
+        instances: &[String],
+        color: String,
+        element: GraphElement,
+        graph_args_no: usize,
+    ) -> &Self {
+        trace!("Processing sum of {:?}", instances);
+
+        let (_, data_sources, stack) = get_metric_file(data);
+        let paths = self.get_instances_paths(data, instances);
 
         if self.graph_args.args.len() <= graph_args_no {
             self.graph_args.new_graph();
         }
 
-        self.graph_args
-            .push(process.as_str(), color.as_str(), 3, path.to_str().unwrap());
+        self.graph_args.push_sum_ds(
+            process.as_str(),
+            color.as_str(),
+            element,
+            &paths
+                .iter()
+                .map(|path| path.to_str().unwrap())
+                .collect::<Vec<&str>>(),
+            data_sources,
+            stack,
+        );
+
+        self
+    }
+
+    /// Returns paths of rrd files with metric drawn for given instances of process
+    fn get_instances_paths(&self, data: &ProcessesData, instances: &[String]) -> Vec<PathBuf> {
+        let (filename, _, _) = get_metric_file(data);
+
+        instances
+            .iter()
+            .map(|instance| {
+                PathBuf::from(self.input_dir.as_str())
+                    .join(String::from("processes-") + instance)
+                    .join(&filename)
+            })
+            .collect()
+    }
+
+    /// Returns processes and their instances with rrd file of drawn metric, so missing
+    /// files are reported before rrdtool fails on them. Process without any instance
+    /// left is skipped with warning.
+    fn skip_missing_files(
+        &self,
+        processes: Vec<String>,
+        mut instances: HashMap<String, Vec<String>>,
+        data: &ProcessesData,
+    ) -> (Vec<String>, HashMap<String, Vec<String>>) {
+        let (filename, _, _) = get_metric_file(data);
+
+        let processes = processes
+            .into_iter()
+            .filter(|process| {
+                let found = instances[process]
+                    .iter()
+                    .filter(|instance| {
+                        let path = PathBuf::from(self.input_dir.as_str())
+                            .join(String::from("processes-") + instance)
+                            .join(&filename);
+
+                        match self.metric_file_exists(&path) {
+                            true => true,
+                            false => {
+                                warn!("Skipping {}, file not found: {}", instance, path.display());
+                                false
+                            }
+                        }
+                    })
+                    .cloned()
+                    .collect::<Vec<String>>();
+
+                let drawn = !found.is_empty();
+                instances.insert(String::from(process), found);
+                drawn
+            })
+            .collect::<Vec<String>>();
+
+        (processes, instances)
+    }
+
+    /// Returns if rrd file exists, listing its directory on remote target
+    fn metric_file_exists(&self, path: &Path) -> bool {
+        match self.target {
+            Target::Local => path.exists(),
+            Target::Remote => match (path.parent(), path.file_name()) {
+                (Some(dir), Some(name)) => self
+                    .list_remote_dir(dir.to_str().unwrap())
+                    .map(|files| files.iter().any(|file| name == file.as_str()))
+                    .unwrap_or(false),
+                _ => false,
+            },
+        }
+    }
+
+    /// Returns given number of processes with the highest peak of drawn metric in the
+    /// selected time window, sorted from the highest. Peak of process with multiple
+    /// instances or data sources is the sum of their peaks. Processes without readable data
+    /// are skipped.
+    fn get_top_processes(
+        &self,
+        processes: Vec<String>,
+        instances: &HashMap<String, Vec<String>>,
+        data: &ProcessesData,
+        top: usize,
+    ) -> Vec<String> {
+        let (_, data_sources, _) = get_metric_file(data);
+
+        let peaks = processes
+            .into_iter()
+            .filter_map(|process| {
+                let peak = self
+                    .get_instances_paths(data, &instances[&process])
+                    .iter()
+                    .flat_map(|path| data_sources.iter().map(move |ds| (path, ds)))
+                    .map(|(path, ds)| {
+                        self.get_peak(path.to_str().unwrap(), self.graph_args.get_ds(ds))
+                    })
+                    .sum::<Result<f64>>();
+
+                match peak {
+                    Ok(peak) => Some((process, peak)),
+                    Err(error) => {
+                        warn!("Skipping process {}: {:#}", process, error);
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<(String, f64)>>();
+
+        debug!("Peaks of processes: {:?}", peaks);
+
+        select_top(peaks, top)
+    }
+
+    /// Add process to the graph, drawing chosen metric or number of instances depending on data.
+    /// Process is drawn with color set by user or color with given index from the palette.
+    /// Multiple instances of the process are summed.
+    fn with_process(
+        &mut self,
+        data: &ProcessesData,
+        process: String,
+        instances: &[String],
+        index: usize,
+        graph_args_no: usize,
+    ) -> &Self {
+        let input_dir = PathBuf::from(self.input_dir.as_str());
+
+        let (color, element) = match data.color_overrides.get(&process) {
+            Some(color) => (String::from(color), GraphElement::Line(3)),
+            None => (self.get_color(index), self.get_line(index, 3)),
+        };
+
+        if instances.len() > 1 {
+            return self.with_process_instances(
+                data,
+                process,
+                instances,
+                color,
+                element,
+                graph_args_no,
+            );
+        }
+
+        match data.process_count {
+            true => {
+                self.with_process_count(input_dir, process, color, element, graph_args_no, data)
+            }
+            false => {
+                self.with_process_metric(input_dir, process, color, element, graph_args_no, data)
+            }
+        }
+    }
+
+    /// Add separate graph for each group of processes, skipping groups without any process
+    fn with_processes_groups(
+        &mut self,
+        processes: &[String],
+        instances: &HashMap<String, Vec<String>>,
+        data: &ProcessesData,
+    ) -> &Self {
+        for group in &data.groups {
+            let matched = processes
+                .iter()
+                .filter(|process| {
+                    group
+                        .patterns
+                        .iter()
+                        .any(|pattern| matches_pattern(pattern, process, data.ignore_case))
+                })
+                .collect::<Vec<&String>>();
+
+            if matched.is_empty() {
+                warn!("No processes found for group {}", group.name);
+                continue;
+            }
+
+            debug!("Processes in group {}: {:?}", group.name, matched);
+
+            self.graph_args.new_labeled_graph(&group.name);
+            let graph_args_no = self.graph_args.args.len() - 1;
+
+            for (index, process) in matched.iter().enumerate() {
+                self.with_process(
+                    data,
+                    String::from(*process),
+                    &instances[*process],
+                    index,
+                    graph_args_no,
+                );
+            }
+        }
 
         self
     }
@@ -38,48 +309,99 @@ impl Plugin<&ProcessesData> for Rrdtool {
         debug!("Processes plugin entry point");
         trace!("Processes plugin: {:?}", data);
 
-        let processes =
-            processes_names::get(self.target, &self.input_dir, &self.username, &self.hostname);
-
-        let processes = match processes {
-            Ok(processes) => processes,
-            Err(error) => anyhow::bail!(
-                "Failed to read processes names from directory {}, error: {}",
-                self.input_dir,
-                error
-            ),
-        };
+        let mut processes = processes_names::get(self).context(format!(
+            "Failed to read processes names from directory {}",
+            self.input_dir
+        ))?;
 
         if processes.is_empty() {
-            anyhow::bail!("Couldn't find any processes!");
+            return Err(CggError::NoProcessesFound {
+                input_dir: self.input_dir.clone(),
+            }
+            .into());
         }
 
+        // Directory listing order differs between runs, sort to keep colors stable
+        processes.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then(a.cmp(b)));
+
         trace!("Found processes: {:?}", processes);
 
-        let processes = filter_processes(processes, &data.processes_to_draw).unwrap();
+        let (processes, instances) = group_instances(processes, data.sum_duplicates);
+
+        if let Some(processes_to_draw) = &data.processes_to_draw {
+            let unmatched = get_unmatched(processes_to_draw, &processes, data.ignore_case);
+
+            if !unmatched.is_empty() {
+                warn!("Processes not found: {}", unmatched.join(", "));
+            }
+        }
+
+        let processes = filter_processes(processes, data);
+        let (processes, instances) = self.skip_missing_files(processes, instances, data);
 
         trace!("Processes after filtering: {:?}", processes);
 
-        assert!(
-            processes.len() < Rrdtool::COLORS.len(),
-            "Too many processes! We are running out of colors to proceed."
-        );
+        let processes = match data.top {
+            Some(top) => self.get_top_processes(processes, &instances, data, top),
+            None => processes,
+        };
+
+        for name in data.color_overrides.keys() {
+            if !processes.contains(name) {
+                warn!("Color set for process {}, which is not drawn", name);
+            }
+        }
+
+        if !data.groups.is_empty() {
+            self.with_processes_groups(&processes, &instances, data);
+            return Ok(self);
+        }
+
+        // Config rejects 0 too, but data may be built without it by users of the library
+        if data.max_processes == 0 {
+            anyhow::bail!("Maximum number of processes on one graph must be positive");
+        }
+
+        // Other plugins may have already created their graphs,
+        // combined plugins draw all processes on the last one
+        let (first_graph, max_processes) = match self.graph_args.combine {
+            true => (
+                self.graph_args.args.len().saturating_sub(1),
+                std::cmp::max(processes.len(), 1),
+            ),
+            false => (self.graph_args.args.len(), data.max_processes),
+        };
+        let first_color = self.graph_args.first_color_index();
 
         let len = processes.len();
-        let loops = math::round::ceil(len as f64 / data.max_processes as f64, 0) as u32;
+        let loops = math::round::ceil(len as f64 / max_processes as f64, 0) as u32;
 
         debug!("{} processes should be saved on {} graphs.", len, loops);
 
+        if let Some(max_files) = data.max_files {
+            if loops as usize > max_files {
+                anyhow::bail!(
+                    "{} processes would be saved on {} graphs, more than --max-files {}. \
+                     Draw more processes on one graph with --max_processes, \
+                     choose fewer with --processes or --top, or raise --max-files",
+                    len,
+                    loops,
+                    max_files
+                );
+            }
+        }
+
         for i in 0..loops {
-            let lower = i as usize * data.max_processes;
-            let upper = std::cmp::min((i as usize + 1) * data.max_processes, processes.len());
+            let lower = i as usize * max_processes;
+            let upper = std::cmp::min((i as usize + 1) * max_processes, processes.len());
 
-            for (color, process) in processes[lower..upper].iter().enumerate() {
-                self.with_process_rss(
-                    PathBuf::from(self.input_dir.as_str()),
+            for (index, process) in processes[lower..upper].iter().enumerate() {
+                self.with_process(
+                    data,
                     String::from(process),
-                    String::from(Rrdtool::COLORS[color]),
-                    i as usize,
+                    &instances[process],
+                    first_color + index,
+                    first_graph + i as usize,
                 );
             }
         }
@@ -88,38 +410,167 @@ impl Plugin<&ProcessesData> for Rrdtool {
     }
 }
 
-/// If processes_to_draw is Some, returns only the processes in both vectors
-fn filter_processes(
+/// Returns names of processes and directories of instances of each of them.
+/// If `sum_duplicates` is set, instances with numeric suffix, e.g. chrome.1,
+/// are instances of the process without it, otherwise each process is its only instance.
+fn group_instances(
     processes: Vec<String>,
-    processes_to_draw: &Option<Vec<String>>,
-) -> Result<Vec<String>> {
-    match processes_to_draw {
-        None => Ok(processes),
-        Some(processes_to_draw) => Ok(processes
-            .into_iter()
-            .filter(|process| processes_to_draw.contains(&process))
-            .collect::<Vec<String>>()),
+    sum_duplicates: bool,
+) -> (Vec<String>, HashMap<String, Vec<String>>) {
+    let mut names = Vec::new();
+    let mut instances: HashMap<String, Vec<String>> = HashMap::new();
+
+    for process in processes {
+        let name = match sum_duplicates {
+            true => String::from(get_base_name(&process)),
+            false => String::from(&process),
+        };
+
+        if !instances.contains_key(&name) {
+            names.push(String::from(&name));
+        }
+
+        instances.entry(name).or_default().push(process);
+    }
+
+    for (name, instances) in instances.iter_mut() {
+        if instances.len() > 1 {
+            instances.sort();
+            debug!("Summing instances of {}: {:?}", name, instances);
+        }
+    }
+
+    (names, instances)
+}
+
+/// Returns name of rrd file, summed data sources and stacking of drawn metric
+fn get_metric_file(data: &ProcessesData) -> (String, &'static [&'static str], bool) {
+    let (filename, data_sources, stack) = match data.process_count {
+        true => ("ps_count.rrd", &["processes"][..], true),
+        false => (data.metric.to_filename(), data.metric.data_sources(), false),
+    };
+
+    let filename = match &data.file_template {
+        Some(template) => template.replace("{metric}", filename.trim_end_matches(".rrd")),
+        None => String::from(filename),
+    };
+
+    (filename, data_sources, stack)
+}
+
+/// Returns names of `top` processes with the highest peaks, sorted from the highest
+fn select_top(mut peaks: Vec<(String, f64)>, top: usize) -> Vec<String> {
+    peaks.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    peaks.truncate(top);
+
+    peaks.into_iter().map(|(process, _)| process).collect()
+}
+
+/// Returns name of process without numeric suffix of its instance, e.g. chrome for chrome.1
+fn get_base_name(process: &str) -> &str {
+    match process.rfind('.') {
+        Some(dot)
+            if dot > 0
+                && dot + 1 < process.len()
+                && process[dot + 1..].chars().all(|c| c.is_ascii_digit()) =>
+        {
+            &process[..dot]
+        }
+        _ => process,
+    }
+}
+
+/// If processes_to_draw is Some, returns only the processes matching any of its
+/// shell-style glob patterns, e.g. firefox*. Exact names match as well.
+/// If processes_regex is Some, processes must match it too.
+/// Processes matching any of exclude patterns are removed afterwards.
+/// Case is ignored if ignore_case is set.
+fn filter_processes(processes: Vec<String>, data: &ProcessesData) -> Vec<String> {
+    processes
+        .into_iter()
+        .filter(|process| match &data.processes_to_draw {
+            Some(processes_to_draw) => processes_to_draw
+                .iter()
+                .any(|pattern| matches_pattern(pattern, process, data.ignore_case)),
+            None => true,
+        })
+        .filter(|process| match &data.processes_regex {
+            Some(regex) => regex.is_match(process),
+            None => true,
+        })
+        .filter(|process| {
+            !data
+                .exclude
+                .iter()
+                .any(|pattern| matches_pattern(pattern, process, data.ignore_case))
+        })
+        .collect::<Vec<String>>()
+}
+
+/// Returns requested processes which don't match any of found processes
+fn get_unmatched(patterns: &[String], processes: &[String], ignore_case: bool) -> Vec<String> {
+    patterns
+        .iter()
+        .filter(|pattern| {
+            !processes
+                .iter()
+                .any(|process| matches_pattern(pattern, process, ignore_case))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Check whether process name matches shell-style glob pattern or is equal to it,
+/// so names with special characters, e.g. [kworker], are matched exactly.
+/// Both are lowercased before comparing if `ignore_case` is set.
+fn matches_pattern(pattern: &str, process: &str, ignore_case: bool) -> bool {
+    let (pattern, process) = match ignore_case {
+        true => (pattern.to_lowercase(), process.to_lowercase()),
+        false => (String::from(pattern), String::from(process)),
+    };
+
+    if pattern == process {
+        return true;
+    }
+
+    match glob::Pattern::new(&pattern) {
+        Ok(pattern) => pattern.matches(&process),
+        Err(_) => false,
     }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use super::super::process_metric::ProcessMetric;
+    use super::super::processes_data::ProcessesGroup;
+    use super::super::rrdtool::common::tests::captured_logs;
     use super::*;
 
     use anyhow::Result;
-    use std::fs::{create_dir, remove_dir};
+    use std::fs::{create_dir, remove_dir_all, File};
     use std::path::Path;
     use tempfile::TempDir;
 
+    /// Create directory of process with rrd files of RSS and number of instances
+    fn create_process_dir(path: &Path) -> Result<()> {
+        create_dir(path)?;
+        File::create(path.join("ps_rss.rrd"))?;
+        File::create(path.join("ps_count.rrd"))?;
+
+        Ok(())
+    }
+
     #[test]
     pub fn rrdtool_with_process_rss() -> Result<()> {
-        let mut rrd = Rrdtool::new(Path::new("/some/path"));
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
 
-        rrd.with_process_rss(
+        rrd.with_process_metric(
             PathBuf::from("/some/path"),
             String::from("firefox"),
             String::from("#00ff00"),
+            GraphElement::Line(3),
             0,
+            &ProcessesData::new(20, None),
         );
 
         assert_eq!(2, rrd.common_args.len() + rrd.graph_args.args[0].len());
@@ -137,13 +588,15 @@ pub mod tests {
 
     #[test]
     pub fn rrdtool_with_process_rss_process_name_with_space() -> Result<()> {
-        let mut rrd = Rrdtool::new(Path::new("/some/path"));
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
 
-        rrd.with_process_rss(
+        rrd.with_process_metric(
             PathBuf::from("/some/path"),
             String::from("rust language server"),
             String::from("#00ff00"),
+            GraphElement::Line(3),
             0,
+            &ProcessesData::new(20, None),
         );
 
         assert_eq!(2, rrd.common_args.len() + rrd.graph_args.args[0].len());
@@ -159,6 +612,252 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn rrdtool_with_process_cputime() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
+        let mut data = ProcessesData::new(20, None);
+        data.metric = ProcessMetric::CpuTime;
+
+        rrd.with_process_metric(
+            PathBuf::from("/some/path"),
+            String::from("firefox"),
+            String::from("#00ff00"),
+            GraphElement::Line(3),
+            0,
+            &data,
+        );
+
+        // CPU time of process is the sum of user and system time
+        assert_eq!(
+            vec![
+                "DEF:firefox_part1=/some/path/processes-firefox/ps_cputime.rrd:user:AVERAGE",
+                "DEF:firefox_part2=/some/path/processes-firefox/ps_cputime.rrd:syst:AVERAGE",
+                "CDEF:firefox=firefox_part1,firefox_part2,ADDNAN",
+                "LINE3:firefox#00ff00:\"firefox\"",
+            ],
+            rrd.graph_args.args[0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_process_threads() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
+        let mut data = ProcessesData::new(20, None);
+        data.metric = ProcessMetric::Threads;
+
+        rrd.with_process_metric(
+            PathBuf::from("/some/path"),
+            String::from("firefox"),
+            String::from("#00ff00"),
+            GraphElement::Line(3),
+            0,
+            &data,
+        );
+
+        assert_eq!(
+            "DEF:firefox=/some/path/processes-firefox/ps_count.rrd:threads:AVERAGE",
+            rrd.graph_args.args[0][0]
+        );
+        assert_eq!("threads", data.metric.vertical_label());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_file_template() -> Result<()> {
+        captured_logs();
+
+        let temp = TempDir::new().unwrap();
+
+        create_dir(temp.path().join("processes-firefox"))?;
+        create_dir(temp.path().join("processes-firefox").join("ps_rss"))?;
+        File::create(
+            temp.path()
+                .join("processes-firefox")
+                .join("ps_rss")
+                .join("value.rrd"),
+        )?;
+        create_dir(temp.path().join("processes-chrome"))?;
+
+        let mut data = ProcessesData::new(20, None);
+        data.file_template = Some(String::from("{metric}/value.rrd"));
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let defs = rrd.graph_args.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("DEF:"))
+            .collect::<Vec<&String>>();
+        assert_eq!(1, defs.len());
+        assert_eq!(
+            format!(
+                "DEF:firefox={}/processes-firefox/ps_rss/value.rrd:value:AVERAGE",
+                temp.path().display()
+            ),
+            *defs[0]
+        );
+
+        assert!(captured_logs().contains(&format!(
+            "Skipping chrome, file not found: {}/processes-chrome/ps_rss/value.rrd",
+            temp.path().display()
+        )));
+
+        data.process_count = true;
+        data.file_template = Some(String::from("{metric}.rrd"));
+        assert_eq!(
+            (String::from("ps_count.rrd"), &["processes"][..], true),
+            get_metric_file(&data)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_file_template_pages() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["chrome", "dolphin", "firefox"] {
+            create_dir(temp.path().join(String::from("processes-") + process))?;
+        }
+
+        for process in &["dolphin", "firefox"] {
+            std::fs::File::create(
+                temp.path()
+                    .join(String::from("processes-") + process)
+                    .join("ps_rss.rrd"),
+            )?;
+        }
+
+        let mut data = ProcessesData::new(2, None);
+        data.file_template = Some(String::from("{metric}.rrd"));
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        // Skipped process leaves neither empty place on the graph nor unused color
+        assert_eq!(1, rrd.graph_args.args.len());
+        assert_eq!(2, rrd.graph_args.info[0].files.len());
+
+        let lines = rrd.graph_args.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("LINE"))
+            .collect::<Vec<&String>>();
+        assert!(lines[0].starts_with(&format!("LINE3:dolphin{}", Rrdtool::COLORS[0])));
+        assert!(lines[1].starts_with(&format!("LINE3:firefox{}", Rrdtool::COLORS[1])));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_missing_rrd() -> Result<()> {
+        captured_logs();
+
+        let temp = TempDir::new().unwrap();
+        create_process_dir(&temp.path().join("processes-firefox"))?;
+        create_process_dir(&temp.path().join("processes-chrome"))?;
+        create_dir(temp.path().join("processes-chrome.1"))?;
+        create_dir(temp.path().join("processes-dolphin"))?;
+
+        let mut data = ProcessesData::new(20, None);
+        data.sum_duplicates = true;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let defs = rrd.graph_args.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("DEF:"))
+            .collect::<Vec<&String>>();
+        assert_eq!(2, defs.len());
+        assert!(defs[0].starts_with("DEF:chrome="));
+        assert!(defs[1].starts_with("DEF:firefox="));
+
+        let logs = captured_logs();
+        for process in &["chrome.1", "dolphin"] {
+            assert!(logs.contains(&format!(
+                "Skipping {}, file not found: {}/processes-{}/ps_rss.rrd",
+                process,
+                temp.path().display(),
+                process
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_color_overrides() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["chrome", "firefox"] {
+            create_process_dir(&temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let mut data = ProcessesData::new(20, None);
+        data.color_overrides
+            .insert(String::from("firefox"), String::from("#123456"));
+        data.color_overrides
+            .insert(String::from("dolphin"), String::from("#654321"));
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let lines = rrd.graph_args.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("LINE"))
+            .collect::<Vec<&String>>();
+
+        assert_eq!(2, lines.len());
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("LINE3:firefox#123456:")));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("LINE3:chrome#") && !line.contains("#123456")));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_process_count() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["firefox", "chrome"] {
+            create_process_dir(&temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let mut data = ProcessesData::new(20, None);
+        data.process_count = true;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let args = &rrd.graph_args.args[0];
+        let defs = args
+            .iter()
+            .filter(|arg| arg.starts_with("DEF:"))
+            .collect::<Vec<&String>>();
+        let lines = args
+            .iter()
+            .filter(|arg| arg.starts_with("LINE"))
+            .collect::<Vec<&String>>();
+
+        assert_eq!(2, defs.len());
+        assert_eq!(2, lines.len());
+
+        for def in defs {
+            assert!(def.ends_with("/ps_count.rrd:processes:AVERAGE"));
+        }
+
+        assert!(!lines[0].ends_with(":STACK"));
+        assert!(lines[1].ends_with(":STACK"));
+
+        Ok(())
+    }
+
     #[test]
     pub fn rrdtool_with_processes_rss_more_than_max_processes() -> Result<()> {
         let temp = TempDir::new().unwrap();
@@ -173,20 +872,17 @@ pub mod tests {
 
         for path in &paths {
             if !path.exists() {
-                create_dir(path)?;
+                create_process_dir(path)?;
             }
         }
 
-        let mut rrd = Rrdtool::new(temp.path());
+        let mut rrd = Rrdtool::new(temp.path())?;
 
-        rrd.enter_plugin(&ProcessesData {
-            max_processes: 2,
-            processes_to_draw: None,
-        })?;
+        rrd.enter_plugin(&ProcessesData::new(2, None))?;
 
         for path in paths {
             if path.exists() {
-                remove_dir(path)?;
+                remove_dir_all(path)?;
             }
         }
 
@@ -195,6 +891,62 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn rrdtool_with_processes_more_than_colors() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for i in 0..25 {
+            create_process_dir(&temp.path().join(format!("processes-process{}", i)))?;
+        }
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+
+        rrd.enter_plugin(&ProcessesData::new(Rrdtool::COLORS.len(), None))?;
+
+        assert_eq!(2, rrd.graph_args.args.len());
+        assert_eq!(20, rrd.graph_args.info[0].files.len());
+        assert_eq!(5, rrd.graph_args.info[1].files.len());
+        assert!(!rrd
+            .graph_args
+            .args
+            .iter()
+            .flatten()
+            .any(|arg| arg.ends_with(":dashes")));
+
+        assert!(Rrdtool::new(temp.path())?
+            .enter_plugin(&ProcessesData::new(0, None))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_max_files() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for i in 0..9 {
+            create_process_dir(&temp.path().join(format!("processes-process{}", i)))?;
+        }
+
+        let mut data = ProcessesData::new(2, None);
+        data.max_files = Some(4);
+
+        let error = Rrdtool::new(temp.path())?
+            .enter_plugin(&data)
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("9 processes would be saved on 5 graphs, more than --max-files 4."));
+
+        data.max_files = Some(5);
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+        assert_eq!(5, rrd.graph_args.args.len());
+
+        Ok(())
+    }
+
     #[test]
     pub fn rrdtool_filter_processes_none() -> Result<()> {
         let processes = vec![
@@ -202,7 +954,7 @@ pub mod tests {
             String::from("chrome"),
             String::from("dolphin"),
         ];
-        let filtered = filter_processes(processes.to_vec(), &None)?;
+        let filtered = filter_processes(processes.to_vec(), &ProcessesData::new(20, None));
         assert_eq!(processes, filtered);
 
         Ok(())
@@ -223,7 +975,10 @@ pub mod tests {
             String::from("notes"),
         ];
 
-        let mut filtered = filter_processes(processes.to_vec(), &Some(filter.to_vec()))?;
+        let mut filtered = filter_processes(
+            processes.to_vec(),
+            &ProcessesData::new(20, Some(filter.to_vec())),
+        );
         filtered.sort();
 
         assert_eq!(
@@ -233,4 +988,304 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    pub fn rrdtool_filter_processes_glob() -> Result<()> {
+        let processes = vec![
+            String::from("chrome"),
+            String::from("chrome-sandbox"),
+            String::from("firefox"),
+            String::from("gpu-helper"),
+            String::from("[kworker]"),
+        ];
+
+        let filter = vec![
+            String::from("chrome*"),
+            String::from("*-helper"),
+            String::from("[kworker]"),
+        ];
+
+        let filtered = filter_processes(processes.to_vec(), &ProcessesData::new(20, Some(filter)));
+
+        assert_eq!(
+            vec!["chrome", "chrome-sandbox", "gpu-helper", "[kworker]"],
+            filtered
+        );
+
+        let filtered = filter_processes(
+            processes,
+            &ProcessesData::new(20, Some(vec![String::from("spotify*")])),
+        );
+        assert!(filtered.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_filter_processes_exclude() -> Result<()> {
+        let processes = vec![
+            String::from("chrome"),
+            String::from("dbus-daemon"),
+            String::from("firefox"),
+            String::from("kworker"),
+            String::from("systemd"),
+            String::from("systemd-journald"),
+        ];
+
+        let mut data = ProcessesData::new(20, None);
+        data.exclude = vec![String::from("kworker"), String::from("systemd*")];
+
+        assert_eq!(
+            vec!["chrome", "dbus-daemon", "firefox"],
+            filter_processes(processes.to_vec(), &data)
+        );
+
+        // Included processes are filtered first, then excluded ones are removed
+        data.processes_to_draw = Some(vec![
+            String::from("firefox"),
+            String::from("systemd"),
+            String::from("dbus*"),
+        ]);
+        data.exclude.push(String::from("dbus-daemon"));
+
+        assert_eq!(vec!["firefox"], filter_processes(processes, &data));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_filter_processes_regex() -> Result<()> {
+        let processes = vec![
+            String::from("chrome"),
+            String::from("chrome-sandbox"),
+            String::from("firefox"),
+            String::from("firefox-bin"),
+        ];
+
+        let mut data = ProcessesData::new(20, None);
+        data.processes_regex = Some(regex::Regex::new("^(firefox|chrome)$")?);
+
+        assert_eq!(
+            vec!["chrome", "firefox"],
+            filter_processes(processes.to_vec(), &data)
+        );
+
+        // Regex narrows processes given with --processes
+        data.processes_to_draw = Some(vec![String::from("firefox*")]);
+
+        assert_eq!(vec!["firefox"], filter_processes(processes, &data));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_filter_processes_ignore_case() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["Firefox", "chrome", "CHROME-sandbox", "Dolphin"] {
+            create_process_dir(&temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let mut data = ProcessesData::new(20, Some(vec![String::from("firefox")]));
+
+        // Case sensitive by default
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+        assert!(rrd.graph_args.args.is_empty());
+
+        data.processes_to_draw = Some(vec![String::from("firefox"), String::from("Chrome*")]);
+        data.ignore_case = true;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let args = rrd.graph_args.args[0].join(" ");
+        assert_eq!(6, rrd.graph_args.args[0].len());
+        assert!(args.contains("processes-Firefox/ps_rss.rrd"));
+        assert!(args.contains("processes-chrome/ps_rss.rrd"));
+        assert!(args.contains("processes-CHROME-sandbox/ps_rss.rrd"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_sum_duplicates() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["chrome", "chrome.1", "firefox", "node.js"] {
+            create_process_dir(&temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let mut data = ProcessesData::new(20, Some(vec![String::from("chrome")]));
+        data.sum_duplicates = true;
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        let args = &rrd.graph_args.args[0];
+        let input_dir = temp.path().to_str().unwrap();
+
+        assert_eq!(
+            &vec![
+                format!(
+                    "DEF:chrome_part1={}/processes-chrome/ps_rss.rrd:value:AVERAGE",
+                    input_dir
+                ),
+                format!(
+                    "DEF:chrome_part2={}/processes-chrome.1/ps_rss.rrd:value:AVERAGE",
+                    input_dir
+                ),
+                String::from("CDEF:chrome=chrome_part1,chrome_part2,ADDNAN"),
+                format!("LINE3:chrome{}:\"chrome\"", Rrdtool::COLORS[0]),
+            ],
+            args
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn select_top() -> Result<()> {
+        let peaks = vec![
+            (String::from("firefox"), 300.0),
+            (String::from("chrome"), 500.0),
+            (String::from("dolphin"), 100.0),
+            (String::from("spotify"), 400.0),
+        ];
+
+        assert_eq!(
+            vec!["chrome", "spotify"],
+            super::select_top(peaks.to_vec(), 2)
+        );
+        assert_eq!(4, super::select_top(peaks, 10).len());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_top_unreadable() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["chrome", "firefox"] {
+            create_dir(temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let mut data = ProcessesData::new(20, None);
+        data.top = Some(1);
+
+        // rrdtool fails to read peaks of empty directories, so all processes are skipped
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        assert!(rrd.graph_args.args.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_sorted() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["firefox", "Xorg", "chrome", "Discord", "bash"] {
+            create_process_dir(&temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let mut first = Rrdtool::new(temp.path())?;
+        first.enter_plugin(&ProcessesData::new(3, None))?;
+
+        let mut second = Rrdtool::new(temp.path())?;
+        second.enter_plugin(&ProcessesData::new(3, None))?;
+
+        assert_eq!(first.graph_args.args, second.graph_args.args);
+
+        let lines = first
+            .graph_args
+            .args
+            .iter()
+            .flatten()
+            .filter(|arg| arg.starts_with("LINE"))
+            .map(|arg| arg.split(&[':', '#'][..]).nth(1).unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(vec!["bash", "chrome", "Discord", "firefox", "Xorg"], lines);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_unmatched() -> Result<()> {
+        captured_logs();
+
+        let temp = TempDir::new().unwrap();
+
+        for process in &["firefox", "chrome"] {
+            create_process_dir(&temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let filter = vec![
+            String::from("firefox"),
+            String::from("firefx"),
+            String::from("chrom*"),
+            String::from("spotify*"),
+        ];
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&ProcessesData::new(20, Some(filter)))?;
+
+        assert_eq!(2, rrd.graph_args.args[0].len() / 2);
+        assert!(captured_logs().contains(&String::from("Processes not found: firefx, spotify*")));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn get_base_name() -> Result<()> {
+        assert_eq!("chrome", super::get_base_name("chrome.1"));
+        assert_eq!("chrome", super::get_base_name("chrome.12"));
+        assert_eq!("chrome", super::get_base_name("chrome"));
+        assert_eq!("node.js", super::get_base_name("node.js"));
+        assert_eq!("chrome.", super::get_base_name("chrome."));
+        assert_eq!(".1", super::get_base_name(".1"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_processes_groups() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["nginx", "apache2", "postgres", "mysqld", "firefox"] {
+            create_process_dir(&temp.path().join(String::from("processes-") + process))?;
+        }
+
+        let mut data = ProcessesData::new(20, None);
+        data.groups = vec![
+            ProcessesGroup {
+                name: String::from("web"),
+                patterns: vec![String::from("nginx*"), String::from("apache*")],
+            },
+            ProcessesGroup {
+                name: String::from("db"),
+                patterns: vec![String::from("postgres*"), String::from("mysql*")],
+            },
+        ];
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.enter_plugin(&data)?;
+
+        assert_eq!(2, rrd.graph_args.args.len());
+        assert_eq!(Some(String::from("web")), rrd.graph_args.info[0].label);
+        assert_eq!(Some(String::from("db")), rrd.graph_args.info[1].label);
+
+        let web = rrd.graph_args.args[0].join(" ");
+        assert_eq!(4, rrd.graph_args.args[0].len());
+        assert!(web.contains("processes-nginx/ps_rss.rrd"));
+        assert!(web.contains("processes-apache2/ps_rss.rrd"));
+
+        let db = rrd.graph_args.args[1].join(" ");
+        assert_eq!(4, rrd.graph_args.args[1].len());
+        assert!(db.contains("processes-postgres/ps_rss.rrd"));
+        assert!(db.contains("processes-mysqld/ps_rss.rrd"));
+
+        Ok(())
+    }
 }
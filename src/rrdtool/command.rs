@@ -0,0 +1,138 @@
+use std::process::{Command, Output};
+
+/// Executes system commands, allows to replace real execution e.g. in tests
+pub trait CommandRunner: Send + Sync {
+    /// Execute program with given arguments and wait for its output
+    ///
+    /// # Arguments
+    /// * `program` - name or path of the program, e.g. rrdtool
+    /// * `args` - arguments passed to the program
+    ///
+    fn run(&self, program: &str, args: &[String]) -> std::io::Result<Output>;
+}
+
+/// Default [`CommandRunner`] executing commands with [`std::process::Command`]
+pub struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn run(&self, program: &str, args: &[String]) -> std::io::Result<Output> {
+        Command::new(program).args(args).output()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use std::sync::{Arc, Mutex};
+
+    /// Program and arguments of each recorded call
+    pub type Calls = Arc<Mutex<Vec<(String, Vec<String>)>>>;
+
+    /// [`CommandRunner`] recording all calls instead of executing them
+    #[derive(Default)]
+    pub struct MockCommandRunner {
+        /// Program and arguments of each call
+        pub calls: Calls,
+        /// Number of first calls of given program which should fail
+        pub failures: Mutex<HashMap<String, usize>>,
+        /// Exit code of failed calls of given program, 1 if not set
+        pub exit_codes: HashMap<String, i32>,
+        /// Standard output returned by successful calls of given program,
+        /// or of program with given first argument, e.g. "rrdtool first"
+        pub stdout: HashMap<String, Vec<u8>>,
+    }
+
+    impl MockCommandRunner {
+        pub fn new() -> MockCommandRunner {
+            MockCommandRunner::default()
+        }
+
+        /// Make first `count` calls of `program` fail
+        pub fn failing(self, program: &str, count: usize) -> MockCommandRunner {
+            self.failures
+                .lock()
+                .unwrap()
+                .insert(String::from(program), count);
+            self
+        }
+
+        /// Make failed calls of `program` exit with `code`, e.g. 255 of ssh which can't connect
+        pub fn exiting_with(mut self, program: &str, code: i32) -> MockCommandRunner {
+            self.exit_codes.insert(String::from(program), code);
+            self
+        }
+
+        /// Make successful calls of `program` return `stdout`
+        pub fn returning(mut self, program: &str, stdout: &str) -> MockCommandRunner {
+            self.stdout
+                .insert(String::from(program), stdout.as_bytes().to_vec());
+            self
+        }
+
+        /// Returns arguments of all calls of given program
+        pub fn calls_of(calls: &Calls, program: &str) -> Vec<Vec<String>> {
+            calls
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(called, _)| called == program)
+                .map(|(_, args)| args.clone())
+                .collect()
+        }
+    }
+
+    impl CommandRunner for MockCommandRunner {
+        fn run(&self, program: &str, args: &[String]) -> std::io::Result<Output> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((String::from(program), args.to_vec()));
+
+            let mut failures = self.failures.lock().unwrap();
+
+            let status = match failures.get_mut(program) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    ExitStatus::from_raw(self.exit_codes.get(program).unwrap_or(&1) << 8)
+                }
+                _ => ExitStatus::from_raw(0),
+            };
+
+            Ok(Output {
+                status,
+                stdout: match status.success() {
+                    true => args
+                        .first()
+                        .and_then(|arg| self.stdout.get(&format!("{} {}", program, arg)))
+                        .or_else(|| self.stdout.get(program))
+                        .cloned()
+                        .unwrap_or_default(),
+                    false => Vec::new(),
+                },
+                stderr: match status.success() {
+                    true => Vec::new(),
+                    false => b"mock failure".to_vec(),
+                },
+            })
+        }
+    }
+
+    #[test]
+    fn mock_command_runner_failing() {
+        let runner = MockCommandRunner::new().failing("scp", 1);
+        let calls = runner.calls.clone();
+
+        let args = vec![String::from("a"), String::from("b")];
+
+        assert!(!runner.run("scp", &args).unwrap().status.success());
+        assert!(runner.run("scp", &args).unwrap().status.success());
+        assert!(runner.run("ssh", &args).unwrap().status.success());
+
+        assert_eq!(2, MockCommandRunner::calls_of(&calls, "scp").len());
+        assert_eq!(vec![args], MockCommandRunner::calls_of(&calls, "ssh"));
+    }
+}
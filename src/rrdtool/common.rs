@@ -1,11 +1,31 @@
+use super::super::error::CggError;
 use super::super::*;
-use super::graph_arguments::GraphArguments;
+use super::command::{CommandRunner, SystemCommandRunner};
+use super::compare_hosts::get_host_name;
+use super::consolidation_function::ConsolidationFunction;
+use super::graph_arguments::{validate_color, GraphArguments};
+use super::graph_element::GraphElement;
+use super::hrule::Hrule;
+use super::image_format::ImageFormat;
+use super::legend::{LegendDirection, LegendPosition};
+use super::remote::{RemoteListing, SshOptions};
+use super::right_axis::RightAxis;
+use super::transfer::Transfer;
 
 use anyhow::{Context, Result};
-use log::{debug, error, info, trace};
-use std::path::Path;
-use std::process::Command;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, TimeZone};
+use log::{debug, error, info, trace, warn};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
 
 /// Wrapper holding rrdtool command and parameters
 pub struct Rrdtool {
@@ -30,7 +50,70 @@ pub struct Rrdtool {
     /// In case of SSH connection
     pub hostname: Option<String>,
     /// In case of SSH connection
+    pub ssh_options: SshOptions,
+    /// In case of SSH connection
     remote_filename: Option<String>,
+    /// Unique part of names of temporary files on remote target,
+    /// so concurrent runs against the same target don't overwrite each other's graphs
+    remote_id: String,
+    /// Only the lines are drawn, without axes, grid, legend and title
+    pub sparkline: bool,
+    /// Program copying the result back from remote target
+    transfer: Transfer,
+    /// Number of retries of copying the result back from remote target
+    scp_retries: u32,
+    /// Delay before first retry of scp, multiplied with each following attempt
+    scp_retry_delay: Duration,
+    /// Executes all system commands
+    runner: Box<dyn CommandRunner>,
+    /// Start timestamp of graphs
+    start: Option<u64>,
+    /// End timestamp of graphs
+    end: Option<u64>,
+    /// chrono format of time range shown in graph titles, e.g. %Y-%m-%d %H:%M
+    title_time_format: Option<String>,
+    /// Add overview graph over whole data history for each graph
+    pub overview: bool,
+    /// Maximum age in seconds of the newest data point, paired with current timestamp
+    max_data_age: Option<(u64, u64)>,
+    /// Log step of each rrd file
+    show_rrd_step: bool,
+    /// Title of all graphs
+    title: Option<String>,
+    /// Titles of graphs of particular plugins, used instead of common title
+    plugin_titles: HashMap<Plugins, String>,
+    /// Width of graphs, rrdtool default if None
+    width: Option<u32>,
+    /// Height of graphs, rrdtool default if None
+    height: Option<u32>,
+    /// Width and height of graphs of particular plugins, used instead of common size
+    plugin_sizes: HashMap<Plugins, (u32, u32)>,
+    /// Directory with rrd files copied from remote targets
+    fetched: Option<TempDir>,
+    /// Label of vertical axis of all graphs, plugins set their own if None
+    vertical_label: Option<String>,
+    /// Colors of lines, in order of drawing
+    pub palette: Vec<String>,
+    /// Print commands instead of executing them
+    dry_run: bool,
+    /// Write command generating each graph to file next to it, with .cmd appended
+    embed_command: bool,
+    /// Log command generating each graph right before executing it
+    print_command: bool,
+    /// Overwrite existing output files instead of failing
+    force: bool,
+    /// Share one SSH connection by all commands executed on remote target
+    ssh_multiplex: bool,
+    /// Export data to JSON with rrdtool xport instead of drawing graphs
+    export: bool,
+    /// Already listed directories of remote target
+    remote_listing: RemoteListing,
+    /// Host names and directories of all inputs, when plugins of multiple hosts are drawn
+    inputs: Vec<(String, String)>,
+    /// Second vertical axis, drawn when set
+    right_axis: Option<RightAxis>,
+    /// Plugins whose elements are drawn against the right axis
+    right_axis_plugins: Vec<Plugins>,
 }
 
 /// Trait for different plugins
@@ -46,11 +129,28 @@ pub enum Target {
     Remote,
 }
 
+/// Target type, path, username, hostname and SSH port parsed from input path
+pub type InputPath = (Target, String, Option<String>, Option<String>, Option<u16>);
+
 /// Enum for choosing collectd plugins
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Plugins {
     Processes,
     Memory,
+    Cpu,
+    Df,
+}
+
+impl Plugins {
+    /// Returns name of plugin as given in command line
+    pub fn as_str(&self) -> &str {
+        match self {
+            Plugins::Processes => "processes",
+            Plugins::Memory => "memory",
+            Plugins::Cpu => "cpu",
+            Plugins::Df => "df",
+        }
+    }
 }
 
 impl FromStr for Plugins {
@@ -60,6 +160,8 @@ impl FromStr for Plugins {
         match input {
             "processes" => Ok(Plugins::Processes),
             "memory" => Ok(Plugins::Memory),
+            "cpu" => Ok(Plugins::Cpu),
+            "df" => Ok(Plugins::Df),
             _ => Err(()),
         }
     }
@@ -72,10 +174,40 @@ impl Rrdtool {
         "#ffd8b1", "#000075", "#808080", "#000000",
     ];
 
-    pub fn new(input_dir: &Path) -> Rrdtool {
-        let (target, input_dir, username, hostname) = Rrdtool::parse_input_path(input_dir).unwrap();
+    /// Height of overview graph
+    pub const OVERVIEW_HEIGHT: u32 = 100;
+
+    /// Maximum width and height of graphs, bigger graphs are rejected
+    pub const MAX_SIZE: u32 = 10000;
+
+    /// Output filename writing the graph to standard output instead of a file
+    pub const STDOUT: &'static str = "-";
+
+    /// Prefix of temporary files and directories on remote target, unique id of run is appended
+    pub const REMOTE_TEMP_PREFIX: &'static str = "/tmp/cgg-out-";
+
+    /// Redirection of standard output in remote commands, the only argument left unquoted
+    const REDIRECT: &'static str = ">";
+
+    /// Color of lines marking selected time window on overview graph
+    pub const OVERVIEW_WINDOW_COLOR: &'static str = "#ff0000";
+
+    /// Arguments stripping everything but the lines from the graph
+    pub const SPARKLINE_ARGS: &'static [&'static str] = &[
+        "--only-graph",
+        "--no-legend",
+        "--x-grid",
+        "none",
+        "--y-grid",
+        "none",
+    ];
+
+    /// Create rrdtool wrapper for local or remote input, fails if input path is invalid,
+    /// e.g. port of remote target is out of range
+    pub fn new(input_dir: &Path) -> Result<Rrdtool> {
+        let (target, input_dir, username, hostname, port) = Rrdtool::parse_input_path(input_dir)?;
 
-        Rrdtool {
+        Ok(Rrdtool {
             target,
             input_dir,
             command: String::from("rrdtool"),
@@ -85,8 +217,64 @@ impl Rrdtool {
             graph_args: GraphArguments::new(target),
             username,
             hostname,
+            ssh_options: SshOptions {
+                port,
+                identity: None,
+                control_path: None,
+                offline: false,
+            },
             remote_filename: None,
+            remote_id: Rrdtool::build_remote_id(),
+            sparkline: false,
+            transfer: Transfer::Scp,
+            scp_retries: 0,
+            scp_retry_delay: Duration::from_secs(1),
+            runner: Box::new(SystemCommandRunner),
+            start: None,
+            end: None,
+            title_time_format: None,
+            overview: false,
+            max_data_age: None,
+            show_rrd_step: false,
+            title: None,
+            plugin_titles: HashMap::new(),
+            width: None,
+            height: None,
+            plugin_sizes: HashMap::new(),
+            fetched: None,
+            vertical_label: None,
+            palette: Rrdtool::COLORS
+                .iter()
+                .map(|color| String::from(*color))
+                .collect(),
+            dry_run: false,
+            ssh_multiplex: false,
+            export: false,
+            remote_listing: RemoteListing::default(),
+            inputs: Vec::new(),
+            right_axis: None,
+            right_axis_plugins: Vec::new(),
+            embed_command: false,
+            print_command: false,
+            force: false,
+        })
+    }
+
+    /// Set rrdtool binary, e.g. /opt/rrdtool/bin/rrdtool, rrdtool from PATH is used by default
+    pub fn with_command(&mut self, command: String) -> Result<&mut Self> {
+        if command.is_empty() {
+            anyhow::bail!("rrdtool command can't be empty");
         }
+
+        self.command = command;
+        Ok(self)
+    }
+
+    /// Replace runner executing all system commands, e.g. rrdtool, ssh and scp,
+    /// [`SystemCommandRunner`] is used by default
+    pub fn with_runner(&mut self, runner: Box<dyn CommandRunner>) -> Result<&mut Self> {
+        self.runner = runner;
+        Ok(self)
     }
 
     /// Add subcommand to rrdtool, e.g. graph
@@ -95,36 +283,252 @@ impl Rrdtool {
         Ok(self)
     }
 
-    /// Add output file
+    /// Add output file, format of graphs is detected from its extension
     pub fn with_output_file(&mut self, output: String) -> Result<&mut Self> {
-        match self.target {
-            Target::Local => self.output_filename = output,
-            Target::Remote => {
-                self.remote_filename = Some(String::from("/tmp/cgg-out.png"));
-                self.output_filename = output;
-            }
+        if output == Rrdtool::STDOUT && self.target == Target::Remote {
+            anyhow::bail!("Writing graphs to standard output is supported only for local input");
+        }
+
+        let image_format = ImageFormat::from_filename(&output);
+        self.output_filename = output;
+        self.set_image_format(image_format);
+        Ok(self)
+    }
+
+    /// Override format of graphs detected from output filename
+    pub fn with_image_format(&mut self, image_format: Option<ImageFormat>) -> Result<&mut Self> {
+        if let Some(image_format) = image_format {
+            self.set_image_format(image_format);
         }
         Ok(self)
     }
 
-    /// Add width of output file
+    /// Replace --imgformat in common arguments, PNG is default of rrdtool so it's not passed
+    fn set_image_format(&mut self, image_format: ImageFormat) {
+        if let Some(position) = self.common_args.iter().position(|arg| arg == "--imgformat") {
+            self.common_args.drain(position..position + 2);
+        }
+
+        if image_format != ImageFormat::Png {
+            self.common_args.push(String::from("--imgformat"));
+            self.common_args.push(String::from(image_format.as_str()));
+        }
+
+        if self.target == Target::Remote {
+            self.remote_filename = Some(format!(
+                "{}{}.{}",
+                Rrdtool::REMOTE_TEMP_PREFIX,
+                self.remote_id,
+                image_format.extension()
+            ));
+        }
+    }
+
+    /// Add width of output file, between 1 and [`Rrdtool::MAX_SIZE`] pixels
     pub fn with_width(&mut self, width: u32) -> Result<&mut Self> {
-        self.common_args.push(String::from("-w"));
-        self.common_args.push(width.to_string());
+        Rrdtool::validate_size("Width", width)?;
+        self.width = Some(width);
+        Ok(self)
+    }
+
+    fn validate_size(name: &str, size: u32) -> Result<()> {
+        if size == 0 || size > Rrdtool::MAX_SIZE {
+            anyhow::bail!(
+                "{} must be between 1 and {} pixels, got {}",
+                name,
+                Rrdtool::MAX_SIZE,
+                size
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Replace default colors of lines
+    pub fn with_palette(&mut self, colors: Vec<String>) -> Result<&mut Self> {
+        if colors.is_empty() {
+            anyhow::bail!("Palette must contain at least one color");
+        }
+
+        for color in &colors {
+            validate_color(color).context("Invalid color in palette")?;
+        }
+
+        self.palette = colors;
+        Ok(self)
+    }
+
+    /// Returns color from palette for element with given index,
+    /// colors are repeated when palette is exhausted
+    pub fn get_color(&self, index: usize) -> String {
+        String::from(&self.palette[index % self.palette.len()])
+    }
+
+    /// Returns line of given thickness for element with given index,
+    /// dashed when palette is exhausted, so elements with repeated colors differ
+    pub fn get_line(&self, index: usize, thickness: u32) -> GraphElement {
+        match index < self.palette.len() {
+            true => GraphElement::Line(thickness),
+            false => GraphElement::DashedLine(thickness),
+        }
+    }
+
+    /// Add label of vertical axis to all graphs, instead of the plugins defaults
+    pub fn with_vertical_label(&mut self, label: String) -> Result<&mut Self> {
+        let args = self.build_vertical_label_args(&label);
+
+        self.common_args.extend(args);
+        self.vertical_label = Some(label);
+        Ok(self)
+    }
+
+    /// Build arguments setting label of vertical axis, label is a single argument
+    fn build_vertical_label_args(&self, label: &str) -> Vec<String> {
+        vec![String::from("--vertical-label"), String::from(label)]
+    }
+
+    /// Set step of graphed data in seconds. rrdtool uses the RRA with the closest resolution
+    /// available in rrd files, so it can't be finer than their step. None keeps automatic choice.
+    pub fn with_step(&mut self, step: Option<u64>) -> Result<&mut Self> {
+        match step {
+            Some(0) => anyhow::bail!("Step must be positive"),
+            Some(step) => {
+                self.common_args.push(String::from("--step"));
+                self.common_args.push(step.to_string());
+            }
+            None => (),
+        }
+
+        Ok(self)
+    }
+
+    /// Add text printed at the bottom of all graphs, e.g. time of generation
+    pub fn with_watermark(&mut self, watermark: String) -> Result<&mut Self> {
+        self.common_args.push(String::from("--watermark"));
+        self.common_args.push(watermark);
+        Ok(self)
+    }
+
+    /// Add second vertical axis at the right side of graphs, with its own label.
+    /// Elements of `plugins` are drawn against the right axis, so plugins with
+    /// different units can be combined on one graph with [`Rrdtool::with_combine`].
+    pub fn with_right_axis(
+        &mut self,
+        axis: Option<RightAxis>,
+        label: Option<String>,
+        plugins: Vec<Plugins>,
+    ) -> Result<&mut Self> {
+        let axis = match axis {
+            Some(axis) => axis,
+            None if label.is_none() && plugins.is_empty() => return Ok(self),
+            None => anyhow::bail!("Right axis label and plugins require right axis scale"),
+        };
+
+        self.common_args.push(String::from("--right-axis"));
+        self.common_args.push(axis.to_rrdtool());
+
+        if let Some(label) = label {
+            self.common_args.push(String::from("--right-axis-label"));
+            self.common_args.push(label);
+        }
+
+        self.right_axis = Some(axis);
+        self.right_axis_plugins = plugins;
+        Ok(self)
+    }
+
+    /// Draw elements of all plugins on one graph, instead of separate graphs of each plugin
+    pub fn with_combine(&mut self, combine: bool) -> Result<&mut Self> {
+        self.graph_args.combine = combine;
+        Ok(self)
+    }
+
+    /// Set limits of vertical axis of all graphs, axis is scaled automatically
+    /// at the side without limit. Unless `rigid` is set, rrdtool still expands
+    /// the axis when data exceeds the limits.
+    pub fn with_y_limits(
+        &mut self,
+        min: Option<f64>,
+        max: Option<f64>,
+        rigid: bool,
+    ) -> Result<&mut Self> {
+        if let Some(min) = min {
+            self.common_args.push(String::from("--lower-limit"));
+            self.common_args.push(min.to_string());
+        }
+
+        if let Some(max) = max {
+            self.common_args.push(String::from("--upper-limit"));
+            self.common_args.push(max.to_string());
+        }
+
+        if rigid {
+            self.common_args.push(String::from("--rigid"));
+        }
+
         Ok(self)
     }
 
-    /// Add height of output file
+    /// Add horizontal line to all graphs, may be called multiple times
+    pub fn with_hrule(&mut self, hrule: &Hrule) -> Result<&mut Self> {
+        self.common_args.push(hrule.to_rrdtool());
+        Ok(self)
+    }
+
+    /// Add height of output file, between 1 and [`Rrdtool::MAX_SIZE`] pixels
     pub fn with_height(&mut self, height: u32) -> Result<&mut Self> {
-        self.common_args.push(String::from("-h"));
-        self.common_args.push(height.to_string());
+        Rrdtool::validate_size("Height", height)?;
+        self.height = Some(height);
+        Ok(self)
+    }
+
+    /// Set width and height of graphs of given plugins, overriding size of all graphs
+    pub fn with_plugin_sizes(
+        &mut self,
+        plugin_sizes: HashMap<Plugins, (u32, u32)>,
+    ) -> Result<&mut Self> {
+        for (plugin, (width, height)) in &plugin_sizes {
+            Rrdtool::validate_size("Width", *width)
+                .and_then(|_| Rrdtool::validate_size("Height", *height))
+                .context(format!("Invalid size of {} graphs", plugin.as_str()))?;
+        }
+
+        self.plugin_sizes = plugin_sizes;
         Ok(self)
     }
 
+    /// Build -w and -h arguments of graph with given index, size of its plugin is used
+    /// if given, otherwise the common one
+    fn build_size_args(&self, index: usize) -> Vec<String> {
+        let plugin_size = self.graph_args.info[index]
+            .plugin
+            .and_then(|plugin| self.plugin_sizes.get(&plugin));
+
+        let (width, height) = match plugin_size {
+            Some((width, height)) => (Some(*width), Some(*height)),
+            None => (self.width, self.height),
+        };
+
+        let mut args = Vec::new();
+
+        if let Some(width) = width {
+            args.push(String::from("-w"));
+            args.push(width.to_string());
+        }
+
+        if let Some(height) = height {
+            args.push(String::from("-h"));
+            args.push(height.to_string());
+        }
+
+        args
+    }
+
     /// Add start timestamp
     pub fn with_start(&mut self, start: u64) -> Result<&mut Self> {
         self.common_args.push(String::from("--start"));
         self.common_args.push(start.to_string());
+        self.start = Some(start);
         Ok(self)
     }
 
@@ -132,292 +536,3619 @@ impl Rrdtool {
     pub fn with_end(&mut self, end: u64) -> Result<&mut Self> {
         self.common_args.push(String::from("--end"));
         self.common_args.push(end.to_string());
+        self.end = Some(end);
         Ok(self)
     }
 
-    /// Run all plugins
-    pub fn with_plugins(&mut self, plugins_config: config::PluginsConfig) -> Result<&mut Self> {
-        for (plugin, data) in plugins_config.data.iter() {
-            match plugin {
-                Plugins::Processes => {
-                    self.enter_plugin(
-                        data.as_ref()
-                            .downcast_ref::<processes::processes_data::ProcessesData>()
-                            .context("Failed to cast ProcessData")?,
-                    )
-                    .context("Failed \"process\" plugin")?;
-                }
-                Plugins::Memory => {
-                    self.enter_plugin(
-                        data.as_ref()
-                            .downcast_ref::<memory::memory_data::MemoryData>()
-                            .context("Failed to cast MemoryData")?,
-                    )
-                    .context("Failed \"memory\" plugin")?;
-                }
-            };
-        }
-
+    /// Fill gaps in data up to given number of seconds
+    pub fn with_nan_fill(&mut self, seconds: Option<u64>) -> Result<&mut Self> {
+        self.graph_args.nan_fill = seconds;
         Ok(self)
     }
 
-    /// Execute command
-    pub fn exec(&mut self) -> Result<()> {
-        match self.target {
-            Target::Local => {
-                info!("Executing {} locally...", self.command);
-
-                self.exec_local().context("Failed in exec_local")
-            }
-            Target::Remote => {
-                info!("Executing {} remotely...", self.command);
+    /// Set consolidation function of data read from rrd files, AVERAGE by default
+    pub fn with_cf(&mut self, cf: ConsolidationFunction) -> Result<&mut Self> {
+        self.graph_args.cf = cf;
+        Ok(self)
+    }
 
-                self.exec_remote().context("Failed in exec_remote")
+    /// Read given data source from rrd files instead of the default one,
+    /// data sources chosen by plugins for particular metrics are kept
+    pub fn with_ds(&mut self, ds: Option<String>) -> Result<&mut Self> {
+        if let Some(ds) = &ds {
+            // Names allowed by rrdtool create
+            if ds.is_empty()
+                || ds.len() > 19
+                || !ds.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                anyhow::bail!(
+                    "Invalid data source name {}, expected 1 to 19 letters, digits or underscores",
+                    ds
+                );
             }
         }
+
+        self.graph_args.ds = ds;
+        Ok(self)
     }
 
-    /// Execute rrdtool locally
-    fn exec_local(&self) -> Result<()> {
-        let commands = self.build_rrdtool_args();
+    /// Set function reducing data when there are more data points than pixels
+    pub fn with_reduce(&mut self, reduce: Option<ConsolidationFunction>) -> Result<&mut Self> {
+        self.graph_args.reduce = reduce;
+        Ok(self)
+    }
 
-        for args in commands {
-            trace!("Executing locally: {} {:?}", self.command, args);
+    /// Enable sparkline mode, which composes all arguments needed for minimal graph
+    pub fn with_sparkline(&mut self, sparkline: bool) -> Result<&mut Self> {
+        self.sparkline = sparkline;
 
-            let output = Command::new(&self.command)
-                .args(&args)
-                .output()
-                .context(format!(
-                    "Failed to execute rrdtool: {}, args: {:?}",
-                    self.command, args
-                ))?;
+        if sparkline {
+            self.common_args
+                .extend(Rrdtool::SPARKLINE_ARGS.iter().map(|arg| String::from(*arg)));
+        }
 
-            if !output.status.success() {
-                print_process_command_output(output);
+        Ok(self)
+    }
 
-                anyhow::bail!(
-                    "Local rrdtool returned some errors! {} {:?}",
-                    self.command,
-                    args
-                )
-            }
+    /// Set number of retries of copying result back from remote target
+    pub fn with_scp_retries(&mut self, retries: u32) -> Result<&mut Self> {
+        self.scp_retries = retries;
+        Ok(self)
+    }
 
-            info!("Successfully saved {}", args[1]);
+    /// Show time range in graph titles, formatted with given chrono format
+    pub fn with_title_time_format(&mut self, format: Option<String>) -> Result<&mut Self> {
+        if let Some(format) = &format {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                anyhow::bail!("Invalid title time format: {}", format);
+            }
         }
 
-        Ok(())
+        self.title_time_format = format;
+        Ok(self)
     }
 
-    /// Execute rrdtool remotely
-    fn exec_remote(&self) -> Result<()> {
-        let commands = self.build_rrdtool_args();
+    /// Add overview graph showing whole data history with selected time window marked
+    pub fn with_overview(&mut self, overview: bool) -> Result<&mut Self> {
+        self.overview = overview;
+        Ok(self)
+    }
 
-        let network_address = String::from(self.username.as_ref().unwrap().as_str())
-            + "@"
-            + self.hostname.as_ref().unwrap();
+    /// Refuse to generate graphs if the newest data point is older than `max_age` seconds
+    ///
+    /// # Arguments
+    /// * `max_age` - maximum age of data in seconds, no check if None
+    /// * `now` - current timestamp
+    ///
+    pub fn with_max_data_age(&mut self, max_age: Option<u64>, now: u64) -> Result<&mut Self> {
+        self.max_data_age = max_age.map(|max_age| (max_age, now));
+        Ok(self)
+    }
 
-        for (index, mut args) in commands.into_iter().enumerate() {
-            // Insert network address
-            args.insert(0, String::from(network_address.as_str()));
+    /// Arrange legend in given number of columns
+    pub fn with_legend_columns(&mut self, columns: Option<u32>) -> Result<&mut Self> {
+        if columns == Some(0) {
+            anyhow::bail!("Number of legend columns must be positive");
+        }
 
-            // Insert command
-            args.insert(1, String::from(self.command.as_str()));
+        self.graph_args.legend_columns = columns;
+        Ok(self)
+    }
 
-            trace!("Executing remotely: ssh {:?}", args);
+    /// Show or hide legend box of all graphs, and set its position and order of entries.
+    /// Hidden legend only suppresses the box, labels are kept, e.g. naming exported columns.
+    pub fn with_legend(
+        &mut self,
+        visible: bool,
+        position: Option<LegendPosition>,
+        direction: Option<LegendDirection>,
+    ) -> Result<&mut Self> {
+        if !visible {
+            if position.is_some() || direction.is_some() {
+                warn!("Legend is hidden, its position and direction are ignored");
+            }
 
-            // Execute rrdtool remotely
-            let output = Command::new("ssh")
-                .args(&args)
-                .output()
-                .context("Failed to execute SSH command")?;
+            self.common_args.push(String::from("--no-legend"));
+            return Ok(self);
+        }
 
-            if !output.status.success() {
-                print_process_command_output(output);
+        if let Some(position) = position {
+            self.common_args.push(String::from("--legend-position"));
+            self.common_args.push(String::from(position.as_str()));
+        }
 
-                anyhow::bail!("Failed to execute ssh command: ssh {:?}", args)
-            }
+        if let Some(direction) = direction {
+            self.common_args.push(String::from("--legend-direction"));
+            self.common_args.push(String::from(direction.as_str()));
+        }
 
-            let output_filename = self.get_output_filename(index);
+        Ok(self)
+    }
 
-            // scp result back to host
-            let args = &[
-                String::from(&network_address) + ":" + self.remote_filename.as_ref().unwrap(),
-                String::from(output_filename.as_str()),
-            ];
+    /// Print maximum, average and last value of each line in legend
+    pub fn with_statistics(&mut self, statistics: bool) -> Result<&mut Self> {
+        self.graph_args.statistics = statistics;
+        Ok(self)
+    }
 
-            trace!("Executing remotely: scp {:?}", args);
+    /// Draw maximum of each line as translucent area behind it
+    pub fn with_show_max(&mut self, show_max: bool) -> Result<&mut Self> {
+        self.graph_args.show_max = show_max;
+        Ok(self)
+    }
 
-            let output = Command::new("scp")
-                .args(args)
-                .output()
-                .context("Failed to execute SSH")?;
+    /// Draw moving average over given number of seconds over each element
+    pub fn with_trend(&mut self, window: Option<u64>) -> Result<&mut Self> {
+        self.graph_args.trend = window;
+        Ok(self)
+    }
 
-            if !output.status.success() {
-                print_process_command_output(output);
+    /// Draw all data with given graph element instead of the one chosen by plugins
+    pub fn with_style(&mut self, style: Option<GraphElement>) -> Result<&mut Self> {
+        self.graph_args.style = style;
+        Ok(self)
+    }
 
-                anyhow::bail!("Failed to scp result image back to host: scp {:?}", args)
-            }
+    /// Copy graphs generated on remote target with given program
+    pub fn with_transfer(&mut self, transfer: Transfer) -> Result<&mut Self> {
+        self.transfer = transfer;
+        Ok(self)
+    }
+
+    /// Export data to JSON with rrdtool xport instead of drawing graphs,
+    /// the same data is exported as would be drawn
+    pub fn with_export(&mut self, export: bool) -> Result<&mut Self> {
+        self.export = export;
+        self.graph_args.export = export;
 
-            info!("Successfully saved {}", output_filename);
+        if export {
+            self.subcommand = String::from("xport");
         }
 
-        Ok(())
+        Ok(self)
     }
 
-    /// Build vector of rrdtool arguments based on data in self
-    fn build_rrdtool_args(&self) -> Vec<Vec<String>> {
-        let mut commands = Vec::new();
+    /// Print shell commands to standard output instead of executing them
+    pub fn with_dry_run(&mut self, dry_run: bool) -> Result<&mut Self> {
+        self.dry_run = dry_run;
+        Ok(self)
+    }
 
-        let no_of_output_files = self.graph_args.args.len();
+    /// Write shell-quoted command generating each graph to file named after the graph
+    /// with .cmd appended, e.g. out.png.cmd, so the graph can be reproduced
+    pub fn with_embed_command(&mut self, embed_command: bool) -> Result<&mut Self> {
+        self.embed_command = embed_command;
+        Ok(self)
+    }
 
-        debug!("Building arguments for {} files.", no_of_output_files);
+    /// Log shell-quoted command generating each graph at info level before executing it,
+    /// graphs are still generated unlike with dry run
+    pub fn with_print_command(&mut self, print_command: bool) -> Result<&mut Self> {
+        self.print_command = print_command;
+        Ok(self)
+    }
 
-        for i in 0..no_of_output_files {
-            let index = i as usize;
-            commands.push(Vec::new());
+    /// Overwrite existing output files, by default graphs aren't generated if any exists
+    pub fn with_force(&mut self, force: bool) -> Result<&mut Self> {
+        self.force = force;
+        Ok(self)
+    }
 
-            commands[index].push(String::from(self.subcommand.as_str()));
+    /// Connect to remote target with given identity file instead of default keys
+    pub fn with_ssh_key(&mut self, key: Option<PathBuf>) -> Result<&mut Self> {
+        if let Some(key) = &key {
+            if !key.is_file() {
+                anyhow::bail!("Cannot find SSH key {}", key.display());
+            }
+        }
 
-            let output_filename = self.get_output_filename(index);
+        self.ssh_options.identity = key;
+        Ok(self)
+    }
 
-            match self.target {
-                Target::Local => {
-                    commands[index].push(String::from(output_filename.as_str()));
-                    debug!("Building arguments for local {} file.", output_filename);
-                }
-                Target::Remote => {
-                    commands[index].push(String::from(self.remote_filename.as_ref().unwrap()));
-                    debug!(
-                        "Building arguments for remote {} file.",
-                        self.remote_filename.as_ref().unwrap()
-                    );
-                }
-            }
+    /// Execute all commands on remote target over one SSH connection, opened for the run
+    pub fn with_ssh_multiplex(&mut self, ssh_multiplex: bool) -> Result<&mut Self> {
+        self.ssh_multiplex = ssh_multiplex;
+        Ok(self)
+    }
 
-            for common_arg in &self.common_args {
-                commands[index].push(String::from(common_arg));
-            }
+    /// Fail instead of executing ssh, scp or rsync, remote target is never contacted
+    pub fn with_offline(&mut self, offline: bool) -> Result<&mut Self> {
+        self.ssh_options.offline = offline;
+        Ok(self)
+    }
 
-            for graph_arg in &self.graph_args.args[index] {
-                commands[index].push(String::from(graph_arg));
-            }
+    /// Log step of each rrd file, which is the best possible resolution of graph
+    pub fn with_show_rrd_step(&mut self, show_rrd_step: bool) -> Result<&mut Self> {
+        self.show_rrd_step = show_rrd_step;
+        Ok(self)
+    }
 
-            trace!(
-                "Built arguments for {} filename: {:?}",
-                output_filename,
-                commands
-            );
+    /// Set titles of graphs
+    ///
+    /// # Arguments
+    /// * `title` - title of all graphs
+    /// * `plugin_titles` - titles of graphs of given plugins, overriding `title`
+    ///
+    pub fn with_titles(
+        &mut self,
+        title: Option<String>,
+        plugin_titles: HashMap<Plugins, String>,
+    ) -> Result<&mut Self> {
+        self.title = title;
+        self.plugin_titles = plugin_titles;
+        Ok(self)
+    }
+
+    /// Title all graphs with name of the host, i.e. hostname of remote target or last
+    /// component of local input path. Title given with [`Rrdtool::with_titles`] is kept.
+    pub fn with_auto_title(&mut self, auto_title: bool) -> Result<&mut Self> {
+        if auto_title && self.title.is_none() {
+            self.title = Some(match &self.hostname {
+                Some(hostname) => String::from(hostname),
+                None => get_host_name(&self.input_dir),
+            });
         }
 
-        commands
+        Ok(self)
     }
 
-    /// Build output filename based on current index and number of expected output files
-    fn get_output_filename(&self, index: usize) -> String {
-        match self.graph_args.args.len() {
-            1 => String::from(self.output_filename.as_str()),
-            _ => {
-                let mut output_filename = String::from(self.output_filename.as_str());
-                let appendix = String::from("_") + (index + 1).to_string().as_str();
+    /// Run plugins on all given inputs, which have to be on the same target as the input
+    /// given to [`Rrdtool::new`]. Legends and graph labels of each input are prefixed with
+    /// its host name, i.e. name of its directory. Hosts on different targets can be compared
+    /// with [`Rrdtool::with_compare_hosts`].
+    pub fn with_inputs(&mut self, inputs: &[PathBuf]) -> Result<&mut Self> {
+        let mut parsed = Vec::new();
+
+        for input in inputs {
+            let (target, input_dir, username, hostname, port) = Rrdtool::parse_input_path(input)?;
+
+            if target != self.target
+                || username != self.username
+                || hostname != self.hostname
+                || port != self.ssh_options.port
+            {
+                anyhow::bail!(
+                    "Input {} is not on the same target as {}",
+                    input.display(),
+                    self.input_dir
+                );
+            }
 
-                output_filename.insert_str(output_filename.rfind('.').unwrap(), appendix.as_str());
+            parsed.push((get_host_name(&input_dir), input_dir));
+        }
 
-                trace!("Returning output filename: {}", output_filename);
+        // Hosts with the same directory name, e.g. /a/host and /b/host, are told apart by
+        // the whole input directory. Host is a part of output filenames, so without separators.
+        let names = parsed
+            .iter()
+            .map(|(host, _)| host.clone())
+            .collect::<Vec<String>>();
 
-                output_filename
+        for (host, input_dir) in parsed.iter_mut() {
+            if names.iter().filter(|name| *name == host).count() > 1 {
+                *host = input_dir
+                    .trim_matches(&['/', '\\'][..])
+                    .replace(&['/', '\\'][..], "_");
             }
         }
+
+        self.inputs = parsed;
+        Ok(self)
     }
 
-    /// Parse input path to get target type, path, username and hostname
-    fn parse_input_path(
-        input_dir: &Path,
-    ) -> Result<(Target, String, Option<String>, Option<String>)> {
-        let re = regex::Regex::new(".*@.*:.*").context("Failed to create regex")?;
+    /// Run all plugins, on each input if multiple inputs are given
+    pub fn with_plugins(&mut self, plugins_config: config::PluginsConfig) -> Result<&mut Self> {
+        if self.inputs.len() < 2 {
+            self.enter_plugins(&plugins_config)?;
+            return Ok(self);
+        }
 
-        match re.is_match(input_dir.to_str().context("Failed to parse regex")?) {
-            // Remote
-            true => {
+        let input_dir = self.input_dir.clone();
+
+        for (host, dir) in self.inputs.clone() {
+            debug!("Running plugins of host {} in {}", host, dir);
+
+            self.input_dir = dir;
+            self.graph_args.host = Some(host.clone());
+
+            self.enter_plugins(&plugins_config)
+                .context(format!("Failed plugins of host {}", host))?;
+        }
+
+        self.input_dir = input_dir;
+        self.graph_args.host = None;
+
+        Ok(self)
+    }
+
+    /// Run all plugins on current input directory, in order given in [`config::PluginsConfig`]
+    fn enter_plugins(&mut self, plugins_config: &config::PluginsConfig) -> Result<()> {
+        for plugin in plugins_config.ordered() {
+            let data = &plugins_config.data[&plugin];
+            let first_graph = self.graph_args.args.len();
+
+            self.graph_args.right_axis = match self.right_axis_plugins.contains(&plugin) {
+                true => self.right_axis,
+                false => None,
+            };
+
+            let vertical_label = match plugin {
+                Plugins::Processes => {
+                    let data = data
+                        .as_ref()
+                        .downcast_ref::<processes::processes_data::ProcessesData>()
+                        .context("Failed to cast ProcessData")?;
+
+                    self.enter_plugin(data)
+                        .context("Failed \"process\" plugin")?;
+
+                    match data.process_count {
+                        true => "processes",
+                        false => data.metric.vertical_label(),
+                    }
+                }
+                Plugins::Memory => {
+                    let data = data
+                        .as_ref()
+                        .downcast_ref::<memory::memory_data::MemoryData>()
+                        .context("Failed to cast MemoryData")?;
+
+                    self.enter_plugin(data)
+                        .context("Failed \"memory\" plugin")?;
+
+                    match data.unit {
+                        Some(unit) => unit.as_str(),
+                        None => "bytes",
+                    }
+                }
+                Plugins::Cpu => {
+                    self.enter_plugin(
+                        data.as_ref()
+                            .downcast_ref::<cpu::cpu_data::CpuData>()
+                            .context("Failed to cast CpuData")?,
+                    )
+                    .context("Failed \"cpu\" plugin")?;
+
+                    "jiffies"
+                }
+                Plugins::Df => {
+                    self.enter_plugin(
+                        data.as_ref()
+                            .downcast_ref::<df::df_data::DfData>()
+                            .context("Failed to cast DfData")?,
+                    )
+                    .context("Failed \"df\" plugin")?;
+
+                    "bytes"
+                }
+            };
+
+            // Plugin default, unless user has chosen the label for all graphs.
+            // Unit of plugins drawn against the right axis belongs to the right axis label.
+            let vertical_label = match &self.vertical_label {
+                Some(_) => Vec::new(),
+                None if self.graph_args.right_axis.is_some() => Vec::new(),
+                None => self.build_vertical_label_args(vertical_label),
+            };
+
+            for info in &mut self.graph_args.info[first_graph..] {
+                info.plugin = Some(plugin);
+                info.options.extend(vertical_label.iter().cloned());
+            }
+        }
+
+        self.graph_args.right_axis = None;
+
+        Ok(())
+    }
+
+    /// Execute command, returns paths of all saved graphs.
+    /// In dry run only commands generating graphs are printed and no graphs are saved.
+    pub fn exec(&mut self) -> Result<Vec<PathBuf>> {
+        if self.dry_run {
+            if self.max_data_age.is_some() || self.show_rrd_step || self.overview {
+                warn!("Checks of rrd files and overview graphs are skipped in dry run");
+            }
+
+            self.write_commands(&mut std::io::stdout())
+                .context("Failed to print commands")?;
+
+            return Ok(Vec::new());
+        }
+
+        if self.ssh_multiplex
+            && self.target == Target::Remote
+            && self.ssh_options.control_path.is_none()
+        {
+            return self.exec_multiplexed();
+        }
+
+        if let Some((max_age, now)) = self.max_data_age {
+            self.verify_data_age(max_age, now)
+                .context("Data is not fresh")?;
+        }
+
+        if self.show_rrd_step {
+            self.log_rrd_steps()
+                .context("Failed to read steps of rrd files")?;
+        }
+
+        if self.overview && self.export {
+            warn!("Overview graphs are skipped in export");
+        } else if self.overview {
+            self.add_overview_graphs()
+                .context("Failed to add overview graphs")?;
+        }
+
+        // Standard output can't be split into numbered files
+        if self.output_filename == Rrdtool::STDOUT && self.graph_args.args.len() > 1 {
+            anyhow::bail!(
+                "Only one graph can be written to standard output, but {} would be generated",
+                self.graph_args.args.len()
+            );
+        }
+
+        match self.target {
+            Target::Local => {
+                info!("Executing {} locally...", self.command);
+
+                if !self.graph_args.args.is_empty() {
+                    self.check_command()?;
+                }
+
+                self.exec_local().context("Failed in exec_local")
+            }
+            Target::Remote => {
+                info!("Executing {} remotely...", self.command);
+
+                self.exec_remote().context("Failed in exec_remote")
+            }
+        }
+    }
+
+    /// Execute with all ssh and scp commands sharing one master connection,
+    /// which is closed and its control socket removed at the end
+    fn exec_multiplexed(&mut self) -> Result<Vec<PathBuf>> {
+        let control_dir =
+            TempDir::new().context("Failed to create directory for SSH control socket")?;
+
+        self.ssh_options.control_path = Some(control_dir.path().join("control"));
+
+        let result = self
+            .open_ssh_master()
+            .context("Failed to open SSH master connection")
+            .and_then(|_| {
+                let result = self.exec();
+                self.close_ssh_master();
+                result
+            });
+
+        self.ssh_options.control_path = None;
+
+        result
+    }
+
+    /// Open SSH master connection in background, shared by the following commands
+    fn open_ssh_master(&self) -> Result<()> {
+        let mut args = self.ssh_options.args("ssh");
+        args.extend(
+            [
+                "-o",
+                "ControlMaster=auto",
+                "-o",
+                "ControlPersist=yes",
+                "-f",
+                "-N",
+            ]
+            .iter()
+            .map(|arg| String::from(*arg)),
+        );
+        args.push(self.get_network_address());
+
+        trace!("Opening SSH master connection: ssh {:?}", args);
+
+        self.ssh_options.ensure_online("ssh")?;
+
+        let output = self
+            .runner
+            .run("ssh", &args)
+            .context("Failed to execute SSH command")?;
+
+        if !output.status.success() {
+            print_process_command_output(output);
+
+            anyhow::bail!("Failed to execute ssh command: ssh {:?}", args)
+        }
+
+        Ok(())
+    }
+
+    /// Close SSH master connection, failure is only logged as graphs are already generated
+    fn close_ssh_master(&self) {
+        let mut args = self.ssh_options.args("ssh");
+        args.push(String::from("-O"));
+        args.push(String::from("exit"));
+        args.push(self.get_network_address());
+
+        trace!("Closing SSH master connection: ssh {:?}", args);
+
+        match self.runner.run("ssh", &args) {
+            Ok(output) if output.status.success() => (),
+            _ => warn!("Failed to close SSH master connection: ssh {:?}", args),
+        }
+    }
+
+    /// Check whether the newest data point of the primary series, i.e. the first one drawn,
+    /// is not older than `max_age` seconds
+    fn verify_data_age(&self, max_age: u64, now: u64) -> Result<()> {
+        let file = match self
+            .graph_args
+            .info
+            .iter()
+            .flat_map(|info| &info.files)
+            .next()
+        {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let last = self.query_timestamp("last", file)?;
+        let age = now.saturating_sub(last);
+
+        debug!("Newest data in {} is {}s old", file, age);
+
+        if age > max_age {
+            anyhow::bail!(
+                "Newest data in {} is {}s old, which is more than allowed {}s. Is collectd running?",
+                file,
+                age,
+                max_age
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Log step of each rrd file used by graphs, read with rrdtool info
+    fn log_rrd_steps(&self) -> Result<()> {
+        for file in self.get_input_files() {
+            let info = self.query(&[String::from("info"), String::from(file.as_str())])?;
+            let step = parse_rrd_step(&info)
+                .context(format!("Cannot find step in rrdtool info of {}", file))?;
+
+            info!("Step of {} is {}s", file, step);
+        }
+
+        Ok(())
+    }
+
+    /// Add overview graph for each graph, spanning from the first to the last
+    /// timestamp of its rrd files, with vertical lines at window boundaries
+    fn add_overview_graphs(&mut self) -> Result<()> {
+        let graphs = self.graph_args.args.len();
+
+        for index in 0..graphs {
+            let (first, last) = self
+                .get_data_extent(&self.graph_args.info[index].files)
+                .context("Failed to get data extent")?;
+
+            debug!("Overview of graph {} spans {} - {}", index, first, last);
+
+            let label = match self.get_output_appendix(index) {
+                appendix if appendix.is_empty() => String::from("overview"),
+                appendix => String::from(&appendix[1..]) + "_overview",
+            };
+
+            let mut args = self.graph_args.args[index].clone();
+            args.extend(self.build_window_vrules());
+
+            let mut info = self.graph_args.info[index].clone();
+            info.label = Some(label);
+            info.options = vec![
+                String::from("--start"),
+                first.to_string(),
+                String::from("--end"),
+                last.to_string(),
+                String::from("-h"),
+                Rrdtool::OVERVIEW_HEIGHT.to_string(),
+            ];
+
+            self.graph_args.args.push(args);
+            self.graph_args.info.push(info);
+        }
+
+        Ok(())
+    }
+
+    /// Build vertical lines marking selected time window
+    fn build_window_vrules(&self) -> Vec<String> {
+        self.start
+            .iter()
+            .chain(self.end.iter())
+            .map(|timestamp| {
+                String::from("VRULE:") + &timestamp.to_string() + Rrdtool::OVERVIEW_WINDOW_COLOR
+            })
+            .collect()
+    }
+
+    /// Returns the earliest first and the latest last timestamp of given rrd files
+    fn get_data_extent(&self, files: &[String]) -> Result<(u64, u64)> {
+        let mut extent: Option<(u64, u64)> = None;
+
+        for file in files {
+            let first = self.query_timestamp("first", file)?;
+            let last = self.query_timestamp("last", file)?;
+
+            extent = Some(match extent {
+                Some((min, max)) => (std::cmp::min(min, first), std::cmp::max(max, last)),
+                None => (first, last),
+            });
+        }
+
+        extent.context("No rrd files to get data extent from")
+    }
+
+    /// Returns the highest average of data source of rrd file in the selected time window,
+    /// read with PRINT of rrdtool graph. Fails if there is no data in the window.
+    pub fn get_peak(&self, file: &str, ds: &str) -> Result<f64> {
+        let mut args = vec![String::from("graph"), String::from("/dev/null")];
+
+        if let Some(start) = self.start {
+            args.push(String::from("--start"));
+            args.push(start.to_string());
+        }
+
+        if let Some(end) = self.end {
+            args.push(String::from("--end"));
+            args.push(end.to_string());
+        }
+
+        args.push(format!(
+            "DEF:value={}:{}:{}",
+            file,
+            ds,
+            self.graph_args.cf.as_str()
+        ));
+        args.push(String::from("VDEF:peak=value,MAXIMUM"));
+        args.push(String::from("PRINT:peak:%lf"));
+
+        // First line is size of the graph, e.g. 0x0, followed by printed values
+        let output = self.query(&args)?;
+        let peak = output.lines().last().unwrap_or_default().trim();
+
+        match peak.parse::<f64>() {
+            Ok(peak) if !peak.is_nan() => Ok(peak),
+            _ => anyhow::bail!("No data to get peak of {} from: {}", file, peak),
+        }
+    }
+
+    /// Run rrdtool subcommand returning timestamp for given file, e.g. rrdtool first file.rrd
+    fn query_timestamp(&self, subcommand: &str, file: &str) -> Result<u64> {
+        let output = self.query(&[String::from(subcommand), String::from(file)])?;
+
+        output.trim().parse::<u64>().context(format!(
+            "Cannot parse rrdtool {} output: {}",
+            subcommand, output
+        ))
+    }
+
+    /// Run rrdtool on target and return its standard output.
+    /// Missing local rrdtool is reported as [`CggError::RrdtoolNotFound`], as in [`Rrdtool::exec`].
+    pub(crate) fn query(&self, args: &[String]) -> Result<String> {
+        let output = self.run_query(args)?;
+
+        if !output.status.success() {
+            let error = self.query_failure(&output);
+
+            print_process_command_output(output);
+
+            return Err(anyhow::Error::new(error).context(format!(
+                "Query returned some errors! {} {:?}",
+                self.command, args
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Run rrdtool on target and return its output, whatever the status
+    fn run_query(&self, args: &[String]) -> Result<std::process::Output> {
+        let (program, args) = match self.target {
+            Target::Local => (self.command.as_str(), args.to_vec()),
+            Target::Remote => {
+                let mut command = vec![String::from(self.command.as_str())];
+                command.extend(args.iter().cloned());
+
+                ("ssh", self.build_ssh_args(command))
+            }
+        };
+
+        trace!("Querying: {} {:?}", program, args);
+
+        if self.target == Target::Remote {
+            self.ssh_options.ensure_online(program)?;
+        }
+
+        let output = match self.runner.run(program, &args) {
+            Ok(output) => output,
+            Err(error)
+                if self.target == Target::Local && error.kind() == std::io::ErrorKind::NotFound =>
+            {
+                return Err(CggError::RrdtoolNotFound {
+                    command: String::from(&self.command),
+                    target: None,
+                }
+                .into())
+            }
+            Err(error) => {
+                return Err(error).context(format!("Failed to execute {} {:?}", program, args))
+            }
+        };
+
+        Ok(output)
+    }
+
+    /// Returns error of failed query, see [`Rrdtool::query`]
+    fn query_failure(&self, output: &std::process::Output) -> CggError {
+        match self.target {
+            Target::Local => CggError::rrdtool_failed(output),
+            Target::Remote => CggError::remote_failed(self.get_network_address(), output),
+        }
+    }
+
+    /// Run local rrdtool with --version, so missing binary is reported before
+    /// any graph is attempted. Exit code is ignored, as some builds exit with 1.
+    fn check_command(&self) -> Result<()> {
+        match self.runner.run(&self.command, &[String::from("--version")]) {
+            Ok(_) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Err(CggError::RrdtoolNotFound {
+                    command: String::from(&self.command),
+                    target: None,
+                }
+                .into())
+            }
+            Err(error) => Err(error).context(format!("Failed to execute {}", self.command)),
+        }
+    }
+
+    /// Returns version of rrdtool on target, e.g. 1.7.2. Exit code is ignored when version
+    /// is printed, as some builds exit with 1 after printing usage.
+    pub fn get_version(&self) -> Result<String> {
+        let output = self.run_query(&[String::from("--version")])?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+
+        match super::probe::parse_version(&stdout) {
+            Some((version, _)) => Ok(version),
+            None if output.status.success() => {
+                anyhow::bail!("Cannot find rrdtool version in: {}", stdout)
+            }
+            None => {
+                let error = self.query_failure(&output);
+
+                print_process_command_output(output);
+
+                Err(anyhow::Error::new(error).context("Failed to get rrdtool version"))
+            }
+        }
+    }
+
+    /// Returns all rrd files used by graphs, without duplicates
+    pub fn get_input_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = Vec::new();
+
+        for file in self.graph_args.info.iter().flat_map(|info| &info.files) {
+            if !files.contains(file) {
+                files.push(String::from(file));
+            }
+        }
+
+        files
+    }
+
+    /// Copy remote rrd file to local temporary directory, kept until self is dropped.
+    /// Returns local path of the copy.
+    pub fn fetch_remote_file(
+        &mut self,
+        username: &str,
+        hostname: &str,
+        ssh_options: &SshOptions,
+        remote_path: &Path,
+        index: usize,
+    ) -> Result<PathBuf> {
+        if self.fetched.is_none() {
+            self.fetched = Some(TempDir::new().context("Failed to create temp dir")?);
+        }
+
+        let local_path = self
+            .fetched
+            .as_ref()
+            .unwrap()
+            .path()
+            .join(format!("{}-{}.rrd", index, hostname));
+
+        let mut args = ssh_options.args("scp");
+        args.push(String::from(username) + "@" + hostname + ":" + remote_path.to_str().unwrap());
+        args.push(String::from(local_path.to_str().unwrap()));
+
+        trace!("Executing remotely: scp {:?}", args);
+
+        ssh_options.ensure_online("scp")?;
+
+        let output = self
+            .runner
+            .run("scp", &args)
+            .context("Failed to execute scp")?;
+
+        if !output.status.success() {
+            print_process_command_output(output);
+
+            anyhow::bail!("Failed to copy rrd file: scp {:?}", args)
+        }
+
+        info!("Copied {} from {}", remote_path.display(), hostname);
+
+        Ok(local_path)
+    }
+
+    /// Write shell commands generating graphs, as executed by exec, one per line
+    fn write_commands(&self, out: &mut dyn Write) -> Result<()> {
+        let rsync = self.target == Target::Remote && self.transfer == Transfer::Rsync;
+
+        if rsync {
+            let args = self.build_ssh_args(self.build_rsync_mkdir());
+            writeln!(out, "{}", build_shell_command("ssh", &args))?;
+        }
+
+        for (index, mut args) in self.build_rrdtool_args().into_iter().enumerate() {
+            match self.target {
+                Target::Local if self.export => writeln!(
+                    out,
+                    "{} > {}",
+                    build_shell_command(&self.command, &args),
+                    shell_quote(&self.get_output_filename(index))
+                )?,
+                Target::Local => writeln!(out, "{}", build_shell_command(&self.command, &args))?,
+                Target::Remote => {
+                    args.insert(0, String::from(self.command.as_str()));
+
+                    let args = self.build_ssh_args(args);
+                    writeln!(out, "{}", build_shell_command("ssh", &args))?;
+
+                    if self.transfer == Transfer::Scp {
+                        let (program, args) = self.build_fetch_args(
+                            &[self.get_remote_filename(index)],
+                            &self.get_output_filename(index),
+                        );
+                        writeln!(out, "{}", build_shell_command(program, &args))?;
+                    }
+                }
+            }
+        }
+
+        if rsync {
+            let (program, args) =
+                self.build_fetch_args(&self.get_remote_filenames(), &self.get_rsync_destination());
+            writeln!(out, "{}", build_shell_command(program, &args))?;
+
+            let args = self.build_ssh_args(self.build_rsync_rmdir());
+            writeln!(out, "{}", build_shell_command("ssh", &args))?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute rrdtool locally
+    /// Graphs are generated concurrently, by at most as many threads as available cores.
+    /// All graphs are attempted, even if some of them fail.
+    fn exec_local(&self) -> Result<Vec<PathBuf>> {
+        self.check_existing_outputs()?;
+
+        let started = Instant::now();
+        let commands = self.build_rrdtool_args();
+
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1)
+            .min(commands.len());
+
+        debug!(
+            "Executing {} commands in {} threads",
+            commands.len(),
+            threads
+        );
+
+        let next = AtomicUsize::new(0);
+        let errors = Mutex::new(Vec::new());
+
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+
+                    match commands.get(index) {
+                        Some(args) => {
+                            let output_filename = self.get_output_filename(index);
+
+                            if let Err(error) = self.exec_local_command(args, &output_filename) {
+                                errors.lock().unwrap().push((index, error));
+                            }
+                        }
+                        None => break,
+                    }
+                });
+            }
+        });
+
+        let mut errors = errors.into_inner().unwrap();
+        errors.sort_by_key(|(index, _)| *index);
+
+        match errors.len() {
+            0 => (),
+            1 => return Err(errors.remove(0).1),
+            failed => anyhow::bail!(
+                "Failed to generate {} of {} graphs:\n{}",
+                failed,
+                commands.len(),
+                errors
+                    .iter()
+                    .map(|(_, error)| format!("{:#}", error))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            ),
+        }
+
+        // Graph written to standard output isn't a generated file
+        let paths = (0..commands.len())
+            .map(|index| self.get_output_filename(index))
+            .filter(|path| path != Rrdtool::STDOUT)
+            .map(PathBuf::from)
+            .collect::<Vec<PathBuf>>();
+
+        info!("{}", build_run_summary(&paths, started.elapsed(), None));
+
+        Ok(paths)
+    }
+
+    /// Fails listing output files which already exist, unless overwriting is forced
+    fn check_existing_outputs(&self) -> Result<()> {
+        if self.force {
+            return Ok(());
+        }
+
+        let existing = (0..self.graph_args.args.len())
+            .map(|index| self.get_output_filename(index))
+            .filter(|path| path != Rrdtool::STDOUT && Path::new(path).exists())
+            .collect::<Vec<String>>();
+
+        if !existing.is_empty() {
+            anyhow::bail!(
+                "Output files already exist, use --force to overwrite them: {}",
+                existing.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Execute rrdtool locally generating one graph, exported data is written from
+    /// standard output to the output file
+    fn exec_local_command(&self, args: &[String], output_filename: &str) -> Result<()> {
+        trace!("Executing locally: {} {:?}", self.command, args);
+
+        if self.print_command {
+            info!("{}", build_shell_command(&self.command, args));
+        }
+
+        let output = self.runner.run(&self.command, args).context(format!(
+            "Failed to execute rrdtool: {}, args: {:?}",
+            self.command, args
+        ))?;
+
+        if !output.status.success() {
+            let error = CggError::rrdtool_failed(&output);
+
+            print_process_command_output(output);
+
+            return Err(anyhow::Error::new(error).context(format!(
+                "Local rrdtool returned some errors! {} {:?}",
+                self.command, args
+            )));
+        }
+
+        // Logs go to standard error, so only the graph is written to standard output
+        if output_filename == Rrdtool::STDOUT {
+            return std::io::stdout()
+                .write_all(&output.stdout)
+                .context("Failed to write graph to standard output");
+        }
+
+        if self.export {
+            std::fs::write(output_filename, &output.stdout)
+                .context(format!("Failed to write {}", output_filename))?;
+        }
+
+        if self.embed_command {
+            write_command_file(output_filename, &build_shell_command(&self.command, args))?;
+        }
+
+        info!("Successfully saved {}", output_filename);
+
+        Ok(())
+    }
+
+    /// Execute rrdtool remotely
+    fn exec_remote(&self) -> Result<Vec<PathBuf>> {
+        // Checked before anything is generated, so nothing is left on remote target
+        self.check_existing_outputs()?;
+
+        if self.transfer == Transfer::Scp {
+            return self.exec_remote_graphs();
+        }
+
+        self.exec_ssh(self.build_rsync_mkdir())
+            .context("Failed to create remote directory for graphs")?;
+
+        let result = self.exec_remote_graphs();
+
+        // Removed whatever the result, failure doesn't hide result of the run
+        if let Err(error) = self.exec_ssh(self.build_rsync_rmdir()) {
+            warn!(
+                "Failed to remove remote directory {}: {:#}",
+                self.get_rsync_remote_dir(),
+                error
+            );
+        }
+
+        result
+    }
+
+    /// Generate graphs remotely and copy them to local destination
+    fn exec_remote_graphs(&self) -> Result<Vec<PathBuf>> {
+        let started = Instant::now();
+        let commands = self.build_rrdtool_args();
+        let mut paths = Vec::new();
+        let mut shell_commands = Vec::new();
+        let mut transfer = Duration::from_secs(0);
+
+        for (index, mut args) in commands.into_iter().enumerate() {
+            // Insert command
+            args.insert(0, String::from(self.command.as_str()));
+
+            if self.print_command {
+                info!(
+                    "{}",
+                    build_shell_command("ssh", &self.build_ssh_args(args.clone()))
+                );
+            }
+
+            // Execute rrdtool remotely
+            let output = self.run_ssh(args.clone())?;
+
+            // Shell of remote target exits with 127 when command is not found
+            if output.status.code() == Some(127) {
+                print_process_command_output(output);
+
+                return Err(CggError::RrdtoolNotFound {
+                    command: String::from(&self.command),
+                    target: Some(self.get_network_address()),
+                }
+                .into());
+            }
+
+            if !output.status.success() {
+                let error = CggError::remote_failed(self.get_network_address(), &output);
+
+                print_process_command_output(output);
+
+                return Err(anyhow::Error::new(error).context(format!(
+                    "Remote rrdtool returned some errors! ssh {:?}",
+                    self.build_ssh_args(args)
+                )));
+            }
+
+            let output_filename = self.get_output_filename(index);
+            shell_commands.push(build_shell_command("ssh", &self.build_ssh_args(args)));
+
+            // scp copies each graph right after it's generated
+            if self.transfer == Transfer::Scp {
+                let transfer_started = Instant::now();
+
+                self.fetch_remote_graphs(&[self.get_remote_filename(index)], &output_filename)?;
+
+                transfer += transfer_started.elapsed();
+
+                info!("Successfully saved {}", output_filename);
+            }
+
+            paths.push(PathBuf::from(output_filename));
+        }
+
+        // rsync copies all graphs at once
+        if self.transfer == Transfer::Rsync {
+            let transfer_started = Instant::now();
+
+            self.fetch_remote_graphs(&self.get_remote_filenames(), &self.get_rsync_destination())?;
+
+            transfer += transfer_started.elapsed();
+
+            for path in &paths {
+                info!("Successfully saved {}", path.display());
+            }
+        }
+
+        if self.embed_command {
+            for (path, command) in paths.iter().zip(&shell_commands) {
+                write_command_file(path.to_str().unwrap(), command)?;
+            }
+        }
+
+        info!(
+            "{}",
+            build_run_summary(&paths, started.elapsed(), Some(transfer))
+        );
+
+        Ok(paths)
+    }
+
+    /// Execute command on remote target with ssh
+    fn exec_ssh(&self, command: Vec<String>) -> Result<()> {
+        let output = self.run_ssh(command.clone())?;
+
+        if output.status.code() == Some(CggError::SSH_CONNECTION_FAILED) {
+            let error = CggError::remote_failed(self.get_network_address(), &output);
+
+            print_process_command_output(output);
+
+            return Err(anyhow::Error::new(error).context(format!(
+                "Failed to execute ssh command: ssh {:?}",
+                self.build_ssh_args(command)
+            )));
+        }
+
+        if !output.status.success() {
+            print_process_command_output(output);
+
+            anyhow::bail!(
+                "Failed to execute ssh command: ssh {:?}",
+                self.build_ssh_args(command)
+            )
+        }
+
+        Ok(())
+    }
+
+    /// Run command on remote target with ssh and return its output, whatever the status
+    fn run_ssh(&self, command: Vec<String>) -> Result<std::process::Output> {
+        let args = self.build_ssh_args(command);
+
+        trace!("Executing remotely: ssh {:?}", args);
+
+        self.ssh_options.ensure_online("ssh")?;
+
+        self.runner
+            .run("ssh", &args)
+            .context("Failed to execute SSH command")
+    }
+
+    /// Copy graphs generated on remote target to local destination.
+    /// Only copying is retried, without generating graphs again.
+    fn fetch_remote_graphs(&self, remote_filenames: &[String], destination: &str) -> Result<()> {
+        let (program, args) = self.build_fetch_args(remote_filenames, destination);
+        let mut attempt = 0;
+
+        self.ssh_options.ensure_online(program)?;
+
+        loop {
+            trace!("Executing remotely: {} {:?}", program, args);
+
+            let output = self
+                .runner
+                .run(program, &args)
+                .context(format!("Failed to execute {}", program))?;
+
+            if output.status.success() {
+                return Ok(());
+            }
+
+            if attempt >= self.scp_retries {
+                print_process_command_output(output);
+
+                anyhow::bail!(
+                    "Failed to {} result image back to host: {} {:?}",
+                    program,
+                    program,
+                    args
+                )
+            }
+
+            attempt += 1;
+
+            warn!(
+                "Failed to {} result image back to host, retrying {}/{}",
+                program, attempt, self.scp_retries
+            );
+
+            std::thread::sleep(self.scp_retry_delay * attempt);
+        }
+    }
+
+    /// Build ssh arguments executing command on remote target. Remote shell parses
+    /// the command again, so every argument except redirection is quoted.
+    fn build_ssh_args(&self, command: Vec<String>) -> Vec<String> {
+        let mut args = self.ssh_options.args("ssh");
+        args.push(self.get_network_address());
+        args.extend(command.iter().map(|arg| match arg.as_str() {
+            Rrdtool::REDIRECT => arg.clone(),
+            _ => shell_quote(arg),
+        }));
+        args
+    }
+
+    /// Build program and its arguments copying graphs from remote target to local destination.
+    /// scp copies a single graph to its path, rsync copies all graphs to a directory.
+    fn build_fetch_args(
+        &self,
+        remote_filenames: &[String],
+        destination: &str,
+    ) -> (&'static str, Vec<String>) {
+        let network_address = self.get_network_address();
+
+        let mut args = match self.transfer {
+            Transfer::Scp => self.ssh_options.args("scp"),
+            Transfer::Rsync => match self.ssh_options.args("ssh") {
+                ssh_args if ssh_args.is_empty() => Vec::new(),
+                ssh_args => vec![String::from("-e"), build_shell_command("ssh", &ssh_args)],
+            },
+        };
+
+        args.extend(
+            remote_filenames
+                .iter()
+                .map(|filename| String::from(network_address.as_str()) + ":" + filename),
+        );
+        args.push(String::from(destination));
+
+        (self.transfer.as_str(), args)
+    }
+
+    /// Build command creating remote directory for graphs copied with rsync
+    fn build_rsync_mkdir(&self) -> Vec<String> {
+        vec![
+            String::from("mkdir"),
+            String::from("-p"),
+            self.get_rsync_remote_dir(),
+        ]
+    }
+
+    /// Build command removing remote directory of graphs copied with rsync
+    fn build_rsync_rmdir(&self) -> Vec<String> {
+        vec![
+            String::from("rm"),
+            String::from("-rf"),
+            self.get_rsync_remote_dir(),
+        ]
+    }
+
+    /// Returns remote directory with graphs copied with rsync
+    fn get_rsync_remote_dir(&self) -> String {
+        String::from(Rrdtool::REMOTE_TEMP_PREFIX) + &self.remote_id
+    }
+
+    /// Returns id unique for each instance within all processes, built from process id,
+    /// random number and number of instances already created by the process. Random part
+    /// tells apart clients with the same process id, e.g. in containers, on the same target.
+    fn build_remote_id() -> String {
+        static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+        // Keys of RandomState are seeded from system randomness
+        let random = RandomState::new().build_hasher().finish();
+
+        format!(
+            "{}-{:016x}-{}",
+            std::process::id(),
+            random,
+            INSTANCES.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    /// Returns files in directory of remote target, each directory is listed only once
+    pub fn list_remote_dir(&self, dir: &str) -> Result<Vec<String>> {
+        if self.target == Target::Local {
+            anyhow::bail!("Cannot list {} via SSH, input directory is local", dir);
+        }
+
+        self.remote_listing.ls(
+            self.runner.as_ref(),
+            dir,
+            self.username.as_ref().unwrap(),
+            self.hostname.as_ref().unwrap(),
+            &self.ssh_options,
+        )
+    }
+
+    /// Returns address of remote target, e.g. user@host
+    fn get_network_address(&self) -> String {
+        String::from(self.username.as_ref().unwrap().as_str())
+            + "@"
+            + self.hostname.as_ref().unwrap()
+    }
+
+    /// Returns path of graph with given index, as generated on remote target
+    fn get_remote_filename(&self, index: usize) -> String {
+        match self.transfer {
+            Transfer::Scp if self.export => {
+                format!("{}{}.json", Rrdtool::REMOTE_TEMP_PREFIX, self.remote_id)
+            }
+            Transfer::Scp => String::from(self.remote_filename.as_ref().unwrap()),
+            // rsync keeps names of files, so each graph is generated with its final name
+            Transfer::Rsync => {
+                let output_filename = self.get_output_filename(index);
+                let name = Path::new(&output_filename)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                self.get_rsync_remote_dir() + "/" + &name
+            }
+        }
+    }
+
+    /// Returns paths of all graphs, as generated on remote target
+    fn get_remote_filenames(&self) -> Vec<String> {
+        (0..self.graph_args.args.len())
+            .map(|index| self.get_remote_filename(index))
+            .collect()
+    }
+
+    /// Returns local directory, to which rsync copies all graphs
+    fn get_rsync_destination(&self) -> String {
+        match Path::new(&self.output_filename).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => format!("{}/", dir.display()),
+            _ => String::from("./"),
+        }
+    }
+
+    /// Build vector of rrdtool arguments based on data in self
+    fn build_rrdtool_args(&self) -> Vec<Vec<String>> {
+        let mut commands = Vec::new();
+
+        let no_of_output_files = self.graph_args.args.len();
+
+        debug!("Building arguments for {} files.", no_of_output_files);
+
+        for i in 0..no_of_output_files {
+            let index = i as usize;
+            commands.push(Vec::new());
+
+            commands[index].push(String::from(self.subcommand.as_str()));
+
+            let output_filename = self.get_output_filename(index);
+
+            // xport prints data to standard output and takes only the time range,
+            // on remote target the output is redirected to file fetched later
+            if self.export {
+                commands[index].extend(self.build_export_args());
+                commands[index].extend(self.graph_args.args[index].iter().cloned());
+
+                if self.target == Target::Remote {
+                    let remote_filename = self.get_remote_filename(index);
+                    commands[index].push(String::from(Rrdtool::REDIRECT));
+                    commands[index].push(remote_filename);
+                }
+
+                continue;
+            }
+
+            match self.target {
+                Target::Local => {
+                    commands[index].push(String::from(output_filename.as_str()));
+                    debug!("Building arguments for local {} file.", output_filename);
+                }
+                Target::Remote => {
+                    let remote_filename = self.get_remote_filename(index);
+
+                    debug!("Building arguments for remote {} file.", remote_filename);
+
+                    commands[index].push(remote_filename);
+                }
+            }
+
+            for common_arg in &self.common_args {
+                commands[index].push(String::from(common_arg));
+            }
+
+            commands[index].extend(self.build_size_args(index));
+
+            for option in &self.graph_args.info[index].options {
+                commands[index].push(String::from(option));
+            }
+
+            if let Some(title) = self.get_title(index) {
+                commands[index].push(String::from("--title"));
+                commands[index].push(title);
+            }
+
+            for graph_arg in &self.graph_args.args[index] {
+                commands[index].push(String::from(graph_arg));
+            }
+
+            trace!(
+                "Built arguments for {} filename: {:?}",
+                output_filename,
+                commands
+            );
+        }
+
+        commands
+    }
+
+    /// Build arguments of rrdtool xport, other than exported data
+    fn build_export_args(&self) -> Vec<String> {
+        let mut args = vec![String::from("--json")];
+
+        if let Some(start) = self.start {
+            args.push(String::from("--start"));
+            args.push(start.to_string());
+        }
+
+        if let Some(end) = self.end {
+            args.push(String::from("--end"));
+            args.push(end.to_string());
+        }
+
+        args
+    }
+
+    /// Build title of graph with given index from the plugin title or common title,
+    /// followed by time range. No title is returned in sparkline mode.
+    fn get_title(&self, index: usize) -> Option<String> {
+        if self.sparkline {
+            return None;
+        }
+
+        let title = self.graph_args.info[index]
+            .plugin
+            .and_then(|plugin| self.plugin_titles.get(&plugin))
+            .or(self.title.as_ref());
+
+        match (title, self.get_title_time_range()) {
+            (Some(title), Some(time_range)) => Some(format!("{} ({})", title, time_range)),
+            (Some(title), None) => Some(String::from(title)),
+            (None, time_range) => time_range,
+        }
+    }
+
+    /// Build time range shown in title, if format of time is set
+    fn get_title_time_range(&self) -> Option<String> {
+        let format = self.title_time_format.as_ref()?;
+
+        let format_time = |timestamp: u64| match Local.timestamp_opt(timestamp as i64, 0).single() {
+            Some(time) => time.format(format).to_string(),
+            None => timestamp.to_string(),
+        };
+
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => Some(format_time(start) + " - " + &format_time(end)),
+            (Some(start), None) => Some(String::from("since ") + &format_time(start)),
+            _ => None,
+        }
+    }
+
+    /// Build output filename based on current index and number of expected output files.
+    /// Labeled graphs get their label appended, the rest are numbered.
+    fn get_output_filename(&self, index: usize) -> String {
+        if self.output_filename == Rrdtool::STDOUT {
+            return String::from(Rrdtool::STDOUT);
+        }
+
+        let appendix = self.get_output_appendix(index);
+
+        if appendix.is_empty() {
+            return String::from(self.output_filename.as_str());
+        }
+
+        let mut output_filename = String::from(self.output_filename.as_str());
+
+        // Appendix goes before extension of the file name, or at the end if there is none
+        let name_start = output_filename.rfind('/').map_or(0, |index| index + 1);
+
+        match output_filename[name_start..].rfind('.') {
+            Some(dot) if dot > 0 => output_filename.insert_str(name_start + dot, &appendix),
+            _ => output_filename.push_str(&appendix),
+        }
+
+        trace!("Returning output filename: {}", output_filename);
+
+        output_filename
+    }
+
+    /// Returns string appended to output filename of graph with given index, e.g. _2 or _web
+    fn get_output_appendix(&self, index: usize) -> String {
+        let unlabeled = self
+            .graph_args
+            .info
+            .iter()
+            .filter(|info| info.label.is_none())
+            .count();
+
+        match &self.graph_args.info[index].label {
+            Some(label) => String::from("_") + label,
+            None => match unlabeled {
+                1 => String::new(),
+                _ => {
+                    let number = self.graph_args.info[..index]
+                        .iter()
+                        .filter(|info| info.label.is_none())
+                        .count();
+
+                    String::from("_") + (number + 1).to_string().as_str()
+                }
+            },
+        }
+    }
+
+    /// Parse input path to get target type, path, username, hostname and SSH port
+    pub fn parse_input_path(input_dir: &Path) -> Result<InputPath> {
+        // Remote only as user@host:path, where neither user nor host contains a path separator
+        // or colon, and host is not a single letter, which would be a Windows drive.
+        // Port may follow host as user@host:port:/path, only if the path is absolute
+        let re = regex::Regex::new(r"^([^@/\\:]+)@([^@/\\:]{2,}):(?:([0-9]+):(/.*)|(.*))$")
+            .context("Failed to create regex")?;
+
+        match re.captures(
+            input_dir
+                .to_str()
+                .context("Input path is not valid UTF-8")?,
+        ) {
+            // Remote
+            Some(captures) => {
                 let target = Target::Remote;
 
-                let re = regex::Regex::new("(.*)@(.*):(.*)").unwrap();
-                let captures = re.captures(input_dir.to_str().unwrap()).unwrap();
-                let username = captures[1].to_string();
-                let hostname = captures[2].to_string();
-                let remote_path = captures.get(3).unwrap().as_str();
+                let username = captures[1].to_string();
+                let hostname = captures[2].to_string();
+                let remote_path = captures
+                    .get(4)
+                    .or_else(|| captures.get(5))
+                    .unwrap()
+                    .as_str();
+
+                let port = match captures.get(3) {
+                    Some(port) => Some(
+                        port.as_str()
+                            .parse::<u16>()
+                            .context(format!("Invalid SSH port {}", port.as_str()))?,
+                    ),
+                    None => None,
+                };
+
+                trace!(
+                    "Parsed remote path, username: {}, hostname: {}, port: {:?}, path: {}",
+                    username,
+                    hostname,
+                    port,
+                    remote_path
+                );
+
+                Ok((
+                    target,
+                    String::from(remote_path),
+                    Some(username),
+                    Some(hostname),
+                    port,
+                ))
+            }
+
+            // Local
+            None => {
+                let target = Target::Local;
+
+                // Follow symlinked directory, e.g. "current", so all paths point to the same data
+                let input_dir =
+                    match input_dir.symlink_metadata() {
+                        Ok(metadata) if metadata.file_type().is_symlink() => input_dir
+                            .canonicalize()
+                            .context(format!("Failed to follow symlink {}", input_dir.display()))?,
+                        _ => input_dir.to_path_buf(),
+                    };
+
+                trace!("Parsed local path: {}", input_dir.display());
+
+                Ok((
+                    target,
+                    String::from(input_dir.to_str().unwrap()),
+                    None,
+                    None,
+                    None,
+                ))
+            }
+        }
+    }
+}
+
+/// Parse step in seconds from output of rrdtool info, i.e. line: step = 10
+fn parse_rrd_step(info: &str) -> Option<u64> {
+    info.lines()
+        .find_map(|line| line.strip_prefix("step = "))
+        .and_then(|step| step.trim().parse::<u64>().ok())
+}
+
+/// Build command line which can be pasted to shell, e.g. rrdtool graph out.png --title 'Processes'
+fn build_shell_command(program: &str, args: &[String]) -> String {
+    std::iter::once(String::from(program))
+        .chain(args.iter().map(|arg| shell_quote(arg)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Write command generating graph to file next to it, e.g. out.png.cmd for out.png
+fn write_command_file(output_filename: &str, command: &str) -> Result<()> {
+    let path = String::from(output_filename) + ".cmd";
+
+    std::fs::write(&path, String::from(command) + "\n")
+        .context(format!("Failed to write command to {}", path))
+}
+
+/// Quote argument in single quotes for POSIX shell, unless it contains only safe characters
+pub fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=,@%+#".contains(c);
+
+    match !arg.is_empty() && !arg.starts_with('#') && arg.chars().all(safe) {
+        true => String::from(arg),
+        false => String::from("'") + &arg.replace('\'', "'\\''") + "'",
+    }
+}
+
+/// Build summary of the run, e.g. Generated 5 graphs (3.2 MB total) in 1.8s
+///
+/// # Arguments
+/// * `paths` - saved graphs
+/// * `elapsed` - duration of the whole run
+/// * `transfer` - time spent on copying graphs from remote target
+///
+fn build_run_summary(paths: &[PathBuf], elapsed: Duration, transfer: Option<Duration>) -> String {
+    let bytes = paths
+        .iter()
+        .map(|path| std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0))
+        .sum::<u64>();
+
+    format!(
+        "Generated {} graph{} ({} total) in {:.1}s{}",
+        paths.len(),
+        match paths.len() {
+            1 => "",
+            _ => "s",
+        },
+        format_bytes(bytes),
+        elapsed.as_secs_f64(),
+        match transfer {
+            Some(transfer) => format!(", transfer took {:.1}s", transfer.as_secs_f64()),
+            None => String::new(),
+        }
+    )
+}
+
+/// Format number of bytes to human readable string, e.g. 3.2 MB
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["kB", "MB", "GB"];
+
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = 0;
+
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Print output of system command
+pub fn print_process_command_output(output: std::process::Output) {
+    error!("status: {}", output.status);
+    error!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+    error!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::command::tests::{Calls, MockCommandRunner};
+    use super::super::palette::PalettePreset;
+    use super::*;
+    use anyhow::Result;
+    use log::{Level, Log, Metadata, Record};
+    use std::path::Path;
+    use std::sync::{Mutex, Once};
+
+    /// Logger keeping all messages, so tests can assert on them
+    struct CapturingLogger;
+
+    static LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static LOGGER: CapturingLogger = CapturingLogger;
+    static LOGGER_INIT: Once = Once::new();
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Info
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                LOGS.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Start capturing logs, returns all messages logged so far by any test
+    pub fn captured_logs() -> Vec<String> {
+        LOGGER_INIT.call_once(|| {
+            // Other logger may be already set, then nothing is captured
+            let _ = log::set_logger(&LOGGER);
+            log::set_max_level(log::LevelFilter::Info);
+        });
+
+        LOGS.lock().unwrap().clone()
+    }
+
+    #[test]
+    pub fn rrdtool_builder() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+
+        rrd.with_output_file(String::from("out.png"))?
+            .with_subcommand(String::from("graph"))?
+            .with_start(123456)?
+            .with_end(1234567)?;
+
+        assert_eq!("rrdtool", rrd.command);
+        assert_eq!("out.png", rrd.output_filename);
+        assert_eq!("graph", rrd.subcommand);
+        assert_eq!(4, rrd.common_args.len());
+        assert_eq!(0, rrd.graph_args.args.len());
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_sparkline() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+
+        rrd.with_sparkline(false)?;
+        assert!(!rrd.sparkline);
+        assert_eq!(0, rrd.common_args.len());
+
+        rrd.with_sparkline(true)?;
+        assert!(rrd.sparkline);
+        assert_eq!(
+            vec![
+                "--only-graph",
+                "--no-legend",
+                "--x-grid",
+                "none",
+                "--y-grid",
+                "none"
+            ],
+            rrd.common_args
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_title_time_format() -> Result<()> {
+        let start = 1605734459;
+        let end = start + 3600;
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_output_file(String::from("out.png"))?
+            .with_start(start)?
+            .with_end(end)?
+            .with_title_time_format(Some(String::from("%Y-%m-%d %H:%M")))?;
+        rrd.graph_args.new_graph();
+
+        let format_time = |timestamp: u64| {
+            Local
+                .timestamp_opt(timestamp as i64, 0)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        };
+
+        let args = rrd.build_rrdtool_args();
+        let title = args[0].iter().position(|arg| arg == "--title").unwrap();
+        assert_eq!(
+            format_time(start) + " - " + &format_time(end),
+            args[0][title + 1]
+        );
+
+        assert!(rrd
+            .with_title_time_format(Some(String::from("%Y-%m-%d %!")))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_auto_title() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let input = temp.path().join("marcin-manjaro");
+
+        let mut rrd = Rrdtool::new(&input)?;
+        rrd.with_output_file(String::from("out.png"))?
+            .with_auto_title(true)?;
+        rrd.graph_args.new_graph();
+        rrd.graph_args.new_graph();
+
+        let args = rrd.build_rrdtool_args();
+        assert_eq!(2, args.len());
+
+        for (index, graph) in args.iter().enumerate() {
+            let title = graph.iter().position(|arg| arg == "--title").unwrap();
+            assert_eq!("marcin-manjaro", graph[title + 1]);
+            assert_eq!(format!("out_{}.png", index + 1), graph[1]);
+        }
+
+        let mut rrd = Rrdtool::new(Path::new("user@10.0.0.1:/var/lib/collectd/host"))?;
+        rrd.with_auto_title(true)?;
+        assert_eq!(Some(String::from("10.0.0.1")), rrd.title);
+
+        let mut rrd = Rrdtool::new(Path::new("user@10.0.0.1:/var/lib/collectd/host"))?;
+        rrd.with_titles(Some(String::from("Common")), HashMap::new())?
+            .with_auto_title(true)?;
+        assert_eq!(Some(String::from("Common")), rrd.title);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_plugin_titles() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        std::fs::create_dir(temp.path().join("processes-firefox"))?;
+        std::fs::File::create(temp.path().join("processes-firefox").join("ps_rss.rrd"))?;
+        std::fs::create_dir(temp.path().join("memory"))?;
+        std::fs::File::create(temp.path().join("memory").join("memory-free.rrd"))?;
+
+        let mut plugins_config = config::PluginsConfig {
+            data: HashMap::new(),
+            order: Vec::new(),
+        };
+        plugins_config.data.insert(
+            Plugins::Processes,
+            Box::new(processes::processes_data::ProcessesData::new(20, None)),
+        );
+        plugins_config.data.insert(
+            Plugins::Memory,
+            Box::new(memory::memory_data::MemoryData::new(vec![
+                memory::memory_type::MemoryType::Free,
+            ])),
+        );
+
+        let mut plugin_titles = HashMap::new();
+        plugin_titles.insert(Plugins::Processes, String::from("Process RSS"));
+        plugin_titles.insert(Plugins::Memory, String::from("System Memory"));
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.with_output_file(String::from("out.png"))?
+            .with_titles(Some(String::from("Common")), plugin_titles)?
+            .with_plugins(plugins_config)?;
+
+        let args = rrd.build_rrdtool_args();
+        assert_eq!(2, args.len());
+
+        for graph in args {
+            let title = &graph[graph.iter().position(|arg| arg == "--title").unwrap() + 1];
+
+            match graph.iter().any(|arg| arg.contains("processes-firefox")) {
+                true => assert_eq!("Process RSS", title),
+                false => assert_eq!("System Memory", title),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_plugin_sizes() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        std::fs::create_dir(temp.path().join("processes-firefox"))?;
+        std::fs::File::create(temp.path().join("processes-firefox").join("ps_rss.rrd"))?;
+        std::fs::create_dir(temp.path().join("memory"))?;
+        std::fs::File::create(temp.path().join("memory").join("memory-free.rrd"))?;
+        std::fs::create_dir(temp.path().join("cpu-0"))?;
+        std::fs::File::create(temp.path().join("cpu-0").join("cpu-user.rrd"))?;
+
+        let mut plugins_config = config::PluginsConfig {
+            data: HashMap::new(),
+            order: vec![Plugins::Processes, Plugins::Memory, Plugins::Cpu],
+        };
+        plugins_config.data.insert(
+            Plugins::Processes,
+            Box::new(processes::processes_data::ProcessesData::new(20, None)),
+        );
+        plugins_config.data.insert(
+            Plugins::Memory,
+            Box::new(memory::memory_data::MemoryData::new(vec![
+                memory::memory_type::MemoryType::Free,
+            ])),
+        );
+        plugins_config.data.insert(
+            Plugins::Cpu,
+            Box::new(cpu::cpu_data::CpuData::new(
+                vec![cpu::cpu_type::CpuType::User],
+                false,
+            )),
+        );
+
+        let mut plugin_sizes = HashMap::new();
+        plugin_sizes.insert(Plugins::Processes, (2048, 1024));
+        plugin_sizes.insert(Plugins::Memory, (1024, 512));
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.with_output_file(String::from("out.png"))?
+            .with_width(800)?
+            .with_height(600)?
+            .with_plugin_sizes(plugin_sizes)?
+            .with_plugins(plugins_config)?;
+
+        let size = |graph: &Vec<String>| {
+            let value = |flag: &str| {
+                assert_eq!(1, graph.iter().filter(|arg| *arg == flag).count());
+                graph[graph.iter().position(|arg| arg == flag).unwrap() + 1].clone()
+            };
+            (value("-w"), value("-h"))
+        };
+
+        let sizes = rrd
+            .build_rrdtool_args()
+            .iter()
+            .map(size)
+            .collect::<Vec<(String, String)>>();
+
+        // Cpu has no size of its own, so the common size is used
+        assert_eq!(
+            vec![
+                (String::from("2048"), String::from("1024")),
+                (String::from("1024"), String::from("512")),
+                (String::from("800"), String::from("600")),
+            ],
+            sizes
+        );
+        assert!(rrd.common_args.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_plugins_order() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        std::fs::create_dir(temp.path().join("processes-firefox"))?;
+        std::fs::File::create(temp.path().join("processes-firefox").join("ps_rss.rrd"))?;
+        std::fs::create_dir(temp.path().join("memory"))?;
+        std::fs::File::create(temp.path().join("memory").join("memory-free.rrd"))?;
+
+        for order in &[
+            vec![Plugins::Memory, Plugins::Processes],
+            vec![Plugins::Processes, Plugins::Memory],
+        ] {
+            let mut plugins_config = config::PluginsConfig {
+                data: HashMap::new(),
+                order: order.clone(),
+            };
+            plugins_config.data.insert(
+                Plugins::Processes,
+                Box::new(processes::processes_data::ProcessesData::new(20, None)),
+            );
+            plugins_config.data.insert(
+                Plugins::Memory,
+                Box::new(memory::memory_data::MemoryData::new(vec![
+                    memory::memory_type::MemoryType::Free,
+                ])),
+            );
+
+            let mut rrd = Rrdtool::new(temp.path())?;
+            rrd.with_output_file(String::from("out.png"))?
+                .with_combine(true)?
+                .with_plugins(plugins_config)?;
+
+            let args = rrd.build_rrdtool_args();
+            assert_eq!(1, args.len());
+
+            let position = |file: &str| args[0].iter().position(|arg| arg.contains(file));
+            let memory = position("memory-free.rrd").unwrap();
+            let processes = position("processes-firefox").unwrap();
+
+            match order[0] {
+                Plugins::Memory => assert!(memory < processes),
+                _ => assert!(processes < memory),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_right_axis() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        std::fs::create_dir(temp.path().join("processes-firefox"))?;
+        std::fs::File::create(temp.path().join("processes-firefox").join("ps_rss.rrd"))?;
+        std::fs::create_dir(temp.path().join("memory"))?;
+        std::fs::File::create(temp.path().join("memory").join("memory-free.rrd"))?;
+
+        let mut plugins_config = config::PluginsConfig {
+            data: HashMap::new(),
+            order: Vec::new(),
+        };
+        plugins_config.data.insert(
+            Plugins::Processes,
+            Box::new(processes::processes_data::ProcessesData::new(20, None)),
+        );
+        plugins_config.data.insert(
+            Plugins::Memory,
+            Box::new(memory::memory_data::MemoryData::new(vec![
+                memory::memory_type::MemoryType::Free,
+            ])),
+        );
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.with_output_file(String::from("out.png"))?
+            .with_combine(true)?
+            .with_right_axis(
+                Some(RightAxis::from_str("0.001:10")?),
+                Some(String::from("RSS of processes")),
+                vec![Plugins::Processes],
+            )?
+            .with_plugins(plugins_config)?;
+
+        let args = rrd.build_rrdtool_args();
+        assert_eq!(1, args.len());
+        let graph = &args[0];
+
+        let axis = graph.iter().position(|arg| arg == "--right-axis").unwrap();
+        assert_eq!("0.001:10", graph[axis + 1]);
+        let label = graph
+            .iter()
+            .position(|arg| arg == "--right-axis-label")
+            .unwrap();
+        assert_eq!("RSS of processes", graph[label + 1]);
+
+        let cdefs = graph
+            .iter()
+            .filter(|arg| arg.starts_with("CDEF:") && arg.contains("_right="))
+            .collect::<Vec<&String>>();
+        assert_eq!(1, cdefs.len());
+        assert!(cdefs[0].ends_with(",10,-,0.001,/"));
+
+        let scaled = &cdefs[0]["CDEF:".len()..cdefs[0].find('=').unwrap()];
+        assert!(graph
+            .iter()
+            .any(|arg| arg.contains(&format!(":{}#", scaled))));
+        assert!(!graph
+            .iter()
+            .any(|arg| arg.starts_with("AREA:") && arg.contains("_right")));
+
+        assert!(Rrdtool::new(temp.path())?
+            .with_right_axis(None, Some(String::from("label")), Vec::new())
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_inputs() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let hosts = vec![temp.path().join("host-a"), temp.path().join("host-b")];
+
+        for host in &hosts {
+            std::fs::create_dir_all(host.join("memory"))?;
+            std::fs::File::create(host.join("memory").join("memory-free.rrd"))?;
+        }
+
+        let plugins_config = || {
+            let mut plugins_config = config::PluginsConfig {
+                data: HashMap::new(),
+                order: Vec::new(),
+            };
+            plugins_config.data.insert(
+                Plugins::Memory,
+                Box::new(memory::memory_data::MemoryData::new(vec![
+                    memory::memory_type::MemoryType::Free,
+                ])) as Box<dyn std::any::Any>,
+            );
+            plugins_config
+        };
+
+        let legends = |graph: &Vec<String>| {
+            graph
+                .iter()
+                .filter(|arg| arg.starts_with("LINE") || arg.starts_with("AREA"))
+                .map(|arg| String::from(arg.split(":\"").nth(1).unwrap()))
+                .collect::<Vec<String>>()
+        };
+
+        // Each host is drawn on its own graph
+        let mut rrd = Rrdtool::new(&hosts[0])?;
+        rrd.with_inputs(&hosts)?.with_plugins(plugins_config())?;
+
+        assert_eq!(2, rrd.graph_args.args.len());
+        assert_eq!(vec!["host-a: free\""], legends(&rrd.graph_args.args[0]));
+        assert_eq!(vec!["host-b: free\""], legends(&rrd.graph_args.args[1]));
+        assert!(rrd.graph_args.args[1][0].contains("host-b/memory/memory-free.rrd"));
+        assert_eq!(hosts[0].to_str().unwrap(), rrd.input_dir);
+
+        // Combined, hosts are overlaid on one graph
+        let mut rrd = Rrdtool::new(&hosts[0])?;
+        rrd.with_combine(true)?
+            .with_inputs(&hosts)?
+            .with_plugins(plugins_config())?;
+
+        assert_eq!(1, rrd.graph_args.args.len());
+        assert_eq!(
+            vec!["host-a: free\"", "host-b: free\""],
+            legends(&rrd.graph_args.args[0])
+        );
+
+        let mut rrd = Rrdtool::new(&hosts[0])?;
+        assert!(rrd
+            .with_inputs(&[PathBuf::from("user@host:/var/lib/collectd/host-b")])
+            .is_err());
+
+        // Hosts with the same directory name are told apart by the whole directory
+        let hosts = vec![
+            temp.path().join("a").join("host"),
+            temp.path().join("b").join("host"),
+        ];
+
+        for host in &hosts {
+            std::fs::create_dir_all(host.join("memory"))?;
+            std::fs::File::create(host.join("memory").join("memory-free.rrd"))?;
+        }
+
+        let mut rrd = Rrdtool::new(&hosts[0])?;
+        rrd.with_inputs(&hosts)?.with_plugins(plugins_config())?;
+
+        assert!(legends(&rrd.graph_args.args[0])[0].ends_with("_a_host: free\""));
+        assert!(legends(&rrd.graph_args.args[1])[0].ends_with("_b_host: free\""));
+        assert!(rrd
+            .inputs
+            .iter()
+            .all(|(host, _)| !host.contains('/') && !host.starts_with('_')));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_overview() -> Result<()> {
+        let runner = MockCommandRunner::new()
+            .returning("rrdtool first", "1600000000\n")
+            .returning("rrdtool last", "1606000000\n");
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?
+            .with_start(1605000000)?
+            .with_end(1605003600)?
+            .with_overview(true)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/local/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        assert_eq!(2, rrd.graph_args.args.len());
+        assert_eq!("out_overview.png", rrd.get_output_filename(1));
+
+        let args = rrd.build_rrdtool_args();
+        let overview = args[1].join(" ");
+
+        assert!(overview.contains("--start 1600000000 --end 1606000000"));
+        assert!(overview.contains("DEF:firefox=/some/local/ps_rss.rrd:value:AVERAGE"));
+        assert!(overview.contains("VRULE:1605000000#ff0000"));
+        assert!(overview.contains("VRULE:1605003600#ff0000"));
+        assert!(!args[0].join(" ").contains("VRULE"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_processes_command() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let temp = TempDir::new().unwrap();
+        let input = temp.path().join("input");
+        std::fs::create_dir_all(input.join("processes-firefox"))?;
+        std::fs::File::create(input.join("processes-firefox").join("ps_rss.rrd"))?;
+        let output = temp.path().join("out.png");
+
+        let mut plugins_config = config::PluginsConfig {
+            data: HashMap::new(),
+            order: Vec::new(),
+        };
+        plugins_config.data.insert(
+            Plugins::Processes,
+            Box::new(processes::processes_data::ProcessesData::new(20, None)),
+        );
+
+        let mut rrd = Rrdtool::new(&input)?;
+        rrd.with_runner(Box::new(runner))?
+            .with_subcommand(String::from("graph"))?
+            .with_output_file(String::from(output.to_str().unwrap()))?
+            .with_width(1024)?
+            .with_height(768)?
+            .with_start(1600000000)?
+            .with_end(1600003600)?
+            .with_plugins(plugins_config)?;
+
+        assert_eq!(vec![output.clone()], rrd.exec()?);
+
+        let rrd_file = input.join("processes-firefox").join("ps_rss.rrd");
+        let expected = vec![
+            String::from("graph"),
+            String::from(output.to_str().unwrap()),
+            String::from("--start"),
+            String::from("1600000000"),
+            String::from("--end"),
+            String::from("1600003600"),
+            String::from("-w"),
+            String::from("1024"),
+            String::from("-h"),
+            String::from("768"),
+            String::from("--vertical-label"),
+            String::from("RSS (bytes)"),
+            format!("DEF:firefox={}:value:AVERAGE", rrd_file.display()),
+            format!("LINE3:firefox{}:\"firefox\"", Rrdtool::COLORS[0]),
+        ];
+
+        assert_eq!(
+            vec![vec![String::from("--version")], expected],
+            MockCommandRunner::calls_of(&calls, "rrdtool")
+        );
+        assert_eq!(2, calls.lock().unwrap().len());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_dry_run() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("my graph.png"))?
+            .with_dry_run(true)?
+            .with_max_data_age(Some(3600), 1600000000)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/local/processes-firefox/ps_rss.rrd",
+        );
+        rrd.graph_args.push(
+            "visual studio code",
+            "#ff0000",
+            GraphElement::Line(3),
+            "/some/local/processes-visual studio code/ps_rss.rrd",
+        );
+
+        let mut out = Vec::new();
+        rrd.write_commands(&mut out)?;
+        let out = String::from_utf8(out)?;
+
+        assert_eq!(1, out.lines().count());
+        assert!(out.starts_with("rrdtool graph 'my graph.png' "));
+        assert!(
+            out.contains(" DEF:firefox=/some/local/processes-firefox/ps_rss.rrd:value:AVERAGE ")
+        );
+        assert!(out.contains(
+            " 'DEF:visual=/some/local/processes-visual studio code/ps_rss.rrd:value:AVERAGE' "
+        ));
+
+        // Nothing is executed, not even checks of data
+        assert!(rrd.exec()?.is_empty());
+        assert!(calls.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_dry_run_remote() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/remote/"))?;
+        rrd.remote_id = String::from("test");
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?
+            .with_dry_run(true)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/remote/processes-firefox/ps_rss.rrd",
+        );
+
+        let mut out = Vec::new();
+        rrd.write_commands(&mut out)?;
+        let out = String::from_utf8(out)?;
+        let lines = out.lines().collect::<Vec<&str>>();
+
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("ssh user@host rrdtool graph /tmp/cgg-out-test.png "));
+        assert!(lines[0]
+            .contains(" DEF:firefox=/some/remote/processes-firefox/ps_rss.rrd:value:AVERAGE "));
+        assert_eq!("scp user@host:/tmp/cgg-out-test.png out.png", lines[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_export() -> Result<()> {
+        let runner = MockCommandRunner::new().returning("rrdtool xport", "{\"data\": []}");
+        let calls = runner.calls.clone();
+
+        let temp = TempDir::new().unwrap();
+        let output = temp.path().join("out.json");
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from(output.to_str().unwrap()))?
+            .with_start(1600000000)?
+            .with_end(1600003600)?
+            .with_width(1024)?
+            .with_export(true)?;
+
+        for process in &["firefox", "chrome"] {
+            rrd.graph_args.push(
+                process,
+                "#00ff00",
+                GraphElement::Line(3),
+                &format!("/some/local/processes-{}/ps_rss.rrd", process),
+            );
+        }
+
+        assert_eq!(
+            vec![vec![
+                "xport",
+                "--json",
+                "--start",
+                "1600000000",
+                "--end",
+                "1600003600",
+                "DEF:firefox=/some/local/processes-firefox/ps_rss.rrd:value:AVERAGE",
+                "XPORT:firefox:\"firefox\"",
+                "DEF:chrome=/some/local/processes-chrome/ps_rss.rrd:value:AVERAGE",
+                "XPORT:chrome:\"chrome\"",
+            ]],
+            rrd.build_rrdtool_args()
+        );
+
+        assert_eq!(vec![output.clone()], rrd.exec()?);
+        assert_eq!("{\"data\": []}", std::fs::read_to_string(&output)?);
+        assert_eq!(
+            vec!["--version", "xport"],
+            MockCommandRunner::calls_of(&calls, "rrdtool")
+                .iter()
+                .map(|args| args[0].as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_export_remote() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/remote/"))?;
+        rrd.remote_id = String::from("test");
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.json"))?
+            .with_export(true)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/remote/processes-firefox/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        let ssh = MockCommandRunner::calls_of(&calls, "ssh");
+        assert_eq!(
+            vec![
+                "user@host",
+                "rrdtool",
+                "xport",
+                "--json",
+                "DEF:firefox=/some/remote/processes-firefox/ps_rss.rrd:value:AVERAGE",
+                "'XPORT:firefox:\"firefox\"'",
+                ">",
+                "/tmp/cgg-out-test.json",
+            ],
+            ssh[0]
+        );
+        assert_eq!(
+            vec![vec!["user@host:/tmp/cgg-out-test.json", "out.json"]],
+            MockCommandRunner::calls_of(&calls, "scp")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_remote_quoting() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/remote/"))?;
+        rrd.remote_id = String::from("test");
+        rrd.runner = Box::new(runner);
+
+        rrd.with_output_file(String::from("out.png"))?
+            .with_vertical_label(String::from("it's $HOME"))?;
+        rrd.graph_args.push(
+            "a$b`c\"d",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/remote/processes-a$b`c\"d/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        let ssh = MockCommandRunner::calls_of(&calls, "ssh");
+        assert_eq!(1, ssh.len());
+        assert!(ssh[0].contains(&String::from(
+            "'DEF:a_b_c_d=/some/remote/processes-a$b`c\"d/ps_rss.rrd:value:AVERAGE'"
+        )));
+        assert!(ssh[0].contains(&String::from("'it'\\''s $HOME'")));
+        assert!(ssh[0]
+            .iter()
+            .skip(1)
+            .filter(|arg| arg.contains('$') || arg.contains('`'))
+            .all(|arg| arg.starts_with('\'') && arg.ends_with('\'')));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_local_parallel() -> Result<()> {
+        /// Runner creating output file of rrdtool graph, i.e. its second argument
+        struct CreatingRunner;
+
+        impl CommandRunner for CreatingRunner {
+            fn run(&self, _: &str, args: &[String]) -> std::io::Result<std::process::Output> {
+                if args[0] == "graph" {
+                    std::thread::sleep(Duration::from_millis(10));
+                    std::fs::write(&args[1], "png")?;
+                }
+
+                MockCommandRunner::new().run("rrdtool", args)
+            }
+        }
+
+        let temp = TempDir::new().unwrap();
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(CreatingRunner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from(temp.path().join("out.png").to_str().unwrap()))?;
+
+        for index in 0..5 {
+            rrd.graph_args.new_graph();
+            rrd.graph_args.push(
+                &format!("process{}", index),
+                "#00ff00",
+                GraphElement::Line(3),
+                "/some/local/ps_rss.rrd",
+            );
+        }
+
+        let paths = rrd.exec()?;
+
+        assert_eq!(5, paths.len());
+
+        for (index, path) in paths.iter().enumerate() {
+            assert_eq!(temp.path().join(format!("out_{}.png", index + 1)), *path);
+            assert!(path.exists());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_missing_command() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(runner);
+        rrd.with_command(String::from("/opt/rrdtool/bin/rrdtool"))?
+            .with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+        rrd.graph_args.new_graph();
+
+        rrd.exec()?;
+        assert_eq!(
+            vec!["--version"],
+            MockCommandRunner::calls_of(&calls, "/opt/rrdtool/bin/rrdtool")[0]
+        );
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_command(String::from("cgg-missing-rrdtool"))?
+            .with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+        rrd.graph_args.new_graph();
+
+        let error = CggError::from(rrd.exec().unwrap_err());
+        assert!(matches!(
+            error,
+            CggError::RrdtoolNotFound { target: None, .. }
+        ));
+        assert_eq!(
+            "cgg-missing-rrdtool not found on PATH; install it or pass --rrdtool-bin",
+            error.to_string()
+        );
+
+        // rrd files are queried before rrdtool is checked
+        rrd.with_max_data_age(Some(60), 1600000000)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/local/ps_rss.rrd",
+        );
+        assert!(matches!(
+            CggError::from(rrd.exec().unwrap_err()),
+            CggError::RrdtoolNotFound { target: None, .. }
+        ));
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/load.rrd"))?;
+        rrd.with_command(String::from("cgg-missing-rrdtool"))?;
+        assert!(matches!(
+            rrd.with_rrd_file().map_err(CggError::from),
+            Err(CggError::RrdtoolNotFound { target: None, .. })
+        ));
+
+        assert!(rrd.with_command(String::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_embed_command() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let output = temp.path().join("out.png");
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(MockCommandRunner::new());
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from(output.to_str().unwrap()))?
+            .with_titles(Some(String::from("Memory usage")), HashMap::new())?
+            .with_embed_command(true)?;
+        rrd.graph_args.new_graph();
+        rrd.graph_args.push(
+            "used",
+            "#00ff00",
+            GraphElement::Stack,
+            "/some/local/memory/memory-used.rrd",
+        );
+
+        rrd.exec()?;
+
+        let command = std::fs::read_to_string(temp.path().join("out.png.cmd"))?;
+        assert!(command.starts_with(&format!("rrdtool graph {} ", output.display())));
+        assert!(command.contains(" --title 'Memory usage' "));
+        assert!(command.ends_with("\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_existing_output() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let output = temp.path().join("out.png");
+
+        let build = |input: &str, force: bool| -> Result<(Rrdtool, Calls)> {
+            let runner = MockCommandRunner::new();
+            let calls = runner.calls.clone();
+
+            let mut rrd = Rrdtool::new(Path::new(input))?;
+            rrd.runner = Box::new(runner);
+
+            rrd.with_subcommand(String::from("graph"))?
+                .with_output_file(String::from(output.to_str().unwrap()))?
+                .with_force(force)?;
+
+            for process in &["firefox", "chrome", "dolphin"] {
+                rrd.graph_args.new_graph();
+                rrd.graph_args.push(
+                    process,
+                    "#00ff00",
+                    GraphElement::Line(3),
+                    &format!("/some/path/processes-{}/ps_rss.rrd", process),
+                );
+            }
+
+            Ok((rrd, calls))
+        };
+
+        std::fs::write(temp.path().join("out_1.png"), "curated")?;
+        std::fs::write(temp.path().join("out_3.png"), "curated")?;
+
+        // Nothing is generated when any output exists, all existing files are listed
+        for input in &["/some/path", "user@host:/some/path"] {
+            let (mut rrd, calls) = build(input, false)?;
+
+            let error = format!("{:#}", rrd.exec().unwrap_err());
+            assert!(error.contains(&format!(
+                "Output files already exist, use --force to overwrite them: {}, {}",
+                temp.path().join("out_1.png").display(),
+                temp.path().join("out_3.png").display()
+            )));
+
+            let calls = calls.lock().unwrap();
+            assert!(calls
+                .iter()
+                .all(|(program, args)| program == "rrdtool" && args == &["--version"]));
+        }
+
+        // Forced run overwrites them
+        let (mut rrd, calls) = build("/some/path", true)?;
+        assert_eq!(3, rrd.exec()?.len());
+        assert_eq!(4, MockCommandRunner::calls_of(&calls, "rrdtool").len());
+
+        let (mut rrd, calls) = build("user@host:/some/path", true)?;
+        assert_eq!(3, rrd.exec()?.len());
+        assert_eq!(3, MockCommandRunner::calls_of(&calls, "scp").len());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_local_parallel_failure() -> Result<()> {
+        // First call checks rrdtool binary, regardless of its status
+        let runner = MockCommandRunner::new().failing("rrdtool", 3);
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+
+        for index in 0..5 {
+            rrd.graph_args.new_graph();
+            rrd.graph_args.push(
+                &format!("process{}", index),
+                "#00ff00",
+                GraphElement::Line(3),
+                "/some/local/ps_rss.rrd",
+            );
+        }
+
+        let error = format!("{:#}", rrd.exec().unwrap_err());
+
+        // Failures don't stop generating other graphs, and all of them are reported
+        assert_eq!(6, MockCommandRunner::calls_of(&calls, "rrdtool").len());
+        assert!(error.contains("Failed to generate 2 of 5 graphs"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_local_failure_typed() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(MockCommandRunner::new().failing("rrdtool", 2));
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/local/ps_rss.rrd",
+        );
+
+        match CggError::from(rrd.exec().unwrap_err()) {
+            CggError::RrdtoolFailed { status, stderr, .. } => {
+                assert_eq!(Some(1), status);
+                assert_eq!("mock failure", stderr);
+            }
+            other => anyhow::bail!("Expected rrdtool failure, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_failure_message_has_stderr() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(MockCommandRunner::new().failing("rrdtool", 1));
+
+        let error = CggError::from(rrd.get_version().unwrap_err());
+        assert_eq!(
+            "rrdtool failed with status 1: mock failure",
+            error.to_string()
+        );
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/var/lib/collectd/host"))?;
+        rrd.runner = Box::new(MockCommandRunner::new().failing("ssh", 1));
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/var/lib/collectd/host/ps_rss.rrd",
+        );
+
+        let error = CggError::from(rrd.exec().unwrap_err());
+        assert!(matches!(error, CggError::RrdtoolFailed { .. }));
+        assert!(error.to_string().contains("mock failure"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_remote_connection_failed() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("user@host:/var/lib/collectd/host"))?;
+        rrd.runner = Box::new(
+            MockCommandRunner::new()
+                .failing("ssh", 2)
+                .exiting_with("ssh", CggError::SSH_CONNECTION_FAILED),
+        );
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/var/lib/collectd/host/ps_rss.rrd",
+        );
+
+        let error = CggError::from(rrd.exec().unwrap_err());
+        assert!(matches!(
+            &error,
+            CggError::RemoteConnectionFailed { target, .. } if target == "user@host"
+        ));
+        assert_eq!(
+            "Failed to connect to user@host: mock failure",
+            error.to_string()
+        );
+
+        let error = CggError::from(rrd.query(&[String::from("--version")]).unwrap_err());
+        assert!(matches!(error, CggError::RemoteConnectionFailed { .. }));
+
+        // Other exit codes of ssh come from the remote command
+        rrd.runner = Box::new(MockCommandRunner::new().failing("ssh", 1));
+        let error = CggError::from(rrd.query(&[String::from("--version")]).unwrap_err());
+        assert!(matches!(error, CggError::RrdtoolFailed { .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_get_version() -> Result<()> {
+        /// Runner printing version of rrdtool and exiting with 1, as some builds do
+        struct VersionRunner;
+
+        impl CommandRunner for VersionRunner {
+            fn run(&self, _: &str, _: &[String]) -> std::io::Result<std::process::Output> {
+                use std::os::unix::process::ExitStatusExt;
+
+                Ok(std::process::Output {
+                    status: std::process::ExitStatus::from_raw(256),
+                    stdout: b"RRDtool 1.7.2  Copyright by Tobias Oetiker\n".to_vec(),
+                    stderr: Vec::new(),
+                })
+            }
+        }
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(VersionRunner);
+        assert_eq!("1.7.2", rrd.get_version()?);
+
+        rrd.runner = Box::new(MockCommandRunner::new().returning("rrdtool", "Usage: rrdtool\n"));
+        assert!(rrd.get_version().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_offline() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/var/lib/collectd/host"))?;
+        rrd.runner = Box::new(runner);
+
+        rrd.with_offline(true)?
+            .with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+
+        assert!(processes::processes_names::get(&rrd).is_err());
+        assert!(rrd.get_version().is_err());
+
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/var/lib/collectd/host/processes-firefox/ps_rss.rrd",
+        );
+
+        let error = format!("{:#}", rrd.exec().unwrap_err());
+        assert!(error.contains("offline mode: refusing remote command ssh"));
+
+        rrd.with_ssh_multiplex(true)?;
+        assert!(rrd.exec().is_err());
+
+        assert!(calls.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_remote_listing_cached() -> Result<()> {
+        let runner = MockCommandRunner::new().returning(
+            "ssh",
+            "cpu-0\ndf-root\nmemory\nprocesses-chrome\nprocesses-firefox\n",
+        );
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/var/lib/collectd/host"))?;
+        rrd.runner = Box::new(runner);
+
+        let processes = processes::processes_names::get(&rrd)?;
+        let mountpoints = processes::processes_names::get_with_prefix(&rrd, "df-")?;
+
+        assert_eq!(vec!["chrome", "firefox"], processes);
+        assert_eq!(vec!["root"], mountpoints);
+        assert_eq!(processes, processes::processes_names::get(&rrd)?);
+
+        let ssh_calls = MockCommandRunner::calls_of(&calls, "ssh");
+        assert_eq!(1, ssh_calls.len());
+        assert_eq!(
+            vec!["user@host", "ls", "/var/lib/collectd/host"],
+            ssh_calls[0]
+        );
+
+        // Other directory is listed separately
+        rrd.list_remote_dir("/var/lib/collectd/host/memory")?;
+        rrd.list_remote_dir("/var/lib/collectd/host/memory")?;
+        assert_eq!(2, MockCommandRunner::calls_of(&calls, "ssh").len());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_remote_listing_local() -> Result<()> {
+        let temp = TempDir::new()?;
+        std::fs::create_dir(temp.path().join("processes-firefox"))?;
+
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.runner = Box::new(runner);
+
+        assert_eq!(vec!["firefox"], processes::processes_names::get(&rrd)?);
+        assert!(rrd.list_remote_dir(&rrd.input_dir).is_err());
+        assert!(calls.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_ssh_multiplex() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/remote/"))?;
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?
+            .with_ssh_multiplex(true)?;
+
+        for process in &["firefox", "chrome"] {
+            rrd.graph_args.new_graph();
+            rrd.graph_args.push(
+                process,
+                "#00ff00",
+                GraphElement::Line(3),
+                &format!("/some/remote/processes-{}/ps_rss.rrd", process),
+            );
+        }
+
+        assert_eq!(2, rrd.exec()?.len());
+
+        let ssh = MockCommandRunner::calls_of(&calls, "ssh");
+        let scp = MockCommandRunner::calls_of(&calls, "scp");
+
+        assert_eq!(4, ssh.len());
+        assert_eq!(2, scp.len());
+
+        let control_path = ssh[0][1].clone();
+        assert!(control_path.starts_with("ControlPath="));
+
+        // Every command uses the same control socket
+        for args in ssh.iter().chain(scp.iter()) {
+            assert_eq!(vec!["-o", &control_path], args[..2]);
+        }
+
+        assert!(ssh[0].contains(&String::from("ControlMaster=auto")));
+        assert_eq!(vec!["-O", "exit", "user@host"], ssh[3][2..]);
+
+        // Control socket is removed at the end
+        assert!(!Path::new(&control_path["ControlPath=".len()..])
+            .parent()
+            .unwrap()
+            .exists());
+        assert!(rrd.ssh_options.control_path.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_build_fetch_args() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("user@host:2222:/some/remote/"))?;
+        rrd.remote_id = String::from("test");
+        rrd.with_output_file(String::from("graphs/out.png"))?;
+
+        assert_eq!(
+            (
+                "scp",
+                vec![
+                    String::from("-P"),
+                    String::from("2222"),
+                    String::from("user@host:/tmp/cgg-out-test.png"),
+                    String::from("graphs/out.png"),
+                ]
+            ),
+            rrd.build_fetch_args(&[rrd.get_remote_filename(0)], "graphs/out.png")
+        );
+
+        rrd.with_transfer(Transfer::Rsync)?;
+        rrd.graph_args.new_graph();
+        rrd.graph_args.new_graph();
+
+        assert_eq!(
+            vec!["/tmp/cgg-out-test/out_1.png", "/tmp/cgg-out-test/out_2.png"],
+            rrd.get_remote_filenames()
+        );
+        assert_eq!("graphs/", rrd.get_rsync_destination());
+        assert_eq!(
+            (
+                "rsync",
+                vec![
+                    String::from("-e"),
+                    String::from("ssh -p 2222"),
+                    String::from("user@host:/tmp/cgg-out-test/out_1.png"),
+                    String::from("user@host:/tmp/cgg-out-test/out_2.png"),
+                    String::from("graphs/"),
+                ]
+            ),
+            rrd.build_fetch_args(&rrd.get_remote_filenames(), &rrd.get_rsync_destination())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_remote_rsync() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/remote/"))?;
+        rrd.remote_id = String::from("test");
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?
+            .with_transfer(Transfer::Rsync)?;
+
+        for process in &["firefox", "chrome", "dolphin"] {
+            rrd.graph_args.new_graph();
+            rrd.graph_args.push(
+                process,
+                "#00ff00",
+                GraphElement::Line(3),
+                &format!("/some/remote/processes-{}/ps_rss.rrd", process),
+            );
+        }
+
+        let paths = rrd.exec()?;
+
+        assert_eq!(3, paths.len());
+        assert_eq!(PathBuf::from("out_3.png"), paths[2]);
+
+        let ssh = MockCommandRunner::calls_of(&calls, "ssh");
+
+        assert_eq!(5, ssh.len());
+        assert_eq!(
+            vec!["user@host", "mkdir", "-p", "/tmp/cgg-out-test"],
+            ssh[0]
+        );
+        assert_eq!(vec!["user@host", "rm", "-rf", "/tmp/cgg-out-test"], ssh[4]);
+        assert_eq!(
+            vec![
+                "user@host",
+                "rrdtool",
+                "graph",
+                "/tmp/cgg-out-test/out_1.png"
+            ],
+            ssh[1][..4]
+        );
+
+        assert!(MockCommandRunner::calls_of(&calls, "scp").is_empty());
+        assert_eq!(
+            vec![vec![
+                "user@host:/tmp/cgg-out-test/out_1.png",
+                "user@host:/tmp/cgg-out-test/out_2.png",
+                "user@host:/tmp/cgg-out-test/out_3.png",
+                "./",
+            ]],
+            MockCommandRunner::calls_of(&calls, "rsync")
+        );
+
+        // Remote directory is removed also when graphs can't be copied
+        let runner = MockCommandRunner::new().failing("rsync", 1);
+        let calls = runner.calls.clone();
+        rrd.runner = Box::new(runner);
+
+        assert!(rrd.exec().is_err());
+        assert_eq!(
+            vec!["user@host", "rm", "-rf", "/tmp/cgg-out-test"],
+            *MockCommandRunner::calls_of(&calls, "ssh").last().unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn shell_quote() -> Result<()> {
+        assert_eq!(
+            "LINE3:firefox#00ff00:firefox",
+            super::shell_quote("LINE3:firefox#00ff00:firefox")
+        );
+        assert_eq!("'free memory'", super::shell_quote("free memory"));
+        assert_eq!("'it'\\''s'", super::shell_quote("it's"));
+        assert_eq!("''", super::shell_quote(""));
+        assert_eq!("'#comment'", super::shell_quote("#comment"));
+        assert_eq!("'$HOME'", super::shell_quote("$HOME"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_max_data_age() -> Result<()> {
+        let now = 1600000000;
+
+        for (last, fresh) in &[(now - 7200, false), (now - 60, true)] {
+            let runner = MockCommandRunner::new().returning("rrdtool last", &last.to_string());
+            let calls = runner.calls.clone();
+
+            let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+            rrd.runner = Box::new(runner);
+
+            rrd.with_subcommand(String::from("graph"))?
+                .with_output_file(String::from("out.png"))?
+                .with_max_data_age(Some(3600), now)?;
+            rrd.graph_args.push(
+                "firefox",
+                "#00ff00",
+                GraphElement::Line(3),
+                "/some/local/ps_rss.rrd",
+            );
+
+            assert_eq!(*fresh, rrd.exec().is_ok());
+
+            // Graph is generated only from fresh data
+            let graphs = MockCommandRunner::calls_of(&calls, "rrdtool")
+                .iter()
+                .filter(|args| args[0] == "graph")
+                .count();
+            assert_eq!(*fresh as usize, graphs);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_get_peak() -> Result<()> {
+        let runner = MockCommandRunner::new().returning("rrdtool graph", "0x0\n1234.500000\n");
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(runner);
+        rrd.with_start(1600000000)?.with_end(1600003600)?;
+
+        assert_eq!(1234.5, rrd.get_peak("/some/local/ps_rss.rrd", "value")?);
+        assert_eq!(
+            vec![vec![
+                "graph",
+                "/dev/null",
+                "--start",
+                "1600000000",
+                "--end",
+                "1600003600",
+                "DEF:value=/some/local/ps_rss.rrd:value:AVERAGE",
+                "VDEF:peak=value,MAXIMUM",
+                "PRINT:peak:%lf",
+            ]],
+            MockCommandRunner::calls_of(&calls, "rrdtool")
+        );
+
+        for output in &["0x0\n-nan\n", "0x0\n"] {
+            rrd.runner = Box::new(MockCommandRunner::new().returning("rrdtool graph", output));
+            assert!(rrd.get_peak("/some/local/ps_rss.rrd", "value").is_err());
+        }
+
+        rrd.runner = Box::new(MockCommandRunner::new().failing("rrdtool", 1));
+        assert!(rrd.get_peak("/some/local/ps_rss.rrd", "value").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_run_summary() -> Result<()> {
+        captured_logs();
+
+        let temp = TempDir::new().unwrap();
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(MockCommandRunner::new());
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from(temp.path().join("out.png").to_str().unwrap()))?
+            .with_force(true)?;
+
+        for process in &["firefox", "chrome", "dolphin"] {
+            rrd.graph_args.new_graph();
+            rrd.graph_args.push(
+                process,
+                "#00ff00",
+                GraphElement::Line(3),
+                "/some/local/ps_rss.rrd",
+            );
+        }
+
+        // Graphs are not generated by mock, so create them to count their size,
+        // which are overwritten when forced
+        for index in 1..=3 {
+            std::fs::write(temp.path().join(format!("out_{}.png", index)), [0u8; 10])?;
+        }
+
+        let paths = rrd.exec()?;
+
+        assert_eq!(3, paths.len());
+        assert!(captured_logs()
+            .iter()
+            .any(|log| log.starts_with("Generated 3 graphs (30 B total) in ")));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_palette() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
+
+        assert_eq!(Rrdtool::COLORS[0], rrd.get_color(0));
+        assert_eq!(GraphElement::Line(3), rrd.get_line(19, 3));
+        assert_eq!(GraphElement::DashedLine(3), rrd.get_line(20, 3));
+
+        rrd.with_palette(vec![String::from("#000000"), String::from("#ffffff")])?;
+
+        assert_eq!("#ffffff", rrd.get_color(1));
+        assert_eq!("#000000", rrd.get_color(2));
+        assert_eq!(GraphElement::DashedLine(3), rrd.get_line(2, 3));
+
+        assert!(rrd.with_palette(Vec::new()).is_err());
+        assert!(rrd.with_palette(vec![String::from("blue")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_palette_preset() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for process in &["chrome", "firefox"] {
+            let dir = temp.path().join(format!("processes-{}", process));
+            std::fs::create_dir(&dir)?;
+            std::fs::File::create(dir.join("ps_rss.rrd"))?;
+        }
+
+        for preset in &[
+            PalettePreset::Default,
+            PalettePreset::Colorblind,
+            PalettePreset::Grayscale,
+        ] {
+            let mut plugins_config = config::PluginsConfig {
+                data: HashMap::new(),
+                order: Vec::new(),
+            };
+            plugins_config.data.insert(
+                Plugins::Processes,
+                Box::new(processes::processes_data::ProcessesData::new(20, None)),
+            );
+
+            let mut rrd = Rrdtool::new(temp.path())?;
+            rrd.with_palette(preset.colors())?
+                .with_plugins(plugins_config)?;
+
+            let colors = rrd.graph_args.args[0]
+                .iter()
+                .filter(|arg| arg.starts_with("LINE"))
+                .map(|arg| {
+                    let color = arg.find('#').unwrap();
+                    String::from(&arg[color..color + 7])
+                })
+                .collect::<Vec<String>>();
+
+            assert_eq!(preset.colors()[..2].to_vec(), colors);
+        }
+
+        assert_ne!(
+            PalettePreset::Default.colors()[0],
+            PalettePreset::Colorblind.colors()[0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_vertical_label() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("memory"))?;
+        std::fs::File::create(temp.path().join("memory").join("memory-free.rrd"))?;
 
-                trace!(
-                    "Parsed remote path, username: {}, hostname: {}, path: {}",
-                    username,
-                    hostname,
-                    remote_path
-                );
+        let plugins_config = || {
+            let mut plugins_config = config::PluginsConfig {
+                data: HashMap::new(),
+                order: Vec::new(),
+            };
+            plugins_config.data.insert(
+                Plugins::Memory,
+                Box::new(memory::memory_data::MemoryData::new(vec![
+                    memory::memory_type::MemoryType::Free,
+                ])) as Box<dyn std::any::Any>,
+            );
+            plugins_config
+        };
 
-                Ok((
-                    target,
-                    String::from(remote_path),
-                    Some(username),
-                    Some(hostname),
-                ))
-            }
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.with_plugins(plugins_config())?;
 
-            // Local
-            false => {
-                let target = Target::Local;
-                Ok((
-                    target,
-                    String::from(input_dir.to_str().unwrap()),
-                    None,
-                    None,
-                ))
-            }
-        }
+        assert_eq!(
+            vec!["--vertical-label", "bytes"],
+            rrd.graph_args.info[0].options
+        );
+
+        let mut rrd = Rrdtool::new(temp.path())?;
+        rrd.with_vertical_label(String::from("free memory"))?
+            .with_plugins(plugins_config())?;
+
+        assert!(rrd.graph_args.info[0].options.is_empty());
+        assert_eq!(vec!["--vertical-label", "free memory"], rrd.common_args);
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/path"))?;
+        rrd.with_vertical_label(String::from("free memory"))?;
+
+        assert_eq!(
+            "'free memory'",
+            rrd.build_ssh_args(rrd.common_args.clone())[2]
+        );
+
+        Ok(())
     }
-}
 
-/// Print output of system command
-pub fn print_process_command_output(output: std::process::Output) {
-    error!("status: {}", output.status);
-    error!("stdout: {}", String::from_utf8_lossy(&output.stdout));
-    error!("stderr: {}", String::from_utf8_lossy(&output.stderr));
-}
+    #[test]
+    pub fn rrdtool_with_step() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_step(None)?;
+        assert!(rrd.common_args.is_empty());
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use anyhow::Result;
-    use std::path::Path;
+        assert!(rrd.with_step(Some(0)).is_err());
+
+        rrd.with_step(Some(3600))?;
+        assert_eq!(vec!["--step", "3600"], rrd.common_args);
+
+        Ok(())
+    }
 
     #[test]
-    pub fn rrdtool_builder() -> Result<()> {
-        let mut rrd = Rrdtool::new(Path::new("/some/local/"));
+    pub fn rrdtool_with_watermark() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_watermark(String::from("generated by cgg on 2020-09-13"))?;
 
-        rrd.with_output_file(String::from("out.png"))?
-            .with_subcommand(String::from("graph"))?
-            .with_start(123456)?
-            .with_end(1234567)?;
+        assert_eq!(
+            vec!["--watermark", "generated by cgg on 2020-09-13"],
+            rrd.common_args
+        );
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/path"))?;
+        rrd.with_watermark(String::from("generated by cgg"))?;
+
+        assert_eq!(
+            vec!["user@host", "--watermark", "'generated by cgg'"],
+            rrd.build_ssh_args(rrd.common_args.clone())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_legend() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_legend(true, None, None)?;
+        assert!(rrd.common_args.is_empty());
+
+        rrd.with_legend(
+            true,
+            Some(LegendPosition::East),
+            Some(LegendDirection::BottomUp),
+        )?;
+        assert_eq!(
+            vec![
+                "--legend-position",
+                "east",
+                "--legend-direction",
+                "bottomup"
+            ],
+            rrd.common_args
+        );
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_legend(false, Some(LegendPosition::West), None)?;
+        assert_eq!(vec!["--no-legend"], rrd.common_args);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_size_limits() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+
+        assert!(rrd.with_width(0).is_err());
+        assert!(rrd.with_width(100000).is_err());
+        assert!(rrd.with_height(0).is_err());
+        assert!(rrd.with_height(Rrdtool::MAX_SIZE + 1).is_err());
+        assert_eq!((None, None), (rrd.width, rrd.height));
+
+        rrd.with_width(1)?.with_height(Rrdtool::MAX_SIZE)?;
+        assert_eq!((Some(1), Some(10000)), (rrd.width, rrd.height));
+
+        let mut sizes = HashMap::new();
+        sizes.insert(Plugins::Memory, (1024, 0));
+        assert!(rrd.with_plugin_sizes(sizes).is_err());
+        assert!(rrd.plugin_sizes.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_y_limits() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_y_limits(Some(0.0), None, false)?;
+        assert_eq!(vec!["--lower-limit", "0"], rrd.common_args);
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_y_limits(None, Some(17179869184.0), false)?;
+        assert_eq!(vec!["--upper-limit", "17179869184"], rrd.common_args);
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_y_limits(Some(-1.5), Some(100.0), true)?;
+        assert_eq!(
+            vec!["--lower-limit", "-1.5", "--upper-limit", "100", "--rigid"],
+            rrd.common_args
+        );
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.with_y_limits(None, None, false)?;
+        assert!(rrd.common_args.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_hrule() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+
+        rrd.with_hrule(&Hrule::from_str("8589934592:#ff0000:8 GiB limit")?)?
+            .with_hrule(&Hrule::from_str("4294967296:#ffa500")?)?;
+
+        assert_eq!(
+            vec![
+                "HRULE:8589934592#ff0000:\"8 GiB limit\"",
+                "HRULE:4294967296#ffa500"
+            ],
+            rrd.common_args
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_show_rrd_step() -> Result<()> {
+        captured_logs();
+
+        let runner = MockCommandRunner::new().returning(
+            "rrdtool info",
+            "filename = \"/some/local/processes-firefox/ps_rss.rrd\"\n\
+             rrd_version = \"0003\"\n\
+             step = 10\n\
+             last_update = 1605734459\n",
+        );
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?
+            .with_show_rrd_step(true)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/local/processes-firefox/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        assert!(captured_logs().contains(&String::from(
+            "Step of /some/local/processes-firefox/ps_rss.rrd is 10s"
+        )));
+
+        assert_eq!(None, parse_rrd_step("rrd_version = \"0003\""));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_ds() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+
+        rrd.with_ds(Some(String::from("rx_bytes")))?;
+        assert_eq!(Some(String::from("rx_bytes")), rrd.graph_args.ds);
+
+        assert!(rrd.with_ds(Some(String::new())).is_err());
+        assert!(rrd.with_ds(Some(String::from("rx:bytes"))).is_err());
+        assert!(rrd
+            .with_ds(Some(String::from("a_very_long_data_source")))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_print_command() -> Result<()> {
+        captured_logs();
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/"))?;
+        rrd.runner = Box::new(MockCommandRunner::new());
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("printed command.png"))?
+            .with_print_command(true)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/local/processes-firefox/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        let command = build_shell_command("rrdtool", &rrd.build_rrdtool_args()[0]);
+
+        assert!(command.starts_with("rrdtool graph 'printed command.png'"));
+        assert!(captured_logs().contains(&command));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn format_bytes() -> Result<()> {
+        assert_eq!("999 B", super::format_bytes(999));
+        assert_eq!("3.2 MB", super::format_bytes(3_200_000));
+        assert_eq!("1.5 GB", super::format_bytes(1_500_000_000));
 
-        assert_eq!("rrdtool", rrd.command);
-        assert_eq!("out.png", rrd.output_filename);
-        assert_eq!("graph", rrd.subcommand);
-        assert_eq!(4, rrd.common_args.len());
-        assert_eq!(0, rrd.graph_args.args.len());
         Ok(())
     }
 
     #[test]
     pub fn rrdtool_simple_exec() -> Result<()> {
-        Rrdtool::new(Path::new("/some/local"))
+        Rrdtool::new(Path::new("/some/local"))?
             .with_subcommand(String::from("graph"))?
             .exec()
             .context("Failed to exec rrdtool")?;
         Ok(())
     }
 
+    #[test]
+    pub fn rrdtool_exec_remote_scp_retries() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new().failing("scp", 1);
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("marcin@10.0.0.1:/some/remote/path"))?;
+        rrd.runner = Box::new(runner);
+        rrd.scp_retry_delay = Duration::from_millis(0);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from(temp.path().join("out.png").to_str().unwrap()))?
+            .with_scp_retries(2)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/remote/path/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        assert_eq!(1, MockCommandRunner::calls_of(&calls, "ssh").len());
+        assert_eq!(2, MockCommandRunner::calls_of(&calls, "scp").len());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_remote_scp_retries_exhausted() -> Result<()> {
+        let runner = MockCommandRunner::new().failing("scp", 2);
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("marcin@10.0.0.1:/some/remote/path"))?;
+        rrd.runner = Box::new(runner);
+        rrd.scp_retry_delay = Duration::from_millis(0);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?
+            .with_scp_retries(1)?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/remote/path/ps_rss.rrd",
+        );
+
+        assert!(rrd.exec().is_err());
+        assert_eq!(1, MockCommandRunner::calls_of(&calls, "ssh").len());
+        assert_eq!(2, MockCommandRunner::calls_of(&calls, "scp").len());
+
+        Ok(())
+    }
+
     #[test]
     pub fn rrdtool_with_output_file_local() -> Result<()> {
         let path = Path::new("/some/local/path");
-        let mut rrd = Rrdtool::new(path);
+        let mut rrd = Rrdtool::new(path)?;
         rrd.with_output_file(String::from("out.png"))?;
 
         assert_eq!("out.png", rrd.output_filename);
@@ -426,22 +4157,157 @@ pub mod tests {
 
     #[test]
     pub fn rrdtool_with_output_file_remote() -> Result<()> {
-        let mut rrd = Rrdtool::new(Path::new("marcin@10.0.0.1:/some/remote/path"));
+        let mut rrd = Rrdtool::new(Path::new("marcin@10.0.0.1:/some/remote/path"))?;
+        rrd.remote_id = String::from("test");
         rrd.with_output_file(String::from("out.png"))?;
 
-        assert_eq!("/tmp/cgg-out.png", rrd.remote_filename.unwrap());
+        assert_eq!("/tmp/cgg-out-test.png", rrd.remote_filename.unwrap());
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_output_file_stdout() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/remote/path"))?;
+        assert!(rrd.with_output_file(String::from("-")).is_err());
+
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("/some/local/path"))?;
+        rrd.runner = Box::new(runner);
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("-"))?;
+
+        for _ in 0..2 {
+            rrd.graph_args.new_labeled_graph("web");
+            rrd.graph_args.push(
+                "firefox",
+                "#00ff00",
+                GraphElement::Line(3),
+                "/some/local/path/processes-firefox/ps_rss.rrd",
+            );
+        }
+
+        assert_eq!("-", rrd.get_output_filename(1));
+        assert!(rrd.exec().is_err());
+        assert!(calls.lock().unwrap().is_empty());
+
+        // Standard output isn't returned as generated file
+        rrd.graph_args.args.pop();
+        rrd.graph_args.info.pop();
+        assert!(rrd.exec()?.is_empty());
+        assert!(MockCommandRunner::calls_of(&calls, "rrdtool")
+            .iter()
+            .any(|args| args[0] == "graph" && args[1] == "-"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_remote_filename_unique() -> Result<()> {
+        let mut first = Rrdtool::new(Path::new("user@host:/some/remote/path"))?;
+        let mut second = Rrdtool::new(Path::new("user@host:/some/remote/path"))?;
+
+        first.with_output_file(String::from("out.png"))?;
+        second.with_output_file(String::from("out.png"))?;
+
+        // Process id, random part and number of instance
+        let parts = first.remote_id.split('-').collect::<Vec<&str>>();
+        assert_eq!(3, parts.len());
+        assert_eq!(std::process::id().to_string(), parts[0]);
+        assert_eq!(16, parts[1].len());
+        assert!(u64::from_str_radix(parts[1], 16).is_ok());
+
+        assert_ne!(first.get_remote_filename(0), second.get_remote_filename(0));
+        assert!(first
+            .get_remote_filename(0)
+            .starts_with(Rrdtool::REMOTE_TEMP_PREFIX));
+
+        first.with_transfer(Transfer::Rsync)?.graph_args.new_graph();
+        second
+            .with_transfer(Transfer::Rsync)?
+            .graph_args
+            .new_graph();
+
+        assert_ne!(first.get_remote_filename(0), second.get_remote_filename(0));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_output_file_svg() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("marcin@10.0.0.1:/some/remote/path"))?;
+        rrd.remote_id = String::from("test");
+        rrd.with_output_file(String::from("graphs/out.svg"))?;
+
+        assert_eq!(vec!["--imgformat", "SVG"], rrd.common_args);
+        assert_eq!(
+            "/tmp/cgg-out-test.svg",
+            rrd.remote_filename.as_ref().unwrap()
+        );
+
+        // Explicit format wins over extension
+        rrd.with_image_format(Some(ImageFormat::Png))?;
+
+        assert!(rrd.common_args.is_empty());
+        assert_eq!("/tmp/cgg-out-test.png", rrd.remote_filename.unwrap());
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_output_file_pdf() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/local/path"))?;
+        rrd.with_output_file(String::from("out.PDF"))?
+            .with_image_format(None)?;
+
+        assert_eq!(vec!["--imgformat", "PDF"], rrd.common_args);
+        assert_eq!(None, rrd.remote_filename);
+
+        rrd.with_image_format(Some(ImageFormat::Eps))?;
+        assert_eq!(vec!["--imgformat", "EPS"], rrd.common_args);
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_output_file_unknown_extension() -> Result<()> {
+        for output in &["out.gif", "out", "out.svg.bak"] {
+            let mut rrd = Rrdtool::new(Path::new("marcin@10.0.0.1:/some/remote/path"))?;
+            rrd.remote_id = String::from("test");
+            rrd.with_output_file(String::from(*output))?;
+
+            assert!(rrd.common_args.is_empty());
+            assert_eq!("/tmp/cgg-out-test.png", rrd.remote_filename.unwrap());
+        }
         Ok(())
     }
 
     #[test]
     pub fn rrdtool_parse_input_path_local() -> Result<()> {
         let original_path = Path::new("/some/local/path");
-        let (target, path, username, hostname) = Rrdtool::parse_input_path(&original_path)?;
+        let (target, path, username, hostname, port) = Rrdtool::parse_input_path(&original_path)?;
 
         assert!(Target::Local == target);
         assert_eq!(original_path.to_str().unwrap(), path);
         assert!(username.is_none());
         assert!(hostname.is_none());
+        assert!(port.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_parse_input_path_local_symlink() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let host = temp.path().join("host-1");
+        let current = temp.path().join("current");
+
+        std::fs::create_dir(&host)?;
+        std::os::unix::fs::symlink(&host, &current)?;
+
+        let (target, path, _, _, _) = Rrdtool::parse_input_path(&current)?;
+
+        assert!(Target::Local == target);
+        assert_eq!(host.canonicalize()?.to_str().unwrap(), path);
 
         Ok(())
     }
@@ -449,12 +4315,13 @@ pub mod tests {
     #[test]
     pub fn rrdtool_parse_input_path_remote_hostname() -> Result<()> {
         let original_path = Path::new("marcin@localhost:/some/remote/path");
-        let (target, path, username, hostname) = Rrdtool::parse_input_path(&original_path)?;
+        let (target, path, username, hostname, port) = Rrdtool::parse_input_path(&original_path)?;
 
         assert!(Target::Remote == target);
         assert_eq!("/some/remote/path", path);
         assert_eq!("marcin", username.unwrap());
         assert_eq!("localhost", hostname.unwrap());
+        assert!(port.is_none());
 
         Ok(())
     }
@@ -462,7 +4329,7 @@ pub mod tests {
     #[test]
     pub fn rrdtool_parse_input_path_remote_ip() -> Result<()> {
         let original_path = Path::new("twardak@10.0.0.52:/some/remote/path/");
-        let (target, path, username, hostname) = Rrdtool::parse_input_path(&original_path)?;
+        let (target, path, username, hostname, _) = Rrdtool::parse_input_path(&original_path)?;
 
         assert!(Target::Remote == target);
         assert_eq!("/some/remote/path/", path);
@@ -472,9 +4339,141 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn rrdtool_parse_input_path_remote_path_with_colon() -> Result<()> {
+        let (target, path, username, hostname, _) =
+            Rrdtool::parse_input_path(Path::new("user@host:/p:ath"))?;
+
+        assert!(Target::Remote == target);
+        assert_eq!("/p:ath", path);
+        assert_eq!("user", username.unwrap());
+        assert_eq!("host", hostname.unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_parse_input_path_remote_port() -> Result<()> {
+        let (target, path, username, hostname, port) =
+            Rrdtool::parse_input_path(Path::new("user@host:2222:/var/lib/collectd"))?;
+
+        assert!(Target::Remote == target);
+        assert_eq!("/var/lib/collectd", path);
+        assert_eq!("user", username.unwrap());
+        assert_eq!("host", hostname.unwrap());
+        assert_eq!(Some(2222), port);
+
+        // Relative path starting with digits is not a port
+        let (_, path, _, _, port) = Rrdtool::parse_input_path(Path::new("user@host:2222:data"))?;
+
+        assert_eq!("2222:data", path);
+        assert!(port.is_none());
+
+        assert!(Rrdtool::parse_input_path(Path::new("user@host:99999:/var/lib/collectd")).is_err());
+        assert!(Rrdtool::new(Path::new("user@host:99999:/var/lib/collectd")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_exec_remote_port() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:2222:/some/remote/"))?;
+        rrd.remote_id = String::from("test");
+        rrd.runner = Box::new(runner);
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/remote/processes-firefox/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        let ssh = MockCommandRunner::calls_of(&calls, "ssh");
+        let scp = MockCommandRunner::calls_of(&calls, "scp");
+
+        assert_eq!(
+            vec!["-p", "2222", "user@host", "rrdtool", "graph"],
+            ssh[0][..5]
+        );
+        assert_eq!(
+            vec!["-P", "2222", "user@host:/tmp/cgg-out-test.png", "out.png"],
+            scp[0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_ssh_key() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let key = temp.path().join("id_ed25519");
+        std::fs::write(&key, "key")?;
+
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let mut rrd = Rrdtool::new(Path::new("user@host:/some/remote/"))?;
+        rrd.remote_id = String::from("test");
+        rrd.runner = Box::new(runner);
+
+        assert!(rrd.with_ssh_key(Some(temp.path().join("missing"))).is_err());
+
+        rrd.with_subcommand(String::from("graph"))?
+            .with_output_file(String::from("out.png"))?
+            .with_ssh_key(Some(key.clone()))?;
+        rrd.graph_args.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/remote/processes-firefox/ps_rss.rrd",
+        );
+
+        rrd.exec()?;
+
+        let key = String::from(key.to_str().unwrap());
+
+        assert_eq!(
+            vec!["-i", &key, "user@host", "rrdtool"],
+            MockCommandRunner::calls_of(&calls, "ssh")[0][..4]
+        );
+        assert_eq!(
+            vec!["-i", &key, "user@host:/tmp/cgg-out-test.png", "out.png"],
+            MockCommandRunner::calls_of(&calls, "scp")[0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_parse_input_path_local_look_alike() -> Result<()> {
+        for original_path in &[
+            "C:\\collectd\\data",
+            "/home/a@b/data",
+            "/home/a@b:c/data",
+            "user@C:\\collectd\\data",
+        ] {
+            let (target, path, username, hostname, _) =
+                Rrdtool::parse_input_path(Path::new(original_path))?;
+
+            assert!(Target::Local == target, "{} is not local", original_path);
+            assert_eq!(*original_path, path);
+            assert!(username.is_none());
+            assert!(hostname.is_none());
+        }
+
+        Ok(())
+    }
+
     #[test]
     pub fn rrdtool_get_output_filename_single_file() -> Result<()> {
-        let mut rrd = Rrdtool::new(Path::new("/some/path"));
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
 
         rrd.with_output_file(String::from("some_file.png"))?;
         rrd.graph_args.new_graph();
@@ -488,7 +4487,7 @@ pub mod tests {
 
     #[test]
     pub fn rrdtool_get_output_filename_multiple_files() -> Result<()> {
-        let mut rrd = Rrdtool::new(Path::new("/some/path"));
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
 
         rrd.with_output_file(String::from("some other file.png"))?;
         rrd.graph_args.new_graph();
@@ -501,4 +4500,45 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    pub fn rrdtool_get_output_filename_labeled_files() -> Result<()> {
+        let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
+
+        rrd.with_output_file(String::from("out.png"))?;
+        rrd.graph_args.new_labeled_graph("web");
+        rrd.graph_args.new_graph();
+        rrd.graph_args.new_labeled_graph("db");
+
+        assert_eq!("out_web.png", rrd.get_output_filename(0));
+        assert_eq!("out.png", rrd.get_output_filename(1));
+        assert_eq!("out_db.png", rrd.get_output_filename(2));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_get_output_filename_without_extension() -> Result<()> {
+        for (output, first, second) in &[
+            ("mychart", "mychart_1", "mychart_2"),
+            ("mychart.", "mychart_1.", "mychart_2."),
+            (
+                "dir.with.dots/out",
+                "dir.with.dots/out_1",
+                "dir.with.dots/out_2",
+            ),
+            ("dir/.hidden", "dir/.hidden_1", "dir/.hidden_2"),
+        ] {
+            let mut rrd = Rrdtool::new(Path::new("/some/path"))?;
+
+            rrd.with_output_file(String::from(*output))?;
+            rrd.graph_args.new_graph();
+            rrd.graph_args.new_graph();
+
+            assert_eq!(*first, rrd.get_output_filename(0));
+            assert_eq!(*second, rrd.get_output_filename(1));
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,207 @@
+use super::super::memory::memory_type::MemoryType;
+use super::common::{Plugins, Rrdtool, Target};
+use super::remote::SshOptions;
+
+use anyhow::{Context, Result};
+use log::{debug, trace};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Data needed to draw the same metric of multiple hosts on one graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareHosts {
+    /// Local or remote paths to collectd data of each host
+    pub inputs: Vec<PathBuf>,
+    /// Plugin of the metric
+    pub plugin: Plugins,
+    /// Process name or memory type, depending on plugin
+    pub metric: String,
+}
+
+impl Rrdtool {
+    /// Draw the same metric of all hosts on one graph, each line labeled with its host.
+    /// Graph is always generated locally, rrd files of remote hosts are copied first.
+    pub fn with_compare_hosts(&mut self, compare: CompareHosts) -> Result<&mut Self> {
+        debug!(
+            "Comparing {} of hosts: {:?}",
+            compare.metric, compare.inputs
+        );
+
+        let filename = get_metric_filename(compare.plugin, &compare.metric)?;
+
+        self.target = Target::Local;
+        self.graph_args.target = Target::Local;
+        self.graph_args.new_graph();
+
+        let mut hosts = Vec::new();
+
+        for (index, input) in compare.inputs.iter().enumerate() {
+            let (target, input_dir, username, hostname, port) = Rrdtool::parse_input_path(input)?;
+
+            let (host, path) = match target {
+                Target::Local => {
+                    let path = Path::new(&input_dir).join(&filename);
+
+                    if !path.exists() {
+                        anyhow::bail!("Cannot find {}", path.display());
+                    }
+
+                    (get_host_name(&input_dir), path)
+                }
+                Target::Remote => {
+                    let hostname = hostname.unwrap();
+                    let ssh_options = SshOptions {
+                        port,
+                        identity: self.ssh_options.identity.clone(),
+                        control_path: None,
+                        offline: self.ssh_options.offline,
+                    };
+                    let path = self
+                        .fetch_remote_file(
+                            &username.unwrap(),
+                            &hostname,
+                            &ssh_options,
+                            &Path::new(&input_dir).join(&filename),
+                            index,
+                        )
+                        .context(format!("Failed to copy {} from {}", filename, hostname))?;
+
+                    (hostname, path)
+                }
+            };
+
+            trace!("Comparing host {}: {}", host, path.display());
+
+            hosts.push((host, path));
+        }
+
+        for (index, (host, path)) in hosts.iter().enumerate() {
+            // Hosts with the same directory name, e.g. /a/host and /b/host, are told apart
+            // by the whole input, variables get unique names from their legends
+            let label = match hosts.iter().filter(|(other, _)| other == host).count() {
+                1 => host.clone(),
+                _ => String::from(compare.inputs[index].to_string_lossy()),
+            };
+
+            let color = self.get_color(index);
+            let element = self.get_line(index, 3);
+
+            self.graph_args
+                .push(&label, &color, element, path.to_str().unwrap());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Returns path of rrd file, relative to host directory, with given metric
+fn get_metric_filename(plugin: Plugins, metric: &str) -> Result<String> {
+    match plugin {
+        Plugins::Processes => Ok(String::from("processes-") + metric + "/ps_rss.rrd"),
+        Plugins::Memory => match MemoryType::from_str(metric) {
+            Ok(memory_type) => Ok(String::from("memory/") + memory_type.to_filename()),
+            Err(_) => anyhow::bail!("Unknown memory type {}", metric),
+        },
+        Plugins::Cpu => anyhow::bail!("--compare-hosts is not supported by cpu plugin"),
+        Plugins::Df => Ok(String::from("df-") + metric + "/df_complex-used.rrd"),
+    }
+}
+
+/// Returns host name from collectd directory, which is named after the host
+pub fn get_host_name(input_dir: &str) -> String {
+    match Path::new(input_dir).file_name() {
+        Some(name) => String::from(name.to_string_lossy()),
+        None => String::from(input_dir),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use std::fs::{create_dir_all, File};
+    use tempfile::TempDir;
+
+    #[test]
+    pub fn rrdtool_with_compare_hosts() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let hosts = vec![temp.path().join("host-a"), temp.path().join("host-b")];
+
+        for host in &hosts {
+            create_dir_all(host.join("processes-firefox"))?;
+            File::create(host.join("processes-firefox").join("ps_rss.rrd"))?;
+        }
+
+        let mut rrd = Rrdtool::new(&hosts[0])?;
+        rrd.with_compare_hosts(CompareHosts {
+            inputs: hosts.clone(),
+            plugin: Plugins::Processes,
+            metric: String::from("firefox"),
+        })?;
+
+        assert_eq!(1, rrd.graph_args.args.len());
+
+        let lines = rrd.graph_args.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("LINE"))
+            .collect::<Vec<&String>>();
+
+        assert_eq!(2, lines.len());
+        assert!(lines[0].ends_with(":\"host-a\""));
+        assert!(lines[1].ends_with(":\"host-b\""));
+        assert!(rrd.graph_args.args[0][0].contains("host-a/processes-firefox/ps_rss.rrd"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn rrdtool_with_compare_hosts_same_name() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+        let hosts = vec![
+            temp.path().join("a").join("host"),
+            temp.path().join("b").join("host"),
+        ];
+
+        for host in &hosts {
+            create_dir_all(host.join("processes-firefox"))?;
+            File::create(host.join("processes-firefox").join("ps_rss.rrd"))?;
+        }
+
+        let mut rrd = Rrdtool::new(&hosts[0])?;
+        rrd.with_compare_hosts(CompareHosts {
+            inputs: hosts.clone(),
+            plugin: Plugins::Processes,
+            metric: String::from("firefox"),
+        })?;
+
+        let vnames = rrd.graph_args.args[0]
+            .iter()
+            .filter_map(|arg| arg.strip_prefix("DEF:"))
+            .map(|def| def.split('=').next().unwrap())
+            .collect::<Vec<&str>>();
+
+        assert_eq!(2, vnames.len());
+        assert_ne!(vnames[0], vnames[1]);
+
+        let lines = rrd.graph_args.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("LINE"))
+            .collect::<Vec<&String>>();
+
+        assert!(lines[0].contains("a/host"));
+        assert!(lines[1].contains("b/host"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn get_metric_filename() -> Result<()> {
+        assert_eq!(
+            "memory/memory-used.rrd",
+            super::get_metric_filename(Plugins::Memory, "used")?
+        );
+        assert!(super::get_metric_filename(Plugins::Memory, "firefox").is_err());
+
+        Ok(())
+    }
+}
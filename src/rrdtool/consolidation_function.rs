@@ -0,0 +1,70 @@
+use std::str::FromStr;
+
+/// rrdtool consolidation function, used e.g. to reduce data when
+/// there are more data points than pixels on a graph
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ConsolidationFunction {
+    Average,
+    Min,
+    Max,
+    Last,
+}
+
+impl ConsolidationFunction {
+    /// Returns name of consolidation function as used by rrdtool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::consolidation_function::ConsolidationFunction;
+    ///
+    /// assert_eq!("MAX", ConsolidationFunction::Max.as_str());
+    /// ```
+    ///
+    pub fn as_str(&self) -> &str {
+        match self {
+            ConsolidationFunction::Average => "AVERAGE",
+            ConsolidationFunction::Min => "MIN",
+            ConsolidationFunction::Max => "MAX",
+            ConsolidationFunction::Last => "LAST",
+        }
+    }
+}
+
+/// Returns [`ConsolidationFunction`] from str, case insensitive
+impl FromStr for ConsolidationFunction {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ConsolidationFunction, Self::Err> {
+        match input.to_ascii_uppercase().as_str() {
+            "AVERAGE" => Ok(ConsolidationFunction::Average),
+            "MIN" => Ok(ConsolidationFunction::Min),
+            "MAX" => Ok(ConsolidationFunction::Max),
+            "LAST" => Ok(ConsolidationFunction::Last),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn consolidation_function_string_conversion() {
+        for cf in &[
+            ConsolidationFunction::Average,
+            ConsolidationFunction::Min,
+            ConsolidationFunction::Max,
+            ConsolidationFunction::Last,
+        ] {
+            assert_eq!(Ok(*cf), ConsolidationFunction::from_str(cf.as_str()));
+        }
+
+        assert_eq!(
+            Ok(ConsolidationFunction::Max),
+            ConsolidationFunction::from_str("max")
+        );
+        assert!(ConsolidationFunction::from_str("median").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use super::common::{Plugins, Rrdtool, Target};
+
+use anyhow::{Context, Result};
+use log::trace;
+use std::fs::read_dir;
+
+/// collectd plugins recognized in input directory, which can't be drawn yet
+const UNSUPPORTED_PLUGINS: &[&str] = &["interface", "load", "swap"];
+
+/// Returns plugins which have data in input directory, probed by their
+/// characteristic directories, e.g. processes-* for processes
+///
+/// # Arguments
+/// * `rrdtool` - [`Rrdtool`] with local or remote input directory
+///
+pub fn detect_plugins(rrdtool: &Rrdtool) -> Result<Vec<Plugins>> {
+    let names = list_input_dir(rrdtool)?;
+
+    let plugins = [
+        Plugins::Processes,
+        Plugins::Memory,
+        Plugins::Cpu,
+        Plugins::Df,
+    ]
+    .iter()
+    .filter(|plugin| names.iter().any(|name| is_plugin_dir(**plugin, name)))
+    .copied()
+    .collect::<Vec<Plugins>>();
+
+    trace!("Detected plugins: {:?}", plugins);
+
+    Ok(plugins)
+}
+
+/// Returns names of collectd plugins which have data in input directory,
+/// but aren't supported by cgg, e.g. interface
+pub fn detect_unsupported_plugins(rrdtool: &Rrdtool) -> Result<Vec<&'static str>> {
+    let names = list_input_dir(rrdtool)?;
+
+    Ok(UNSUPPORTED_PLUGINS
+        .iter()
+        .filter(|plugin| {
+            names.iter().any(|name| {
+                // Plugins with instances have directories like interface-eth0
+                name == *plugin || name.starts_with(&(String::from(**plugin) + "-"))
+            })
+        })
+        .copied()
+        .collect())
+}
+
+/// Check whether directory in input directory holds data of given plugin
+fn is_plugin_dir(plugin: Plugins, name: &str) -> bool {
+    match plugin {
+        Plugins::Processes => name.starts_with("processes-"),
+        Plugins::Memory => name == "memory",
+        Plugins::Cpu => name
+            .strip_prefix("cpu-")
+            .and_then(|core| core.parse::<u32>().ok())
+            .is_some(),
+        Plugins::Df => name.starts_with("df-"),
+    }
+}
+
+/// Returns names of all entries of local or remote input directory
+fn list_input_dir(rrdtool: &Rrdtool) -> Result<Vec<String>> {
+    let input_dir = rrdtool.input_dir.as_str();
+
+    match rrdtool.target {
+        Target::Local => Ok(read_dir(input_dir)
+            .context(format!("Failed to read directory: {}", input_dir))?
+            .filter_map(|path| path.ok())
+            .filter_map(|path| path.file_name().to_str().map(String::from))
+            .collect()),
+        Target::Remote => rrdtool
+            .list_remote_dir(input_dir)
+            .context(format!("Failed to read remote directory {}", input_dir)),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::fs::create_dir;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detect_plugins() -> Result<()> {
+        let temp = TempDir::new().unwrap();
+
+        for dir in &[
+            "cpu-0",
+            "cpu-1",
+            "df-root",
+            "interface-eth0",
+            "swap",
+            "cpu-total",
+        ] {
+            create_dir(temp.path().join(dir))?;
+        }
+
+        let rrd = Rrdtool::new(temp.path())?;
+
+        assert_eq!(
+            vec![Plugins::Cpu, Plugins::Df],
+            super::detect_plugins(&rrd)?
+        );
+        assert_eq!(
+            vec!["interface", "swap"],
+            super::detect_unsupported_plugins(&rrd)?
+        );
+
+        let empty = TempDir::new().unwrap();
+        assert!(super::detect_plugins(&Rrdtool::new(empty.path())?)?.is_empty());
+
+        assert!(super::detect_plugins(&Rrdtool::new(&empty.path().join("missing"))?).is_err());
+
+        Ok(())
+    }
+}
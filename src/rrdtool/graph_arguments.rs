@@ -1,4 +1,7 @@
-use super::common::Target;
+use super::common::{Plugins, Target};
+use super::consolidation_function::ConsolidationFunction;
+use super::graph_element::GraphElement;
+use super::right_axis::RightAxis;
 
 use log::trace;
 
@@ -10,19 +13,141 @@ pub struct GraphArguments {
     /// First dimension splits it between files,
     /// Second dimension holds the arguments
     pub args: Vec<Vec<String>>,
+    /// Additional information about each graph, indexed the same as args
+    pub info: Vec<GraphInfo>,
+    /// Fill unknown values up to given number of seconds after last known value
+    pub nan_fill: Option<u64>,
+    /// Consolidation function of data read from rrd files
+    pub cf: ConsolidationFunction,
+    /// Data source read instead of [`GraphArguments::DEFAULT_DS`]
+    pub ds: Option<String>,
+    /// Function reducing data when there are more data points than pixels
+    pub reduce: Option<ConsolidationFunction>,
+    /// Number of legend entries in one line
+    pub legend_columns: Option<u32>,
+    /// Graph element overriding the one chosen by plugins
+    pub style: Option<GraphElement>,
+    /// Print maximum, average and last value of each element in legend
+    pub statistics: bool,
+    /// Draw MAX of each element as translucent area behind it, unless it's stacked
+    pub show_max: bool,
+    /// Draw moving average over given number of seconds of each element as thick
+    /// translucent line over it, unless it's stacked
+    pub trend: Option<u64>,
+    /// Export data with XPORT instead of drawing it
+    pub export: bool,
+    /// Draw elements of all plugins on one graph
+    pub combine: bool,
+    /// Host prefixed to legends and graph labels, when plugins of multiple hosts are drawn
+    pub host: Option<String>,
+    /// Number of elements drawn on the current graph before the current plugin,
+    /// which elements of the plugin don't stack on
+    stack_base: usize,
+    /// Right axis which elements of the current plugin are drawn against
+    pub right_axis: Option<RightAxis>,
+    /// Name and size in base units of the unit which values of the current plugin
+    /// are converted to, e.g. MiB and 1048576 for values in bytes
+    pub unit: Option<(String, u64)>,
+}
+
+/// Information about a graph, which is not passed directly to rrdtool
+#[derive(Debug, Default, Clone)]
+pub struct GraphInfo {
+    /// Appended to output filename instead of the graph number
+    pub label: Option<String>,
+    /// rrd files used by the graph
+    pub files: Vec<String>,
+    /// Options overriding common arguments for this graph only
+    pub options: Vec<String>,
+    /// Plugin which created the graph
+    pub plugin: Option<Plugins>,
 }
 
 impl GraphArguments {
+    /// Width of legend text when statistics are printed
+    pub const STATISTICS_LEGEND_WIDTH: usize = 20;
+
+    /// Data source of most collectd rrd files
+    pub const DEFAULT_DS: &'static str = "value";
+
     pub fn new(target: Target) -> GraphArguments {
         GraphArguments {
             target,
             args: Vec::new(),
+            info: Vec::new(),
+            nan_fill: None,
+            cf: ConsolidationFunction::Average,
+            ds: None,
+            reduce: None,
+            legend_columns: None,
+            style: None,
+            statistics: false,
+            show_max: false,
+            trend: None,
+            export: false,
+            combine: false,
+            host: None,
+            stack_base: 0,
+            right_axis: None,
+            unit: None,
         }
     }
 
     /// Create new output file for following commands
     pub fn new_graph(&mut self) {
-        self.args.push(Vec::new())
+        self.args.push(Vec::new());
+        self.info.push(GraphInfo::default());
+        self.stack_base = 0;
+    }
+
+    /// Create new output file for following commands, with label appended to its name
+    pub fn new_labeled_graph(&mut self, label: &str) {
+        let label = match &self.host {
+            Some(host) => format!("{}_{}", host, label),
+            None => String::from(label),
+        };
+
+        self.new_graph();
+        self.info.last_mut().unwrap().label = Some(label);
+    }
+
+    /// Create new output file for elements of a plugin, unless plugins are combined
+    /// and the graph was already created by another plugin
+    pub fn new_plugin_graph(&mut self) {
+        match !self.combine || self.args.is_empty() {
+            true => self.new_graph(),
+            false => self.stack_base = self.drawn(),
+        }
+    }
+
+    /// Create new output file for elements of a plugin, with label appended to its name,
+    /// unless plugins are combined and the graph was already created by another plugin
+    pub fn new_labeled_plugin_graph(&mut self, label: &str) {
+        match !self.combine || self.args.is_empty() {
+            true => self.new_labeled_graph(label),
+            false => self.stack_base = self.drawn(),
+        }
+    }
+
+    /// Returns index of the first color which should be used by a plugin,
+    /// so combined plugins don't repeat colors of elements already drawn
+    pub fn first_color_index(&self) -> usize {
+        match self.combine {
+            true => self.drawn(),
+            false => 0,
+        }
+    }
+
+    /// Returns number of elements with legend already drawn on the current graph
+    fn drawn(&self) -> usize {
+        match self.args.last() {
+            Some(args) => args
+                .iter()
+                .filter(|arg| arg.starts_with("LINE") || arg.starts_with("AREA"))
+                .filter(|arg| arg.contains(":\""))
+                .count(),
+            None => 0,
+        }
     }
 
     /// Add new graph argument
@@ -31,58 +156,476 @@ impl GraphArguments {
     ///
     /// * `legend_name` - name to be shown on graph legend
     /// * `color` - color of line, e.g. #ffaabb
-    /// * `thickness` - line thickness
+    /// * `element` - how to draw data, e.g. line of given thickness
     /// * `path` - full path to rrd file
     ///
-    pub fn push(&mut self, legend_name: &str, color: &str, thickness: u32, path: &str) {
-        let legend_first_word = legend_name.split_whitespace().next().unwrap();
+    /// Returns name of variable holding the drawn data, see [`GraphArguments::push_sum`]
+    pub fn push(
+        &mut self,
+        legend_name: &str,
+        color: &str,
+        element: GraphElement,
+        path: &str,
+    ) -> String {
+        self.push_with_ds(legend_name, color, element, path, Self::DEFAULT_DS, false)
+    }
+
+    /// Add new graph argument reading given data source of rrd file
+    ///
+    /// # Arguments
+    ///
+    /// * `legend_name` - name to be shown on graph legend
+    /// * `color` - color of line, e.g. #ffaabb
+    /// * `element` - how to draw data, e.g. line of given thickness
+    /// * `path` - full path to rrd file
+    /// * `ds` - name of data source in rrd file, e.g. value
+    /// * `stack` - stack element on the previous one, ignored for the first element in graph
+    ///
+    /// Returns name of variable holding the drawn data, see [`GraphArguments::push_sum`]
+    pub fn push_with_ds(
+        &mut self,
+        legend_name: &str,
+        color: &str,
+        element: GraphElement,
+        path: &str,
+        ds: &str,
+        stack: bool,
+    ) -> String {
+        self.push_sum(legend_name, color, element, &[path], ds, stack)
+    }
+
+    /// Add new graph argument drawing sum of given data source of multiple rrd files,
+    /// e.g. of all instances of a process. Each file is read with a hidden DEF,
+    /// summed with CDEF, which is drawn instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `legend_name` - name to be shown on graph legend
+    /// * `color` - color of line, e.g. #ffaabb
+    /// * `element` - how to draw data, e.g. line of given thickness
+    /// * `paths` - full paths to rrd files, a single file is drawn directly
+    /// * `ds` - name of data source in rrd files, e.g. value
+    /// * `stack` - stack element on the previous one, ignored for the first element in graph
+    ///
+    /// Returns name of variable holding the drawn data, before it's scaled to the right axis,
+    /// which may be used in expression of [`GraphArguments::push_cdef`]
+    pub fn push_sum(
+        &mut self,
+        legend_name: &str,
+        color: &str,
+        element: GraphElement,
+        paths: &[&str],
+        ds: &str,
+        stack: bool,
+    ) -> String {
+        self.push_sum_ds(legend_name, color, element, paths, &[ds], stack)
+    }
+
+    /// Add new graph argument drawing sum of given data sources of multiple rrd files,
+    /// e.g. user and system CPU time of all instances of a process
+    ///
+    /// # Arguments
+    ///
+    /// * `legend_name` - name to be shown on graph legend
+    /// * `color` - color of line, e.g. #ffaabb
+    /// * `element` - how to draw data, e.g. line of given thickness
+    /// * `paths` - full paths to rrd files, a single file is drawn directly
+    /// * `data_sources` - names of data sources summed in each rrd file, e.g. user and syst
+    /// * `stack` - stack element on the previous one, ignored for the first element in graph
+    ///
+    /// Returns name of variable holding the drawn data, see [`GraphArguments::push_sum`]
+    pub fn push_sum_ds(
+        &mut self,
+        legend_name: &str,
+        color: &str,
+        element: GraphElement,
+        paths: &[&str],
+        data_sources: &[&str],
+        stack: bool,
+    ) -> String {
+        if self.args.last_mut().is_none() {
+            self.new_graph();
+        }
+
+        let unique_name = self.build_unique_name(legend_name);
+
+        let sources = paths
+            .iter()
+            .flat_map(|path| data_sources.iter().map(move |ds| (*path, *ds)))
+            .collect::<Vec<(&str, &str)>>();
+
+        let defs = match sources.as_slice() {
+            [(path, ds)] => vec![self.build_graph_def(&unique_name, path, ds)],
+            _ => self.build_graph_sum(&unique_name, &sources, self.cf),
+        };
+
+        let (mut cdefs, vname) = match self.nan_fill {
+            Some(seconds) => self.build_graph_nan_fill(&unique_name, seconds),
+            None => (Vec::new(), String::from(&unique_name)),
+        };
 
-        let def = self.build_graph_def(legend_first_word, path);
-        let line = self.build_graph_line(legend_first_word, legend_name, color, thickness);
+        let (unit_cdefs, vname) = self.build_graph_unit(&vname);
+        cdefs.extend(unit_cdefs);
 
-        if self.args.last_mut() == None {
-            self.args.push(Vec::new());
+        let drawn = self.drawn();
+        let legend = self.build_legend(legend_name, drawn);
+
+        let element = match self.export {
+            true => GraphElement::Xport,
+            false => self.style.unwrap_or(element),
+        };
+        let (axis_cdefs, drawn_vname) = self.build_graph_right_axis(&vname);
+        let mut line = self.build_graph_line(&drawn_vname, &legend, color, element);
+
+        if (stack || element == GraphElement::Stack)
+            && element != GraphElement::Xport
+            && drawn > self.stack_base
+        {
+            line.push_str(":STACK");
+        }
+
+        if let GraphElement::DashedLine(_) = element {
+            line.push_str(":dashes");
         }
 
+        // Area behind stacked element would break the stack
+        let stacked = stack || element == GraphElement::Stack;
+        let band = match self.show_max && !self.export && !stacked {
+            true => self.build_graph_max_band(&unique_name, &sources, color),
+            false => Vec::new(),
+        };
+        let trend = match self.trend {
+            Some(window) if !self.export && !stacked => {
+                self.build_graph_trend(&vname, window, color, element)
+            }
+            _ => Vec::new(),
+        };
+
         trace!(
-            "Pushed new GraphArguments[{}][{}]:\n{:?}\n{:?}",
+            "Pushed new GraphArguments[{}][{}]:\n{:?}\n{:?}\n{:?}",
             self.args.len(),
             self.args.last().unwrap().len(),
-            def,
+            defs,
+            cdefs,
             line
         );
 
-        self.args.last_mut().unwrap().push(def);
+        self.info
+            .last_mut()
+            .unwrap()
+            .files
+            .extend(paths.iter().map(|path| String::from(*path)));
+        self.args.last_mut().unwrap().extend(defs);
+        self.args.last_mut().unwrap().extend(cdefs);
+        self.args.last_mut().unwrap().extend(axis_cdefs);
+        self.args.last_mut().unwrap().extend(band);
         self.args.last_mut().unwrap().push(line);
+        self.args.last_mut().unwrap().extend(trend);
+
+        if self.statistics && !self.export {
+            let gprints = self.build_graph_statistics(&vname);
+            self.args.last_mut().unwrap().extend(gprints);
+        }
+
+        vname
+    }
+
+    /// Add new graph argument drawing result of CDEF expression over variables already
+    /// defined in the current graph, e.g. sum of all memory types. The element is neither
+    /// stacked nor changed by style of the graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `legend_name` - name to be shown on graph legend
+    /// * `color` - color of line, e.g. #ffaabb
+    /// * `element` - how to draw data, e.g. line of given thickness
+    /// * `expression` - RPN expression, e.g. used,free,+
+    ///
+    /// Returns name of variable holding result of the expression
+    pub fn push_cdef(
+        &mut self,
+        legend_name: &str,
+        color: &str,
+        element: GraphElement,
+        expression: &str,
+    ) -> String {
+        if self.args.last_mut().is_none() {
+            self.new_graph();
+        }
+
+        let unique_name = self.build_unique_name(legend_name);
+
+        let drawn = self.drawn();
+        let legend = self.build_legend(legend_name, drawn);
+
+        let element = match self.export {
+            true => GraphElement::Xport,
+            false => element,
+        };
+        let (axis_cdefs, drawn_vname) = self.build_graph_right_axis(&unique_name);
+        let line = self.build_graph_line(&drawn_vname, &legend, color, element);
+
+        let args = self.args.last_mut().unwrap();
+        args.push(format!("CDEF:{}={}", unique_name, expression));
+        args.extend(axis_cdefs);
+        args.push(line);
+
+        if self.statistics && !self.export {
+            let gprints = self.build_graph_statistics(&unique_name);
+            self.args.last_mut().unwrap().extend(gprints);
+        }
+
+        unique_name
     }
 
-    fn build_graph_def(&mut self, unique_name: &str, path: &str) -> String {
+    /// Build legend of element, prefixed with host if given. Legend line is broken after
+    /// each `legend_columns` entries, with statistics each element has its own line,
+    /// padded so values are aligned.
+    fn build_legend(&self, legend_name: &str, drawn: usize) -> String {
+        let legend_name = match &self.host {
+            Some(host) => format!("{}: {}", host, legend_name),
+            None => String::from(legend_name),
+        };
+
+        match (self.statistics, self.legend_columns) {
+            _ if self.export => legend_name,
+            (true, _) => format!(
+                "{:<width$}",
+                legend_name,
+                width = GraphArguments::STATISTICS_LEGEND_WIDTH
+            ),
+            (false, Some(columns)) if (drawn + 1) % columns as usize == 0 => legend_name + "\\l",
+            (false, _) => legend_name,
+        }
+    }
+
+    /// Build name of variable from the first word of legend, sanitized with [`sanitize_vname`].
+    /// Index is appended if the name is already defined in the current graph,
+    /// e.g. rust_2 for "rust analyzer" drawn after "rust language server".
+    fn build_unique_name(&self, legend_name: &str) -> String {
+        let name = sanitize_vname(legend_name.split_whitespace().next().unwrap_or_default());
+
+        let is_defined = |candidate: &str| {
+            let def = String::from("DEF:") + candidate + "=";
+            let cdef = String::from("CDEF:") + candidate + "=";
+            self.args
+                .last()
+                .unwrap()
+                .iter()
+                .any(|arg| arg.starts_with(&def) || arg.starts_with(&cdef))
+        };
+
+        let mut unique_name = name.clone();
+        let mut index = 1;
+
+        while is_defined(&unique_name) {
+            index += 1;
+            unique_name = format!("{}_{}", name, index);
+        }
+
+        unique_name
+    }
+
+    /// Returns data source to read, the default one is replaced with data source given by user,
+    /// other data sources chosen by plugins are kept
+    pub fn get_ds<'a>(&'a self, ds: &'a str) -> &'a str {
+        match &self.ds {
+            Some(user_ds) if ds == Self::DEFAULT_DS => user_ds,
+            _ => ds,
+        }
+    }
+
+    fn build_graph_def(&mut self, unique_name: &str, path: &str, ds: &str) -> String {
+        self.build_graph_def_with_cf(unique_name, path, ds, self.cf)
+    }
+
+    fn build_graph_def_with_cf(
+        &mut self,
+        unique_name: &str,
+        path: &str,
+        ds: &str,
+        cf: ConsolidationFunction,
+    ) -> String {
         String::from("DEF:")
-            + unique_name
+            + &sanitize_vname(unique_name)
             + "="
-            + match self.target {
-                Target::Local => "",
-                Target::Remote => "\"",
-            }
             + path
-            + match self.target {
-                Target::Local => "",
-                Target::Remote => "\"",
+            + ":"
+            + self.get_ds(ds)
+            + ":"
+            + cf.as_str()
+            + &match self.reduce {
+                Some(reduce) => String::from(":reduce=") + reduce.as_str(),
+                None => String::new(),
             }
-            + ":value:AVERAGE"
     }
 
+    /// Build hidden DEF of each file and data source, and CDEF named `unique_name` summing them.
+    /// Unknown values are treated as zero, unless all of them are unknown.
+    fn build_graph_sum(
+        &mut self,
+        unique_name: &str,
+        sources: &[(&str, &str)],
+        cf: ConsolidationFunction,
+    ) -> Vec<String> {
+        let parts = (1..=sources.len())
+            .map(|index| format!("{}_part{}", unique_name, index))
+            .collect::<Vec<String>>();
+
+        let mut args = parts
+            .iter()
+            .zip(sources)
+            .map(|(part, (path, ds))| self.build_graph_def_with_cf(part, path, ds, cf))
+            .collect::<Vec<String>>();
+
+        let sum = parts[1..]
+            .iter()
+            .fold(String::from(&parts[0]), |sum, part| {
+                sum + "," + part + ",ADDNAN"
+            });
+
+        args.push(format!("CDEF:{}={}", unique_name, sum));
+
+        args
+    }
+
+    /// Build CDEFs filling unknown values with the last known one,
+    /// but only up to `seconds` after it was seen. Longer gaps stay unknown.
+    ///
+    /// Returns CDEFs and the name of variable which should be drawn.
+    fn build_graph_nan_fill(&mut self, unique_name: &str, seconds: u64) -> (Vec<String>, String) {
+        let seen = String::from(unique_name) + "_seen";
+        let held = String::from(unique_name) + "_held";
+        let filled = String::from(unique_name) + "_filled";
+
+        let cdefs = vec![
+            format!("CDEF:{}={},UN,PREV,TIME,IF", seen, unique_name),
+            format!("CDEF:{}={},UN,PREV,{},IF", held, unique_name, unique_name),
+            format!(
+                "CDEF:{}={},UN,TIME,{},-,{},GT,UNKN,{},IF,{},IF",
+                filled, unique_name, seen, seconds, held, unique_name
+            ),
+        ];
+
+        (cdefs, filled)
+    }
+
+    /// Build CDEF converting variable to the unit of the current plugin, e.g.
+    /// CDEF:used_mib=used,1048576,/
+    ///
+    /// Returns CDEFs and the name of variable which should be drawn.
+    fn build_graph_unit(&self, vname: &str) -> (Vec<String>, String) {
+        match &self.unit {
+            Some((unit, divisor)) => {
+                let converted = String::from(vname) + "_" + &unit.to_lowercase();
+                (
+                    vec![format!("CDEF:{}={},{},/", converted, vname, divisor)],
+                    converted,
+                )
+            }
+            None => (Vec::new(), String::from(vname)),
+        }
+    }
+
+    /// Build CDEF scaling variable drawn against the right axis, exported data is not scaled.
+    ///
+    /// Returns CDEFs and the name of variable which should be drawn.
+    fn build_graph_right_axis(&self, vname: &str) -> (Vec<String>, String) {
+        match self.right_axis {
+            Some(axis) if !self.export => {
+                let scaled = String::from(vname) + "_right";
+                (vec![axis.build_cdef(&scaled, vname)], scaled)
+            }
+            _ => (Vec::new(), String::from(vname)),
+        }
+    }
+
+    /// Build DEF reading MAX of the data and AREA without legend drawing it
+    /// with translucent color of the element, e.g. #ff000040 for #ff0000
+    fn build_graph_max_band(
+        &mut self,
+        unique_name: &str,
+        sources: &[(&str, &str)],
+        color: &str,
+    ) -> Vec<String> {
+        let max_name = String::from(unique_name) + "_max";
+
+        let mut args = match sources {
+            [(path, ds)] => {
+                vec![self.build_graph_def_with_cf(&max_name, path, ds, ConsolidationFunction::Max)]
+            }
+            _ => self.build_graph_sum(&max_name, sources, ConsolidationFunction::Max),
+        };
+
+        let (unit_cdefs, max_name) = self.build_graph_unit(&max_name);
+        args.extend(unit_cdefs);
+
+        let (axis_cdefs, max_name) = self.build_graph_right_axis(&max_name);
+        args.extend(axis_cdefs);
+
+        let color = color.get(..7).unwrap_or(color);
+        args.push(format!("AREA:{}{}40", max_name, color));
+
+        args
+    }
+
+    /// Build CDEF with moving average of variable over `window` seconds and line without legend
+    /// drawing it thicker than the element, with translucent color of the element
+    fn build_graph_trend(
+        &self,
+        vname: &str,
+        window: u64,
+        color: &str,
+        element: GraphElement,
+    ) -> Vec<String> {
+        let trend = String::from(vname) + "_trend";
+        let mut args = vec![format!("CDEF:{}={},{},TREND", trend, vname, window)];
+
+        let (axis_cdefs, trend) = self.build_graph_right_axis(&trend);
+        args.extend(axis_cdefs);
+
+        let thickness = match element {
+            GraphElement::Line(thickness) | GraphElement::DashedLine(thickness) => thickness + 2,
+            _ => 3,
+        };
+
+        let color = color.get(..7).unwrap_or(color);
+        args.push(format!("LINE{}:{}{}80", thickness, trend, color));
+
+        args
+    }
+
+    /// Build GPRINTs with maximum, average and last value of variable, ending legend line
+    fn build_graph_statistics(&mut self, unique_name: &str) -> Vec<String> {
+        vec![
+            format!("GPRINT:{}:MAX:\"max %6.1lf%S\"", unique_name),
+            format!("GPRINT:{}:AVERAGE:\"avg %6.1lf%S\"", unique_name),
+            format!("GPRINT:{}:LAST:\"last %6.1lf%S\\l\"", unique_name),
+        ]
+    }
+
+    /// Build element drawing variable, color is #rrggbb or #rrggbbaa with transparency,
+    /// e.g. #ff000080 draws half-transparent red area
     fn build_graph_line(
         &mut self,
         unique_name: &str,
         legend_name: &str,
         color: &str,
-        thickness: u32,
+        element: GraphElement,
     ) -> String {
-        String::from("LINE")
-            + &thickness.to_string()
+        // Exported data has no color, legend names the column
+        if element == GraphElement::Xport {
+            return element.to_rrdtool()
+                + ":"
+                + &sanitize_vname(unique_name)
+                + ":\""
+                + legend_name
+                + "\"";
+        }
+
+        element.to_rrdtool()
             + ":"
-            + unique_name
+            + &sanitize_vname(unique_name)
             + color
             + ":\""
             + legend_name
@@ -90,6 +633,55 @@ impl GraphArguments {
     }
 }
 
+/// Validate color accepted by rrdtool, #rrggbb or #rrggbbaa with transparency
+///
+/// # Examples
+///
+/// ```
+/// use cgg::rrdtool::graph_arguments::validate_color;
+///
+/// assert!(validate_color("#ff000080").is_ok());
+/// assert!(validate_color("red").is_err());
+/// ```
+///
+pub fn validate_color(color: &str) -> anyhow::Result<()> {
+    let valid = color.starts_with('#')
+        && (color.len() == 7 || color.len() == 9)
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+    match valid {
+        true => Ok(()),
+        false => anyhow::bail!("Expected #rrggbb or #rrggbbaa color, got {}", color),
+    }
+}
+
+/// Returns name usable as rrdtool variable, with all characters other than
+/// [A-Za-z0-9_] replaced by underscore, prefixed if it starts with a digit
+///
+/// # Examples
+///
+/// ```
+/// use cgg::rrdtool::graph_arguments::sanitize_vname;
+///
+/// assert_eq!("node_js", sanitize_vname("node.js"));
+/// ```
+///
+pub fn sanitize_vname(name: &str) -> String {
+    let vname = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+
+    match vname.chars().next() {
+        None => String::from("value"),
+        Some(c) if c.is_ascii_digit() => String::from("v") + &vname,
+        Some(_) => vname,
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -100,14 +692,18 @@ pub mod tests {
         let mut graph_arguments_local = super::GraphArguments::new(Target::Local);
         let mut graph_arguments_remote = super::GraphArguments::new(Target::Remote);
 
-        let res_local =
-            graph_arguments_local.build_graph_line("unique_name", "legend name", "#abcdef", 3);
+        let res_local = graph_arguments_local.build_graph_line(
+            "unique_name",
+            "legend name",
+            "#abcdef",
+            GraphElement::Line(3),
+        );
 
         let res_remote = graph_arguments_remote.build_graph_line(
             "other_unique_name",
             "remote legend name",
             "#fedcba",
-            5,
+            GraphElement::Line(5),
         );
 
         assert_eq!("LINE3:unique_name#abcdef:\"legend name\"", res_local);
@@ -116,6 +712,35 @@ pub mod tests {
             res_remote
         );
 
+        assert_eq!(
+            "AREA:unique_name#abcdef80:\"legend name\"",
+            graph_arguments_local.build_graph_line(
+                "unique_name",
+                "legend name",
+                "#abcdef80",
+                GraphElement::Area,
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_color() -> Result<()> {
+        super::validate_color("#abcdef")?;
+        super::validate_color("#ABCDEF80")?;
+
+        for color in &[
+            "abcdef",
+            "#abcde",
+            "#abcdef8",
+            "#abcdef801",
+            "#ghijkl",
+            "#ab€d",
+        ] {
+            assert!(super::validate_color(color).is_err());
+        }
+
         Ok(())
     }
 
@@ -124,10 +749,16 @@ pub mod tests {
         let mut graph_arguments_local = super::GraphArguments::new(Target::Local);
         let mut graph_arguments_remote = super::GraphArguments::new(Target::Remote);
 
-        let res_local =
-            graph_arguments_local.build_graph_def("local_unique_name", "/some/local/path.rrd");
-        let res_remote =
-            graph_arguments_remote.build_graph_def("remote_unique_name", "/some/remote/path.rrd");
+        let res_local = graph_arguments_local.build_graph_def(
+            "local_unique_name",
+            "/some/local/path.rrd",
+            "value",
+        );
+        let res_remote = graph_arguments_remote.build_graph_def(
+            "remote_unique_name",
+            "/some/remote/path.rrd",
+            "value",
+        );
 
         assert_eq!(
             "DEF:local_unique_name=/some/local/path.rrd:value:AVERAGE",
@@ -135,20 +766,219 @@ pub mod tests {
         );
 
         assert_eq!(
-            "DEF:remote_unique_name=\"/some/remote/path.rrd\":value:AVERAGE",
+            "DEF:remote_unique_name=/some/remote/path.rrd:value:AVERAGE",
             res_remote
         );
 
         Ok(())
     }
 
+    #[test]
+    fn build_graph_def_ds() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+
+        graph_arguments.new_graph();
+        graph_arguments.push(
+            "firefox",
+            "#00ff00",
+            GraphElement::Line(3),
+            "/some/path/ps_rss.rrd",
+        );
+        assert_eq!(
+            "DEF:firefox=/some/path/ps_rss.rrd:value:AVERAGE",
+            graph_arguments.args[0][0]
+        );
+
+        graph_arguments.ds = Some(String::from("gauge"));
+        assert_eq!(
+            "DEF:firefox=/some/path/ps_rss.rrd:gauge:AVERAGE",
+            graph_arguments.build_graph_def("firefox", "/some/path/ps_rss.rrd", "value")
+        );
+
+        // Data sources chosen by plugins are kept
+        assert_eq!(
+            "DEF:firefox=/some/path/ps_count.rrd:threads:AVERAGE",
+            graph_arguments.build_graph_def("firefox", "/some/path/ps_count.rrd", "threads")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn build_graph_def_cf() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+
+        for (cf, expected) in &[
+            (ConsolidationFunction::Average, "AVERAGE"),
+            (ConsolidationFunction::Min, "MIN"),
+            (ConsolidationFunction::Max, "MAX"),
+            (ConsolidationFunction::Last, "LAST"),
+        ] {
+            graph_arguments.cf = *cf;
+
+            assert_eq!(
+                format!("DEF:firefox=/some/local/path.rrd:value:{}", expected),
+                graph_arguments.build_graph_def("firefox", "/some/local/path.rrd", "value")
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_show_max() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.show_max = true;
+
+        graph_arguments.push(
+            "firefox",
+            "#ff0000",
+            GraphElement::Line(3),
+            "/some/path/ps_rss.rrd",
+        );
+        graph_arguments.push_with_ds(
+            "chrome",
+            "#00ff00",
+            GraphElement::Stack,
+            "/some/path/ps_count.rrd",
+            "processes",
+            true,
+        );
+
+        assert_eq!(
+            vec![
+                "DEF:firefox=/some/path/ps_rss.rrd:value:AVERAGE",
+                "DEF:firefox_max=/some/path/ps_rss.rrd:value:MAX",
+                "AREA:firefox_max#ff000040",
+                "LINE3:firefox#ff0000:\"firefox\"",
+                "DEF:chrome=/some/path/ps_count.rrd:processes:AVERAGE",
+                "AREA:chrome#00ff00:\"chrome\":STACK",
+            ],
+            graph_arguments.args[0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_trend() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.trend = Some(600);
+        graph_arguments.show_max = true;
+
+        graph_arguments.push(
+            "firefox",
+            "#ff0000",
+            GraphElement::Line(3),
+            "/some/path/ps_rss.rrd",
+        );
+        graph_arguments.push(
+            "chrome",
+            "#00ff00",
+            GraphElement::Area,
+            "/some/path/chrome.rrd",
+        );
+        graph_arguments.push(
+            "used",
+            "#0000ff",
+            GraphElement::Stack,
+            "/some/path/memory-used.rrd",
+        );
+
+        // Trend is drawn over the element, maximum behind it
+        assert_eq!(
+            vec![
+                "DEF:firefox=/some/path/ps_rss.rrd:value:AVERAGE",
+                "DEF:firefox_max=/some/path/ps_rss.rrd:value:MAX",
+                "AREA:firefox_max#ff000040",
+                "LINE3:firefox#ff0000:\"firefox\"",
+                "CDEF:firefox_trend=firefox,600,TREND",
+                "LINE5:firefox_trend#ff000080",
+                "DEF:chrome=/some/path/chrome.rrd:value:AVERAGE",
+                "DEF:chrome_max=/some/path/chrome.rrd:value:MAX",
+                "AREA:chrome_max#00ff0040",
+                "AREA:chrome#00ff00:\"chrome\"",
+                "CDEF:chrome_trend=chrome,600,TREND",
+                "LINE3:chrome_trend#00ff0080",
+                "DEF:used=/some/path/memory-used.rrd:value:AVERAGE",
+                "AREA:used#0000ff:\"used\":STACK",
+            ],
+            graph_arguments.args[0]
+        );
+
+        // Trend isn't exported
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.trend = Some(600);
+        graph_arguments.export = true;
+        graph_arguments.push(
+            "firefox",
+            "#ff0000",
+            GraphElement::Line(3),
+            "/some/path/ps_rss.rrd",
+        );
+
+        assert!(!graph_arguments.args[0]
+            .iter()
+            .any(|arg| arg.contains("TREND")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_combine() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.combine = true;
+
+        graph_arguments.new_plugin_graph();
+        assert_eq!(0, graph_arguments.first_color_index());
+        graph_arguments.push("used", "#ff0000", GraphElement::Stack, "/memory-used.rrd");
+
+        graph_arguments.new_labeled_plugin_graph("cpu");
+        assert_eq!(1, graph_arguments.first_color_index());
+        graph_arguments.push("user", "#00ff00", GraphElement::Line(3), "/cpu-user.rrd");
+
+        assert_eq!(1, graph_arguments.args.len());
+        assert_eq!(None, graph_arguments.info[0].label);
+        assert_eq!(4, graph_arguments.args[0].len());
+
+        graph_arguments.combine = false;
+        graph_arguments.new_plugin_graph();
+        assert_eq!(0, graph_arguments.first_color_index());
+        assert_eq!(2, graph_arguments.args.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn build_graph_def_reduce() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.reduce = Some(ConsolidationFunction::Max);
+
+        assert_eq!(
+            "DEF:firefox=/some/local/path.rrd:value:AVERAGE:reduce=MAX",
+            graph_arguments.build_graph_def("firefox", "/some/local/path.rrd", "value")
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn graph_arguments_push() -> Result<()> {
         let mut graph_arguments_local = super::GraphArguments::new(Target::Local);
         let mut graph_arguments_remote = super::GraphArguments::new(Target::Remote);
 
-        graph_arguments_local.push("unique legend name", "#ffaabb", 3, "/some/local/path.rrd");
-        graph_arguments_remote.push("remote legend name", "#bbaaff", 5, "/some/remote/path.rrd");
+        graph_arguments_local.push(
+            "unique legend name",
+            "#ffaabb",
+            GraphElement::Line(3),
+            "/some/local/path.rrd",
+        );
+        graph_arguments_remote.push(
+            "remote legend name",
+            "#bbaaff",
+            GraphElement::Line(5),
+            "/some/remote/path.rrd",
+        );
 
         assert_eq!(1, graph_arguments_local.args.len());
         assert_eq!(2, graph_arguments_local.args[0].len());
@@ -158,4 +988,320 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn sanitize_vname() -> Result<()> {
+        assert_eq!("firefox", super::sanitize_vname("firefox"));
+        assert_eq!("node_js", super::sanitize_vname("node.js"));
+        assert_eq!("my_daemon", super::sanitize_vname("my-daemon"));
+        assert_eq!("gr__e", super::sanitize_vname("größe"));
+        assert_eq!("___", super::sanitize_vname("日本語"));
+        assert_eq!("v7zip", super::sanitize_vname("7zip"));
+        assert_eq!("value", super::sanitize_vname(""));
+
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.push(
+            "my-daemon.sh",
+            "#ffaabb",
+            GraphElement::Line(3),
+            "/some/path.rrd",
+        );
+
+        assert_eq!(
+            "DEF:my_daemon_sh=/some/path.rrd:value:AVERAGE",
+            graph_arguments.args[0][0]
+        );
+        assert_eq!(
+            "LINE3:my_daemon_sh#ffaabb:\"my-daemon.sh\"",
+            graph_arguments.args[0][1]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_same_first_word() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+
+        for process in &["rust language server", "rust analyzer", "rust"] {
+            graph_arguments.push(process, "#ffaabb", GraphElement::Line(3), "/some/path.rrd");
+        }
+
+        let args = &graph_arguments.args[0];
+
+        assert_eq!("DEF:rust=/some/path.rrd:value:AVERAGE", args[0]);
+        assert_eq!("LINE3:rust#ffaabb:\"rust language server\"", args[1]);
+        assert_eq!("DEF:rust_2=/some/path.rrd:value:AVERAGE", args[2]);
+        assert_eq!("LINE3:rust_2#ffaabb:\"rust analyzer\"", args[3]);
+        assert_eq!("DEF:rust_3=/some/path.rrd:value:AVERAGE", args[4]);
+
+        // Names are unique within a graph only
+        graph_arguments.new_graph();
+        graph_arguments.push("rust", "#ffaabb", GraphElement::Line(3), "/some/path.rrd");
+
+        assert_eq!(
+            "DEF:rust=/some/path.rrd:value:AVERAGE",
+            graph_arguments.args[1][0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_stack() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+
+        for memory in &["used", "cached", "free"] {
+            graph_arguments.push(memory, "#ffaabb", GraphElement::Stack, "/some/path.rrd");
+        }
+
+        assert_eq!("AREA:used#ffaabb:\"used\"", graph_arguments.args[0][1]);
+        assert_eq!(
+            "AREA:cached#ffaabb:\"cached\":STACK",
+            graph_arguments.args[0][3]
+        );
+        assert_eq!(
+            "AREA:free#ffaabb:\"free\":STACK",
+            graph_arguments.args[0][5]
+        );
+
+        graph_arguments.new_graph();
+        graph_arguments.style = Some(GraphElement::Area);
+        graph_arguments.push(
+            "firefox",
+            "#ffaabb",
+            GraphElement::Line(3),
+            "/some/path.rrd",
+        );
+
+        assert_eq!(
+            "AREA:firefox#ffaabb:\"firefox\"",
+            graph_arguments.args[1][1]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_export() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.export = true;
+        graph_arguments.statistics = true;
+        graph_arguments.style = Some(GraphElement::Area);
+
+        graph_arguments.push("used", "#ffaabb", GraphElement::Stack, "/some/path.rrd");
+        graph_arguments.push(
+            "free memory",
+            "#ffaabb",
+            GraphElement::Stack,
+            "/some/path.rrd",
+        );
+
+        assert_eq!(
+            vec![
+                "DEF:used=/some/path.rrd:value:AVERAGE",
+                "XPORT:used:\"used\"",
+                "DEF:free=/some/path.rrd:value:AVERAGE",
+                "XPORT:free:\"free memory\"",
+            ],
+            graph_arguments.args[0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_sum() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+
+        graph_arguments.push_sum(
+            "chrome",
+            "#ffaabb",
+            GraphElement::Line(3),
+            &[
+                "/chrome/ps_rss.rrd",
+                "/chrome.1/ps_rss.rrd",
+                "/chrome.2/ps_rss.rrd",
+            ],
+            "value",
+            false,
+        );
+        graph_arguments.push(
+            "chrome",
+            "#ffaabb",
+            GraphElement::Line(3),
+            "/chrome/ps_rss.rrd",
+        );
+
+        assert_eq!(
+            vec![
+                "DEF:chrome_part1=/chrome/ps_rss.rrd:value:AVERAGE",
+                "DEF:chrome_part2=/chrome.1/ps_rss.rrd:value:AVERAGE",
+                "DEF:chrome_part3=/chrome.2/ps_rss.rrd:value:AVERAGE",
+                "CDEF:chrome=chrome_part1,chrome_part2,ADDNAN,chrome_part3,ADDNAN",
+                "LINE3:chrome#ffaabb:\"chrome\"",
+                "DEF:chrome_2=/chrome/ps_rss.rrd:value:AVERAGE",
+                "LINE3:chrome_2#ffaabb:\"chrome\"",
+            ],
+            graph_arguments.args[0]
+        );
+        assert_eq!(4, graph_arguments.info[0].files.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_sum_ds() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.show_max = true;
+
+        graph_arguments.push_sum_ds(
+            "chrome",
+            "#ffaabb",
+            GraphElement::Line(3),
+            &["/chrome/ps_cputime.rrd", "/chrome.1/ps_cputime.rrd"],
+            &["user", "syst"],
+            false,
+        );
+
+        assert_eq!(
+            vec![
+                "DEF:chrome_part1=/chrome/ps_cputime.rrd:user:AVERAGE",
+                "DEF:chrome_part2=/chrome/ps_cputime.rrd:syst:AVERAGE",
+                "DEF:chrome_part3=/chrome.1/ps_cputime.rrd:user:AVERAGE",
+                "DEF:chrome_part4=/chrome.1/ps_cputime.rrd:syst:AVERAGE",
+                "CDEF:chrome=chrome_part1,chrome_part2,ADDNAN,chrome_part3,ADDNAN,chrome_part4,ADDNAN",
+            ],
+            graph_arguments.args[0][..5]
+        );
+        assert!(graph_arguments.args[0].contains(&String::from(
+            "DEF:chrome_max_part4=/chrome.1/ps_cputime.rrd:syst:MAX"
+        )));
+        assert_eq!(2, graph_arguments.info[0].files.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_dashed_line() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+
+        graph_arguments.push_with_ds(
+            "firefox",
+            "#ffaabb",
+            GraphElement::DashedLine(3),
+            "/some/path.rrd",
+            "value",
+            true,
+        );
+        graph_arguments.push_with_ds(
+            "chrome",
+            "#ffaabb",
+            GraphElement::DashedLine(3),
+            "/some/path.rrd",
+            "value",
+            true,
+        );
+
+        assert_eq!(
+            "LINE3:firefox#ffaabb:\"firefox\":dashes",
+            graph_arguments.args[0][1]
+        );
+        assert_eq!(
+            "LINE3:chrome#ffaabb:\"chrome\":STACK:dashes",
+            graph_arguments.args[0][3]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_legend_columns() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.legend_columns = Some(2);
+
+        for process in &["firefox", "chrome", "dolphin", "vscode", "spotify"] {
+            graph_arguments.push(
+                process,
+                "#ffaabb",
+                GraphElement::Line(3),
+                "/some/local/path.rrd",
+            );
+        }
+
+        let lines = graph_arguments.args[0]
+            .iter()
+            .filter(|arg| arg.starts_with("LINE"))
+            .collect::<Vec<&String>>();
+
+        assert_eq!("LINE3:firefox#ffaabb:\"firefox\"", lines[0]);
+        assert_eq!("LINE3:chrome#ffaabb:\"chrome\\l\"", lines[1]);
+        assert_eq!("LINE3:dolphin#ffaabb:\"dolphin\"", lines[2]);
+        assert_eq!("LINE3:vscode#ffaabb:\"vscode\\l\"", lines[3]);
+        assert_eq!("LINE3:spotify#ffaabb:\"spotify\"", lines[4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_statistics() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.statistics = true;
+        graph_arguments.legend_columns = Some(2);
+        graph_arguments.nan_fill = Some(300);
+
+        graph_arguments.push(
+            "firefox",
+            "#ffaabb",
+            GraphElement::Line(3),
+            "/some/path.rrd",
+        );
+        graph_arguments.push("chrome", "#ffaabb", GraphElement::Line(3), "/some/path.rrd");
+
+        let args = &graph_arguments.args[0];
+
+        assert_eq!(
+            "LINE3:firefox_filled#ffaabb:\"firefox             \"",
+            args[4]
+        );
+        assert_eq!("GPRINT:firefox_filled:MAX:\"max %6.1lf%S\"", args[5]);
+        assert_eq!("GPRINT:firefox_filled:AVERAGE:\"avg %6.1lf%S\"", args[6]);
+        assert_eq!("GPRINT:firefox_filled:LAST:\"last %6.1lf%S\\l\"", args[7]);
+        assert_eq!(
+            "LINE3:chrome_filled#ffaabb:\"chrome              \"",
+            args[12]
+        );
+        assert_eq!(16, args.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_arguments_push_nan_fill() -> Result<()> {
+        let mut graph_arguments = super::GraphArguments::new(Target::Local);
+        graph_arguments.nan_fill = Some(300);
+
+        graph_arguments.push(
+            "firefox",
+            "#ffaabb",
+            GraphElement::Line(3),
+            "/some/local/path.rrd",
+        );
+
+        assert_eq!(5, graph_arguments.args[0].len());
+        assert_eq!(
+            "DEF:firefox=/some/local/path.rrd:value:AVERAGE",
+            graph_arguments.args[0][0]
+        );
+        assert_eq!(
+            "CDEF:firefox_filled=firefox,UN,TIME,firefox_seen,-,300,GT,UNKN,firefox_held,IF,firefox,IF",
+            graph_arguments.args[0][3]
+        );
+        assert_eq!(
+            "LINE3:firefox_filled#ffaabb:\"firefox\"",
+            graph_arguments.args[0][4]
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+/// Way of drawing data on a graph
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GraphElement {
+    /// Line of given thickness
+    Line(u32),
+    /// Dashed line of given thickness
+    DashedLine(u32),
+    /// Area filled from zero
+    Area,
+    /// Area stacked on the previously drawn element
+    Stack,
+    /// Data exported with rrdtool xport instead of being drawn
+    Xport,
+}
+
+impl GraphElement {
+    /// Returns name of graph element as used by rrdtool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::graph_element::GraphElement;
+    ///
+    /// assert_eq!("LINE3", GraphElement::Line(3).to_rrdtool());
+    /// assert_eq!("AREA", GraphElement::Stack.to_rrdtool());
+    /// ```
+    ///
+    pub fn to_rrdtool(&self) -> String {
+        match self {
+            GraphElement::Line(thickness) | GraphElement::DashedLine(thickness) => {
+                String::from("LINE") + &thickness.to_string()
+            }
+            GraphElement::Area | GraphElement::Stack => String::from("AREA"),
+            GraphElement::Xport => String::from("XPORT"),
+        }
+    }
+}
+
+/// Returns [`GraphElement`] from str, line is drawn with thickness 3
+impl FromStr for GraphElement {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<GraphElement, Self::Err> {
+        match input {
+            "line" => Ok(GraphElement::Line(3)),
+            "area" => Ok(GraphElement::Area),
+            "stack" => Ok(GraphElement::Stack),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn graph_element_from_str() {
+        assert_eq!(Ok(GraphElement::Line(3)), GraphElement::from_str("line"));
+        assert_eq!(Ok(GraphElement::Area), GraphElement::from_str("area"));
+        assert_eq!(Ok(GraphElement::Stack), GraphElement::from_str("stack"));
+        assert!(GraphElement::from_str("bar").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use super::graph_arguments::validate_color;
+
+use anyhow::{anyhow, Context};
+use std::str::FromStr;
+
+/// Horizontal line drawn across graphs at given value, e.g. memory limit
+#[derive(Clone, PartialEq, Debug)]
+pub struct Hrule {
+    /// Value at which the line is drawn
+    pub value: f64,
+    /// Color of line, e.g. #ff0000
+    pub color: String,
+    /// Text shown on graph legend
+    pub label: Option<String>,
+}
+
+impl Hrule {
+    /// Returns HRULE argument as expected by rrdtool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::hrule::Hrule;
+    /// use std::str::FromStr;
+    ///
+    /// let hrule = Hrule::from_str("8589934592:#ff0000:8 GiB limit").unwrap();
+    ///
+    /// assert_eq!("HRULE:8589934592#ff0000:\"8 GiB limit\"", hrule.to_rrdtool());
+    /// ```
+    ///
+    pub fn to_rrdtool(&self) -> String {
+        let rule = format!("HRULE:{}{}", self.value, self.color);
+
+        match &self.label {
+            Some(label) => rule + ":\"" + label + "\"",
+            None => rule,
+        }
+    }
+}
+
+/// Returns [`Hrule`] from str given as value:#rrggbb[:label]
+impl FromStr for Hrule {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Hrule, Self::Err> {
+        let mut parts = input.splitn(3, ':');
+
+        let value = parts.next().unwrap_or_default();
+        let value = value
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| anyhow!("Expected number, got {}", value))
+            .context(format!("Invalid value of hrule {}", input))?;
+
+        let color = parts.next().unwrap_or_default();
+        validate_color(color).context(format!("Invalid color of hrule {}", input))?;
+        let color = String::from(color);
+
+        let label = parts
+            .next()
+            .map(|label| label.trim_matches('"'))
+            .filter(|label| !label.is_empty())
+            .map(String::from);
+
+        Ok(Hrule {
+            value,
+            color,
+            label,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn hrule_from_str() -> Result<()> {
+        assert_eq!(
+            Hrule {
+                value: 8589934592.0,
+                color: String::from("#ff0000"),
+                label: Some(String::from("8 GiB limit")),
+            },
+            Hrule::from_str("8589934592:#ff0000:\"8 GiB limit\"")?
+        );
+        assert_eq!(
+            "HRULE:0.5#00ff00",
+            Hrule::from_str("0.5:#00ff00")?.to_rrdtool()
+        );
+        assert_eq!(
+            Some(String::from("limit: 8 GiB")),
+            Hrule::from_str("8589934592:#ff0000:limit: 8 GiB")?.label
+        );
+
+        assert!(Hrule::from_str("8 GiB:#ff0000").is_err());
+        assert!(Hrule::from_str("inf:#ff0000").is_err());
+        assert!(Hrule::from_str("100").is_err());
+        assert!(Hrule::from_str("100:red:limit").is_err());
+        assert_eq!(
+            "HRULE:100#ff000080",
+            Hrule::from_str("100:#ff000080")?.to_rrdtool()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+use std::str::FromStr;
+
+/// Format of generated graphs, passed to rrdtool with --imgformat
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+    Pdf,
+    Eps,
+}
+
+impl ImageFormat {
+    /// Returns name of the format as expected by rrdtool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::image_format::ImageFormat;
+    ///
+    /// assert_eq!("SVG", ImageFormat::Svg.as_str());
+    /// ```
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Svg => "SVG",
+            ImageFormat::Pdf => "PDF",
+            ImageFormat::Eps => "EPS",
+        }
+    }
+
+    /// Returns extension of files in this format, without leading dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Svg => "svg",
+            ImageFormat::Pdf => "pdf",
+            ImageFormat::Eps => "eps",
+        }
+    }
+
+    /// Detects format from extension of the filename, PNG is used for unknown extensions
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::image_format::ImageFormat;
+    ///
+    /// assert_eq!(ImageFormat::Pdf, ImageFormat::from_filename("graphs/out.PDF"));
+    /// assert_eq!(ImageFormat::Png, ImageFormat::from_filename("out.gif"));
+    /// ```
+    ///
+    pub fn from_filename(filename: &str) -> ImageFormat {
+        Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| ImageFormat::from_str(extension).ok())
+            .unwrap_or(ImageFormat::Png)
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ImageFormat, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "svg" => Ok(ImageFormat::Svg),
+            "pdf" => Ok(ImageFormat::Pdf),
+            "eps" => Ok(ImageFormat::Eps),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+/// Placement of legend relative to the graph, passed to rrdtool with --legend-position
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LegendPosition {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl LegendPosition {
+    /// Returns name of the position as expected by rrdtool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::legend::LegendPosition;
+    ///
+    /// assert_eq!("east", LegendPosition::East.as_str());
+    /// ```
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LegendPosition::North => "north",
+            LegendPosition::South => "south",
+            LegendPosition::West => "west",
+            LegendPosition::East => "east",
+        }
+    }
+}
+
+impl FromStr for LegendPosition {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<LegendPosition, Self::Err> {
+        match input {
+            "north" => Ok(LegendPosition::North),
+            "south" => Ok(LegendPosition::South),
+            "west" => Ok(LegendPosition::West),
+            "east" => Ok(LegendPosition::East),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Order of legend entries, passed to rrdtool with --legend-direction
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LegendDirection {
+    /// Entries in order of drawing, the default
+    TopDown,
+    /// Entries in reverse order, matching stacked areas
+    BottomUp,
+}
+
+impl LegendDirection {
+    /// Returns name of the direction as expected by rrdtool
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LegendDirection::TopDown => "topdown",
+            LegendDirection::BottomUp => "bottomup",
+        }
+    }
+}
+
+impl FromStr for LegendDirection {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<LegendDirection, Self::Err> {
+        match input {
+            "topdown" => Ok(LegendDirection::TopDown),
+            "bottomup" => Ok(LegendDirection::BottomUp),
+            _ => Err(()),
+        }
+    }
+}
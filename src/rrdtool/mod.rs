@@ -1,3 +1,16 @@
+pub mod command;
 pub mod common;
+pub mod compare_hosts;
+pub mod consolidation_function;
+pub mod detect;
 pub mod graph_arguments;
+pub mod graph_element;
+pub mod hrule;
+pub mod image_format;
+pub mod legend;
+pub mod palette;
+pub mod probe;
 pub mod remote;
+pub mod right_axis;
+pub mod rrd_file;
+pub mod transfer;
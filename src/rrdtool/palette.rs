@@ -0,0 +1,57 @@
+use super::common::Rrdtool;
+use std::str::FromStr;
+
+/// Ready-made palettes of colors of lines, chosen with --palette-preset
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PalettePreset {
+    /// Default palette of 20 distinct colors, see [`Rrdtool::COLORS`]
+    Default,
+    /// Okabe-Ito colors, distinguishable with color vision deficiency
+    Colorblind,
+    /// Shades of gray, from black to light gray, e.g. for printing
+    Grayscale,
+}
+
+impl PalettePreset {
+    /// Okabe-Ito palette, black is the last one as it's the least distinct from axes
+    pub const COLORBLIND: &'static [&'static str] = &[
+        "#e69f00", "#56b4e9", "#009e73", "#f0e442", "#0072b2", "#d55e00", "#cc79a7", "#000000",
+    ];
+
+    /// Shades of gray distinct enough to be told apart
+    pub const GRAYSCALE: &'static [&'static str] =
+        &["#000000", "#404040", "#707070", "#989898", "#b8b8b8"];
+
+    /// Returns colors of the palette, in order of drawing
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::palette::PalettePreset;
+    ///
+    /// assert_eq!("#e69f00", PalettePreset::Colorblind.colors()[0]);
+    /// ```
+    ///
+    pub fn colors(&self) -> Vec<String> {
+        let colors = match self {
+            PalettePreset::Default => Rrdtool::COLORS,
+            PalettePreset::Colorblind => PalettePreset::COLORBLIND,
+            PalettePreset::Grayscale => PalettePreset::GRAYSCALE,
+        };
+
+        colors.iter().map(|color| String::from(*color)).collect()
+    }
+}
+
+impl FromStr for PalettePreset {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<PalettePreset, Self::Err> {
+        match input {
+            "default" => Ok(PalettePreset::Default),
+            "colorblind" => Ok(PalettePreset::Colorblind),
+            "grayscale" => Ok(PalettePreset::Grayscale),
+            _ => Err(()),
+        }
+    }
+}
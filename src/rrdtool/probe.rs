@@ -0,0 +1,137 @@
+use super::command::CommandRunner;
+
+use anyhow::{Context, Result};
+use log::trace;
+use std::fmt;
+
+/// rrdtool feature which may be missing in older builds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    /// Name of the feature, e.g. rrdtool option
+    pub name: &'static str,
+    /// cgg options depending on the feature
+    pub used_by: &'static str,
+    /// Whether installed rrdtool supports the feature
+    pub supported: bool,
+}
+
+/// Capabilities of installed rrdtool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeReport {
+    /// Version string, e.g. 1.7.2
+    pub version: String,
+    /// Known features and whether they are supported
+    pub features: Vec<Feature>,
+}
+
+/// Known features with the first rrdtool version supporting them
+const FEATURE_GATES: &[(&str, &str, (u32, u32, u32))] = &[
+    ("graph", "all graphs", (1, 0, 0)),
+    ("--only-graph", "--sparkline", (1, 2, 0)),
+    ("PDF output", "PDF output file", (1, 3, 0)),
+    ("--daemon", "rrdcached", (1, 4, 0)),
+    ("--legend-position", "legend placement", (1, 5, 0)),
+];
+
+impl fmt::Display for ProbeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "rrdtool version: {}", self.version)?;
+        writeln!(f, "features:")?;
+
+        for feature in &self.features {
+            writeln!(
+                f,
+                "  [{}] {} ({})",
+                match feature.supported {
+                    true => "x",
+                    false => " ",
+                },
+                feature.name,
+                feature.used_by
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Run `rrdtool --version` and report which features are supported
+///
+/// # Arguments
+/// * `runner` - executes rrdtool
+/// * `command` - rrdtool command, e.g. rrdtool
+///
+pub fn probe(runner: &dyn CommandRunner, command: &str) -> Result<ProbeReport> {
+    let output = runner
+        .run(command, &[String::from("--version")])
+        .context(format!("Failed to execute {} --version", command))?;
+
+    // rrdtool --version exits with 1 on some builds, rely on the output only
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    trace!("{} --version returned: {}", command, stdout);
+
+    let (version, parsed) =
+        parse_version(&stdout).context(format!("Cannot find rrdtool version in: {}", stdout))?;
+
+    Ok(ProbeReport {
+        version,
+        features: FEATURE_GATES
+            .iter()
+            .map(|(name, used_by, since)| Feature {
+                name,
+                used_by,
+                supported: parsed >= *since,
+            })
+            .collect(),
+    })
+}
+
+/// Parse version from the first line of `rrdtool --version`, e.g.
+/// RRDtool 1.7.2  Copyright by Tobias Oetiker <tobi@oetiker.ch>
+pub fn parse_version(output: &str) -> Option<(String, (u32, u32, u32))> {
+    let re = regex::Regex::new(r"RRDtool (\d+)\.(\d+)\.(\d+)").unwrap();
+    let captures = re.captures(output)?;
+
+    let number = |index: usize| captures[index].parse::<u32>().ok();
+
+    Some((
+        String::from(&captures[0]["RRDtool ".len()..]),
+        (number(1)?, number(2)?, number(3)?),
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::command::tests::MockCommandRunner;
+    use super::*;
+
+    #[test]
+    pub fn probe_reports_version_and_features() -> Result<()> {
+        let runner = MockCommandRunner::new().returning(
+            "rrdtool",
+            "RRDtool 1.4.8  Copyright 1997-2013 by Tobias Oetiker <tobi@oetiker.ch>\n",
+        );
+
+        let report = probe(&runner, "rrdtool")?;
+
+        assert_eq!("1.4.8", report.version);
+        assert_eq!(FEATURE_GATES.len(), report.features.len());
+        assert!(report
+            .features
+            .iter()
+            .any(|f| f.name == "--daemon" && f.supported));
+        assert!(report
+            .features
+            .iter()
+            .any(|f| f.name == "--legend-position" && !f.supported));
+
+        let printed = report.to_string();
+        assert!(printed.contains("rrdtool version: 1.4.8"));
+        assert!(printed.contains("[ ] --legend-position"));
+
+        assert!(probe(&MockCommandRunner::new(), "rrdtool").is_err());
+
+        Ok(())
+    }
+}
@@ -1,31 +1,138 @@
+use super::super::error::CggError;
+use super::command::CommandRunner;
 use super::common;
 
 use anyhow::{Context, Result};
-use std::process::Command;
+use log::trace;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Options of ssh and scp connections to remote target
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SshOptions {
+    /// SSH port of remote target, if other than default
+    pub port: Option<u16>,
+    /// Identity file used instead of default keys and agent
+    pub identity: Option<PathBuf>,
+    /// Control socket of master connection shared by all connections
+    pub control_path: Option<PathBuf>,
+    /// Refuse to execute any command on remote target, e.g. in CI
+    pub offline: bool,
+}
+
+impl SshOptions {
+    /// Returns arguments of given program, i.e. ssh or scp, which sets port with -P
+    pub fn args(&self, program: &str) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(port) = self.port {
+            args.push(String::from(match program {
+                "scp" => "-P",
+                _ => "-p",
+            }));
+            args.push(port.to_string());
+        }
+
+        if let Some(identity) = &self.identity {
+            args.push(String::from("-i"));
+            args.push(String::from(identity.to_string_lossy()));
+        }
+
+        if let Some(control_path) = &self.control_path {
+            args.push(String::from("-o"));
+            args.push(String::from("ControlPath=") + &control_path.to_string_lossy());
+        }
+
+        args
+    }
+
+    /// Fails in offline mode, to be called before given program connects to remote target
+    pub fn ensure_online(&self, program: &str) -> Result<()> {
+        if self.offline {
+            anyhow::bail!("offline mode: refusing remote command {}", program);
+        }
+
+        Ok(())
+    }
+}
+
+/// Listings of remote directories, so each directory is listed only once per run
+#[derive(Debug, Default)]
+pub struct RemoteListing {
+    /// Files of already listed directories, keyed by path of remote directory
+    listings: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl RemoteListing {
+    /// Get list of remote files, executing ls only if the directory wasn't listed before
+    ///
+    /// # Arguments
+    /// * `runner` - executes ssh command
+    /// * `dir` - path of remote directory
+    /// * `username` - username to SSH login
+    /// * `hostname` - hostname of remote target
+    /// * `ssh_options` - options of SSH connection
+    ///
+    pub fn ls(
+        &self,
+        runner: &dyn CommandRunner,
+        dir: &str,
+        username: &str,
+        hostname: &str,
+        ssh_options: &SshOptions,
+    ) -> Result<Vec<String>> {
+        let mut listings = self.listings.lock().unwrap();
+
+        if let Some(files) = listings.get(dir) {
+            trace!("Using cached listing of remote directory {}", dir);
+            return Ok(files.clone());
+        }
+
+        let files = ls(runner, dir, username, hostname, ssh_options)?;
+        listings.insert(String::from(dir), files.clone());
+
+        Ok(files)
+    }
+}
 
 /// Get list of remote files
 ///
 /// # Arguments
+/// * `runner` - executes ssh command
 /// * `dir` - path of remote directory
 /// * `username` - username to SSH login
 /// * `hostname` - hostname of remote target
+/// * `ssh_options` - options of SSH connection
 ///
-pub fn ls(dir: &str, username: &str, hostname: &str) -> Result<Vec<String>> {
+pub fn ls(
+    runner: &dyn CommandRunner,
+    dir: &str,
+    username: &str,
+    hostname: &str,
+    ssh_options: &SshOptions,
+) -> Result<Vec<String>> {
+    ssh_options.ensure_online("ssh")?;
+
     let network_address = String::from(username) + "@" + hostname;
 
-    let output = Command::new("ssh")
-        .args(&[&network_address, &String::from("ls"), &String::from(dir)])
-        .output()
-        .context("Failed to execute SSH")?;
+    let mut args = ssh_options.args("ssh");
+    args.extend(vec![
+        network_address.clone(),
+        String::from("ls"),
+        common::shell_quote(dir),
+    ]);
+
+    let output = runner.run("ssh", &args).context("Failed to execute SSH")?;
 
     if !output.status.success() {
         common::print_process_command_output(output);
 
-        anyhow::bail!(
-            "Failed to list remote directories in {}:{}!",
-            network_address,
-            dir
-        );
+        return Err(CggError::RemoteListingFailed {
+            target: network_address,
+            dir: String::from(dir),
+        }
+        .into());
     }
 
     Ok(String::from_utf8_lossy(&output.stdout)
@@ -36,8 +143,12 @@ pub fn ls(dir: &str, username: &str, hostname: &str) -> Result<Vec<String>> {
 
 #[cfg(test)]
 pub mod tests {
+    use super::super::command::tests::MockCommandRunner;
+    use super::super::command::SystemCommandRunner;
+    use super::{CggError, SshOptions};
     use anyhow::Result;
     use std::fs::{create_dir, File};
+    use std::path::PathBuf;
     use tempfile::TempDir;
 
     #[test]
@@ -61,12 +172,20 @@ pub mod tests {
         ];
 
         let res = super::ls(
+            &SystemCommandRunner,
             dir.path().to_str().unwrap(),
             &whoami::username(),
             "localhost",
+            &SshOptions::default(),
         );
 
-        let res_nok = super::ls(dir.path().to_str().unwrap(), &whoami::username(), "local");
+        let res_nok = super::ls(
+            &SystemCommandRunner,
+            dir.path().to_str().unwrap(),
+            &whoami::username(),
+            "local",
+            &SshOptions::default(),
+        );
 
         assert!(res.is_ok());
         assert!(res_nok.is_err());
@@ -82,4 +201,81 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn ls_failure_typed() -> Result<()> {
+        let error = super::ls(
+            &MockCommandRunner::new().failing("ssh", 1),
+            "/var/lib/collectd/host",
+            "user",
+            "host",
+            &SshOptions::default(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            CggError::from(error),
+            CggError::RemoteListingFailed { target, dir }
+                if target == "user@host" && dir == "/var/lib/collectd/host"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ls_offline() -> Result<()> {
+        let runner = MockCommandRunner::new();
+        let calls = runner.calls.clone();
+
+        let error = super::ls(
+            &runner,
+            "/var/lib/collectd/host",
+            "user",
+            "host",
+            &SshOptions {
+                offline: true,
+                ..SshOptions::default()
+            },
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("offline mode"));
+        assert!(MockCommandRunner::calls_of(&calls, "ssh").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn ssh_options_args() -> Result<()> {
+        let ssh_options = SshOptions {
+            port: Some(2222),
+            identity: Some(PathBuf::from("/home/user/.ssh/collectd_key")),
+            control_path: None,
+            offline: false,
+        };
+
+        assert_eq!(
+            vec!["-p", "2222", "-i", "/home/user/.ssh/collectd_key"],
+            ssh_options.args("ssh")
+        );
+        assert_eq!(
+            vec!["-P", "2222", "-i", "/home/user/.ssh/collectd_key"],
+            ssh_options.args("scp")
+        );
+
+        let ssh_options = SshOptions {
+            port: None,
+            identity: Some(PathBuf::from("key")),
+            control_path: Some(PathBuf::from("/tmp/cgg/control")),
+            offline: false,
+        };
+
+        assert_eq!(
+            vec!["-i", "key", "-o", "ControlPath=/tmp/cgg/control"],
+            ssh_options.args("ssh")
+        );
+        assert!(SshOptions::default().args("ssh").is_empty());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Context};
+use std::str::FromStr;
+
+/// Second vertical axis at the right side of graphs, for elements in different units,
+/// e.g. processes count drawn together with memory usage.
+///
+/// Value at the right axis is `left * scale + shift`, elements drawn against the right axis
+/// are scaled back to the left axis with a CDEF.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RightAxis {
+    /// Multiplier of values at the left axis
+    pub scale: f64,
+    /// Value added after scaling
+    pub shift: f64,
+}
+
+impl RightAxis {
+    /// Returns value of --right-axis argument as expected by rrdtool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::right_axis::RightAxis;
+    /// use std::str::FromStr;
+    ///
+    /// assert_eq!("0.001:0", RightAxis::from_str("0.001").unwrap().to_rrdtool());
+    /// ```
+    ///
+    pub fn to_rrdtool(&self) -> String {
+        format!("{}:{}", self.scale, self.shift)
+    }
+
+    /// Returns CDEF scaling `vname` from the right axis to the left axis as `scaled`
+    pub fn build_cdef(&self, scaled: &str, vname: &str) -> String {
+        format!(
+            "CDEF:{}={},{},-,{},/",
+            scaled, vname, self.shift, self.scale
+        )
+    }
+}
+
+/// Returns [`RightAxis`] from str given as scale[:shift]
+impl FromStr for RightAxis {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<RightAxis, Self::Err> {
+        let mut parts = input.splitn(2, ':');
+
+        let scale = parts.next().unwrap_or_default();
+        let scale = scale
+            .parse::<f64>()
+            .ok()
+            .filter(|scale| scale.is_finite() && *scale != 0.0)
+            .ok_or_else(|| anyhow!("Expected non-zero number, got {}", scale))
+            .context(format!("Invalid scale of right axis {}", input))?;
+
+        let shift = match parts.next() {
+            Some(shift) => shift
+                .parse::<f64>()
+                .ok()
+                .filter(|shift| shift.is_finite())
+                .ok_or_else(|| anyhow!("Expected number, got {}", shift))
+                .context(format!("Invalid shift of right axis {}", input))?,
+            None => 0.0,
+        };
+
+        Ok(RightAxis { scale, shift })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn right_axis_from_str() -> Result<()> {
+        assert_eq!(
+            RightAxis {
+                scale: 0.5,
+                shift: -10.0,
+            },
+            RightAxis::from_str("0.5:-10")?
+        );
+        assert_eq!("1000:0", RightAxis::from_str("1000")?.to_rrdtool());
+        assert_eq!(
+            "CDEF:a_right=a,-10,-,0.5,/",
+            RightAxis::from_str("0.5:-10")?.build_cdef("a_right", "a")
+        );
+
+        assert!(RightAxis::from_str("0").is_err());
+        assert!(RightAxis::from_str("big").is_err());
+        assert!(RightAxis::from_str("2:low").is_err());
+        assert!(RightAxis::from_str("inf").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use super::common::Rrdtool;
+
+use anyhow::{Context, Result};
+use log::{debug, trace};
+use std::path::Path;
+
+/// Returns true if input points at a single rrd file instead of collectd directory
+///
+/// # Examples
+///
+/// ```
+/// use cgg::rrdtool::rrd_file::is_rrd_file;
+/// use std::path::Path;
+///
+/// assert!(is_rrd_file(Path::new("user@host:/tmp/load.rrd")));
+/// assert!(!is_rrd_file(Path::new("/var/lib/collectd/host")));
+/// ```
+///
+pub fn is_rrd_file(input: &Path) -> bool {
+    input.extension().and_then(|extension| extension.to_str()) == Some("rrd")
+}
+
+impl Rrdtool {
+    /// Draw all data sources of the rrd file given as input on one graph, without
+    /// looking for collectd plugins. Data sources are read from rrdtool info.
+    pub fn with_rrd_file(&mut self) -> Result<&mut Self> {
+        let file = self.input_dir.clone();
+
+        debug!("Drawing all data sources of {}", file);
+
+        let info = self
+            .query(&[String::from("info"), file.clone()])
+            .context(format!("Failed to get rrdtool info of {}", file))?;
+
+        let data_sources = parse_ds_names(&info);
+
+        if data_sources.is_empty() {
+            anyhow::bail!("Cannot find data sources in rrdtool info of {}", file);
+        }
+
+        self.graph_args.new_graph();
+
+        for (index, ds) in data_sources.iter().enumerate() {
+            trace!("Drawing data source {} of {}", ds, file);
+
+            let color = self.get_color(index);
+            let element = self.get_line(index, 3);
+
+            self.graph_args
+                .push_with_ds(ds, &color, element, &file, ds, false);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Parse names of data sources from output of rrdtool info, i.e. lines: ds[value].type = "GAUGE",
+/// in order of their definition
+fn parse_ds_names(info: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    for line in info.lines() {
+        let name = line
+            .strip_prefix("ds[")
+            .and_then(|rest| rest.find("].").map(|end| &rest[..end]));
+
+        if let Some(name) = name {
+            if !names.iter().any(|known| known == name) {
+                names.push(String::from(name));
+            }
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::command::tests::MockCommandRunner;
+    use super::*;
+
+    const INFO: &str = "filename = \"load.rrd\"\n\
+                        rrd_version = \"0003\"\n\
+                        step = 10\n\
+                        ds[shortterm].index = 0\n\
+                        ds[shortterm].type = \"GAUGE\"\n\
+                        ds[midterm].index = 1\n\
+                        ds[midterm].type = \"GAUGE\"\n\
+                        rra[0].cf = \"AVERAGE\"\n";
+
+    #[test]
+    pub fn parse_ds_names() {
+        assert_eq!(
+            vec![String::from("shortterm"), String::from("midterm")],
+            super::parse_ds_names(INFO)
+        );
+        assert!(super::parse_ds_names("step = 10\n").is_empty());
+    }
+
+    #[test]
+    pub fn rrdtool_with_rrd_file() -> Result<()> {
+        let runner = MockCommandRunner::new().returning("rrdtool info", INFO);
+
+        let mut rrd = Rrdtool::new(Path::new("/tmp/load.rrd"))?;
+        rrd.with_runner(Box::new(runner))?;
+
+        rrd.with_rrd_file()?;
+
+        assert_eq!(1, rrd.graph_args.args.len());
+
+        let args = &rrd.graph_args.args[0];
+        assert!(args.iter().any(
+            |arg| arg.starts_with("DEF:") && arg.ends_with("=/tmp/load.rrd:shortterm:AVERAGE")
+        ));
+        assert!(args
+            .iter()
+            .any(|arg| arg.starts_with("DEF:") && arg.ends_with("=/tmp/load.rrd:midterm:AVERAGE")));
+        assert_eq!(2, args.iter().filter(|arg| arg.starts_with("LINE")).count());
+
+        let mut rrd = Rrdtool::new(Path::new("/tmp/empty.rrd"))?;
+        rrd.with_runner(Box::new(
+            MockCommandRunner::new().returning("rrdtool info", "step = 10\n"),
+        ))?;
+        assert!(rrd.with_rrd_file().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use std::str::FromStr;
+
+/// Program copying graphs generated on remote target back to host
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Transfer {
+    /// Each graph is copied separately, right after it's generated
+    Scp,
+    /// All graphs are copied at once, after all of them are generated
+    Rsync,
+}
+
+impl Transfer {
+    /// Returns name of the program
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cgg::rrdtool::transfer::Transfer;
+    ///
+    /// assert_eq!("rsync", Transfer::Rsync.as_str());
+    /// ```
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transfer::Scp => "scp",
+            Transfer::Rsync => "rsync",
+        }
+    }
+}
+
+impl FromStr for Transfer {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Transfer, Self::Err> {
+        match input {
+            "scp" => Ok(Transfer::Scp),
+            "rsync" => Ok(Transfer::Rsync),
+            _ => Err(()),
+        }
+    }
+}
@@ -10,6 +10,24 @@ pub fn init() -> std::io::Result<TempDir> {
     TempDir::new()
 }
 
+/// Create input directory with both memory and processes data, linked from test data
+pub fn init_combined_input() -> anyhow::Result<TempDir> {
+    let input_directory = TempDir::new()?;
+
+    let data = std::env::current_dir()?.join("tests");
+    std::os::unix::fs::symlink(
+        data.join("memory/data/memory"),
+        input_directory.path().join("memory"),
+    )?;
+
+    for entry in std::fs::read_dir(data.join("processes/data"))? {
+        let entry = entry?;
+        std::os::unix::fs::symlink(entry.path(), input_directory.path().join(entry.file_name()))?;
+    }
+
+    Ok(input_directory)
+}
+
 /// Returns path to collectd-graph-generator executable
 pub fn get_cgg_exec_path() -> anyhow::Result<PathBuf> {
     Ok(std::env::current_dir()?.join("target/debug/cgg"))
@@ -1,6 +1,8 @@
 use super::super::common;
 
 use anyhow::Result;
+use cgg::config::Config;
+use clap::{load_yaml, App};
 
 use std::process::Command;
 
@@ -31,6 +33,49 @@ fn main_wrong_directory() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn main_invalid_port() -> Result<()> {
+    common::init()?;
+
+    for args in &[
+        vec!["-t", "last 1 hour"],
+        vec!["--list-processes"],
+        vec!["--detect"],
+    ] {
+        let status = Command::new(common::get_cgg_exec_path()?)
+            .arg("-i")
+            .arg("user@host:99999:/var/lib/collectd")
+            .args(args)
+            .status()?;
+
+        // Panic exits with 101
+        assert_eq!(Some(1), status.code());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn main_no_data() -> Result<()> {
+    common::init()?;
+    let input_directory = tempfile::TempDir::new()?;
+
+    for plugin in &["processes", "memory", "cpu", "df"] {
+        let status = Command::new(common::get_cgg_exec_path()?)
+            .arg("-i")
+            .arg(input_directory.path())
+            .arg("-t")
+            .arg("last 1 hour")
+            .arg("-p")
+            .arg(plugin)
+            .status()?;
+
+        assert_eq!(Some(2), status.code(), "plugin {}", plugin);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn main_failed_run() -> Result<()> {
     common::init()?;
@@ -40,11 +85,154 @@ fn main_failed_run() -> Result<()> {
         .arg(&std::env::current_dir()?.join("tests/processes/data"))
         .arg("-t")
         .arg("last 1 hour")
-        .arg("--plugins")
-        .arg("memory")
+        .arg("--rrdtool-bin")
+        .arg("cgg-missing-rrdtool")
         .status()?;
 
-    assert!(!status.success());
+    assert_eq!(Some(1), status.code());
+
+    Ok(())
+}
+
+#[test]
+fn main_quiet() -> Result<()> {
+    let output_directory = common::init()?;
+
+    let run = |quiet: bool| -> Result<String> {
+        let mut command = Command::new(common::get_cgg_exec_path()?);
+        command
+            .env("RUST_LOG", "info")
+            .arg("-i")
+            .arg(std::env::current_dir()?.join("tests/processes/data"))
+            .arg("-t")
+            .arg("last 1 hour")
+            .arg("-o")
+            .arg(output_directory.path().join("out.png"))
+            .arg("--force");
+
+        if quiet {
+            command.arg("--quiet");
+        }
+
+        Ok(String::from_utf8(command.output()?.stderr)?)
+    };
+
+    assert!(run(false)?.contains("INFO"));
+    assert!(!run(true)?.contains("INFO"));
+
+    Ok(())
+}
+
+#[test]
+fn main_combine() -> Result<()> {
+    let output_directory = common::init()?;
+    let input_directory = common::init_combined_input()?;
+
+    let status = Command::new(common::get_cgg_exec_path()?)
+        .arg("-i")
+        .arg(input_directory.path())
+        .arg("-p")
+        .arg("memory,processes")
+        .arg("--max_processes")
+        .arg("2")
+        .arg("--combine")
+        .arg("-o")
+        .arg(output_directory.path().join("out.png"))
+        .arg("-t")
+        .arg("last month")
+        .status()?;
+
+    assert!(status.success());
+    assert_eq!(1, std::fs::read_dir(output_directory.path())?.count());
+    assert!(output_directory.path().join("out.png").exists());
+
+    Ok(())
+}
+
+#[test]
+fn main_rrd_file() -> Result<()> {
+    let output_directory = common::init()?;
+
+    let status = Command::new(common::get_cgg_exec_path()?)
+        .arg("-i")
+        .arg(std::env::current_dir()?.join("tests/memory/data/memory/memory-used.rrd"))
+        .arg("-t")
+        .arg("last 1 hour")
+        .arg("-o")
+        .arg(output_directory.path().join("out.png"))
+        .status()?;
+
+    assert!(status.success());
+    assert!(output_directory.path().join("out.png").exists());
+
+    Ok(())
+}
+
+#[test]
+fn run_collecting_paths() -> Result<()> {
+    let output_directory = common::init()?;
+    let input_directory = std::env::current_dir()?.join("tests/processes/data");
+    let output = output_directory.path().join("out.png");
+
+    let yaml = load_yaml!("../../src/cli.yml");
+    let cli = App::from(yaml).try_get_matches_from(vec![
+        "cgg",
+        "-i",
+        input_directory.to_str().unwrap(),
+        "-p",
+        "processes",
+        "--max_processes",
+        "3",
+        "-o",
+        output.to_str().unwrap(),
+        "-t",
+        "last month",
+    ])?;
+
+    let mut paths = cgg::run_collecting(Config::new(&cli)?)?;
+    paths.sort();
+
+    let mut on_disk = std::fs::read_dir(output_directory.path())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    on_disk.sort();
+
+    assert!(paths.len() > 1);
+    assert_eq!(on_disk, paths);
+
+    Ok(())
+}
+
+#[test]
+fn main_detect() -> Result<()> {
+    common::init()?;
+    let input_directory = common::init_combined_input()?;
+
+    let output = Command::new(common::get_cgg_exec_path()?)
+        .arg("-i")
+        .arg(input_directory.path())
+        .arg("--detect")
+        .output()?;
+
+    assert!(output.status.success());
+    assert_eq!("processes\nmemory\n", String::from_utf8(output.stdout)?);
+
+    Ok(())
+}
+
+#[test]
+fn main_probe() -> Result<()> {
+    common::init()?;
+
+    let output = Command::new(common::get_cgg_exec_path()?)
+        .arg("probe")
+        .output()?;
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("rrdtool version: "));
+    assert!(stdout.contains("features:"));
 
     Ok(())
 }
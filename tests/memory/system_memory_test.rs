@@ -69,6 +69,7 @@ fn system_memory_local() -> Result<()> {
 
     let mut plugins_config = PluginsConfig {
         data: HashMap::new(),
+        order: Vec::new(),
     };
 
     plugins_config.data.insert(
@@ -90,7 +91,7 @@ fn system_memory_local() -> Result<()> {
         input_dir.display(), output_file.to_str().unwrap(), width, height, start, end
     );
 
-    Rrdtool::new(&input_dir)
+    Rrdtool::new(&input_dir)?
         .with_subcommand(String::from("graph"))
         .context("Failed with_subcommand")?
         .with_output_file(String::from(output_file.to_str().unwrap()))
@@ -21,6 +21,7 @@ pub fn multiple_processes(input_dir: &Path) -> Result<()> {
 
     let mut plugins_config = PluginsConfig {
         data: HashMap::new(),
+        order: Vec::new(),
     };
 
     plugins_config.data.insert(
@@ -33,7 +34,7 @@ pub fn multiple_processes(input_dir: &Path) -> Result<()> {
         input_dir.display(), output_file.to_str().unwrap(), width, height, start, end
     );
 
-    Rrdtool::new(&input_dir)
+    Rrdtool::new(&input_dir)?
         .with_subcommand(String::from("graph"))
         .context("Failed with_subcommand")?
         .with_output_file(String::from(output_file.to_str().unwrap()))
@@ -70,6 +71,7 @@ pub fn multiple_processes_multiple_files(input_dir: &Path) -> Result<()> {
 
     let mut plugins_config = PluginsConfig {
         data: HashMap::new(),
+        order: Vec::new(),
     };
 
     plugins_config
@@ -84,7 +86,7 @@ pub fn multiple_processes_multiple_files(input_dir: &Path) -> Result<()> {
         end
     );
 
-    Rrdtool::new(&input_dir)
+    Rrdtool::new(&input_dir)?
         .with_subcommand(String::from("graph"))
         .context("Failed with_subcommand")?
         .with_output_file(String::from(output_file.to_str().unwrap()))
@@ -159,6 +161,7 @@ pub fn multiple_processes_local_filtered_names(input_dir: &Path) -> Result<()> {
 
     let mut plugins_config = PluginsConfig {
         data: HashMap::new(),
+        order: Vec::new(),
     };
 
     plugins_config.data.insert(
@@ -179,7 +182,7 @@ pub fn multiple_processes_local_filtered_names(input_dir: &Path) -> Result<()> {
         input_dir.display(), output_file.to_str().unwrap(), width, height, start, end
     );
 
-    Rrdtool::new(&input_dir)
+    Rrdtool::new(&input_dir)?
         .with_subcommand(String::from("graph"))
         .context("Failed with_subcommand")?
         .with_output_file(String::from(output_file.to_str().unwrap()))
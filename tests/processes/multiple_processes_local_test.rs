@@ -64,6 +64,29 @@ fn multiple_processes_local_from_binary() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn multiple_processes_local_list_processes() -> Result<()> {
+    common::init()?;
+
+    let output = Command::new(common::get_cgg_exec_path()?)
+        .arg("-i")
+        .arg(std::env::current_dir()?.join("tests/processes/data"))
+        .arg("--list-processes")
+        .output()?;
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let processes = stdout.lines().collect::<Vec<&str>>();
+
+    assert_eq!(
+        vec!["baloo_file", "dolphin", "firefox", "kaccess", "ksmserver"],
+        processes[..5]
+    );
+
+    Ok(())
+}
+
 #[test]
 fn multiple_processes_local_from_binary_wrong_separator() -> Result<()> {
     let output_directory = common::init()?;
@@ -136,3 +159,28 @@ fn multiple_processes_local_from_binary_unix_timestamps() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn multiple_processes_local_from_binary_to_stdout() -> Result<()> {
+    common::init()?;
+
+    let exec_dir = common::get_cgg_exec_path()?;
+
+    let output = Command::new(&exec_dir)
+        .arg("-i")
+        .arg(std::env::current_dir()?.join("tests/processes/data"))
+        .arg("-p")
+        .arg("processes")
+        .arg("--processes")
+        .arg("firefox,spotify")
+        .arg("-o")
+        .arg("-")
+        .arg("-t")
+        .arg("last month")
+        .output()?;
+
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    Ok(())
+}